@@ -0,0 +1,213 @@
+//! A small SM83 disassembler used by the debugger
+//!
+//! Mnemonics follow the same notation as the `opcode!` definitions in
+//! [crate::gameboy::cpu], immediates are written as `u8`, `u16` and `i8` in
+//! the templates below and substituted when formatting.
+
+#[rustfmt::skip]
+const OPCODES: [&str; 0x100] = [
+	// 0x00
+	"NOP", "LD BC,u16", "LD (BC),A", "INC BC", "INC B", "DEC B", "LD B,u8", "RLCA",
+	"LD (u16),SP", "ADD HL,BC", "LD A,(BC)", "DEC BC", "INC C", "DEC C", "LD C,u8", "RRCA",
+	// 0x10
+	"STOP", "LD DE,u16", "LD (DE),A", "INC DE", "INC D", "DEC D", "LD D,u8", "RLA",
+	"JR i8", "ADD HL,DE", "LD A,(DE)", "DEC DE", "INC E", "DEC E", "LD E,u8", "RRA",
+	// 0x20
+	"JR NZ,i8", "LD HL,u16", "LD (HL+),A", "INC HL", "INC H", "DEC H", "LD H,u8", "DAA",
+	"JR Z,i8", "ADD HL,HL", "LD A,(HL+)", "DEC HL", "INC L", "DEC L", "LD L,u8", "CPL",
+	// 0x30
+	"JR NC,i8", "LD SP,u16", "LD (HL-),A", "INC SP", "INC (HL)", "DEC (HL)", "LD (HL),u8", "SCF",
+	"JR C,i8", "ADD HL,SP", "LD A,(HL-)", "DEC SP", "INC A", "DEC A", "LD A,u8", "CCF",
+	// 0x40
+	"LD B,B", "LD B,C", "LD B,D", "LD B,E", "LD B,H", "LD B,L", "LD B,(HL)", "LD B,A",
+	"LD C,B", "LD C,C", "LD C,D", "LD C,E", "LD C,H", "LD C,L", "LD C,(HL)", "LD C,A",
+	// 0x50
+	"LD D,B", "LD D,C", "LD D,D", "LD D,E", "LD D,H", "LD D,L", "LD D,(HL)", "LD D,A",
+	"LD E,B", "LD E,C", "LD E,D", "LD E,E", "LD E,H", "LD E,L", "LD E,(HL)", "LD E,A",
+	// 0x60
+	"LD H,B", "LD H,C", "LD H,D", "LD H,E", "LD H,H", "LD H,L", "LD H,(HL)", "LD H,A",
+	"LD L,B", "LD L,C", "LD L,D", "LD L,E", "LD L,H", "LD L,L", "LD L,(HL)", "LD L,A",
+	// 0x70
+	"LD (HL),B", "LD (HL),C", "LD (HL),D", "LD (HL),E", "LD (HL),H", "LD (HL),L", "HALT",
+	"LD (HL),A", "LD A,B", "LD A,C", "LD A,D", "LD A,E", "LD A,H", "LD A,L", "LD A,(HL)",
+	"LD A,A",
+	// 0x80
+	"ADD A,B", "ADD A,C", "ADD A,D", "ADD A,E", "ADD A,H", "ADD A,L", "ADD A,(HL)", "ADD A,A",
+	"ADC A,B", "ADC A,C", "ADC A,D", "ADC A,E", "ADC A,H", "ADC A,L", "ADC A,(HL)", "ADC A,A",
+	// 0x90
+	"SUB A,B", "SUB A,C", "SUB A,D", "SUB A,E", "SUB A,H", "SUB A,L", "SUB A,(HL)", "SUB A,A",
+	"SBC A,B", "SBC A,C", "SBC A,D", "SBC A,E", "SBC A,H", "SBC A,L", "SBC A,(HL)", "SBC A,A",
+	// 0xA0
+	"AND A,B", "AND A,C", "AND A,D", "AND A,E", "AND A,H", "AND A,L", "AND A,(HL)", "AND A,A",
+	"XOR A,B", "XOR A,C", "XOR A,D", "XOR A,E", "XOR A,H", "XOR A,L", "XOR A,(HL)", "XOR A,A",
+	// 0xB0
+	"OR A,B", "OR A,C", "OR A,D", "OR A,E", "OR A,H", "OR A,L", "OR A,(HL)", "OR A,A", "CP A,B",
+	"CP A,C", "CP A,D", "CP A,E", "CP A,H", "CP A,L", "CP A,(HL)", "CP A,A",
+	// 0xC0
+	"RET NZ", "POP BC", "JP NZ,u16", "JP u16", "CALL NZ,u16", "PUSH BC", "ADD A,u8", "RST 0x00",
+	"RET Z", "RET", "JP Z,u16", "PREFIX CB", "CALL Z,u16", "CALL u16", "ADC A,u8", "RST 0x08",
+	// 0xD0
+	"RET NC", "POP DE", "JP NC,u16", "INVALID", "CALL NC,u16", "PUSH DE", "SUB A,u8", "RST 0x10",
+	"RET C", "RETI", "JP C,u16", "INVALID", "CALL C,u16", "INVALID", "SBC A,u8", "RST 0x18",
+	// 0xE0
+	"LDH (u8),A", "POP HL", "LDH (C),A", "INVALID", "INVALID", "PUSH HL", "AND A,u8",
+	"RST 0x20", "ADD SP,i8", "JP HL", "LD (u16),A", "INVALID", "INVALID", "INVALID", "XOR A,u8",
+	"RST 0x28",
+	// 0xF0
+	"LDH A,(u8)", "POP AF", "LDH A,(C)", "DI", "INVALID", "PUSH AF", "OR A,u8", "RST 0x30",
+	"LD HL,SP+i8", "LD SP,HL", "LD A,(u16)", "EI", "INVALID", "INVALID", "CP A,u8", "RST 0x38",
+];
+
+const PREFIXED_OPERATIONS: [&str; 8] = ["RLC", "RRC", "RL", "RR", "SLA", "SRA", "SWAP", "SRL"];
+const PREFIXED_REGISTERS: [&str; 8] = ["B", "C", "D", "E", "H", "L", "(HL)", "A"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+	None,
+	/// An 8-bit immediate
+	U8(u8),
+	/// A 16-bit immediate, usually an address
+	U16(u16),
+	/// A signed 8-bit immediate which is relative to the next instruction
+	/// (`JR`), the resolved target is stored alongside it
+	Relative(i8, u16),
+	/// A signed 8-bit immediate which is used as an offset from SP
+	Signed(i8),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction {
+	pub address: u16,
+	pub opcode: u8,
+	pub length: u8,
+	template: String,
+	pub operand: Operand,
+}
+
+impl Instruction {
+	/// The address the instruction refers to, if any. This is the jump target
+	/// for `JP`/`JR`/`CALL`/`RST` and the memory address for loads from
+	/// immediate addresses.
+	pub fn referenced_address(&self) -> Option<u16> {
+		match self.operand {
+			Operand::U16(address) => Some(address),
+			Operand::Relative(_, target) => Some(target),
+			Operand::U8(offset) if self.template.starts_with("LDH") => Some(0xFF00 | offset as u16),
+			Operand::None => match self.opcode {
+				0xC7 | 0xCF | 0xD7 | 0xDF | 0xE7 | 0xEF | 0xF7 | 0xFF => {
+					Some((self.opcode & 0b0011_1000) as u16)
+				}
+				_ => None,
+			},
+			_ => None,
+		}
+	}
+
+	/// Formats the instruction, using `label` to substitute any address
+	/// operand with a symbol name
+	pub fn format_with(&self, label: impl Fn(u16) -> Option<String>) -> String {
+		let address_operand = |address: u16, fallback: String| label(address).unwrap_or(fallback);
+
+		match self.operand {
+			Operand::None => self.template.clone(),
+			Operand::U8(value) => match self.template.starts_with("LDH") {
+				true => self.template.replace(
+					"u8",
+					address_operand(
+						0xFF00 | value as u16,
+						format!("${:04X}", 0xFF00 | value as u16),
+					)
+					.as_str(),
+				),
+				false => self.template.replace("u8", format!("${:02X}", value).as_str()),
+			},
+			Operand::U16(value) => self
+				.template
+				.replace("u16", address_operand(value, format!("${:04X}", value)).as_str()),
+			Operand::Relative(_, target) => self
+				.template
+				.replace("i8", address_operand(target, format!("${:04X}", target)).as_str()),
+			Operand::Signed(value) => match self.template.contains("+i8") {
+				true => self.template.replace("+i8", format!("{:+}", value).as_str()),
+				false => self.template.replace("i8", format!("{}", value).as_str()),
+			},
+		}
+	}
+}
+
+impl std::fmt::Display for Instruction {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str(self.format_with(|_| None).as_str())
+	}
+}
+
+/// Disassembles the instruction at `address`, reading the bytes it is made up
+/// of through `read`
+pub fn disassemble(address: u16, read: impl Fn(u16) -> u8) -> Instruction {
+	let opcode = read(address);
+	let imm_u8 = read(address.wrapping_add(1));
+	let imm_u16 = u16::from_le_bytes([imm_u8, read(address.wrapping_add(2))]);
+
+	if opcode == 0xCB {
+		let prefixed = imm_u8;
+		let register = PREFIXED_REGISTERS[prefixed as usize & 0b111];
+		let template = match prefixed >> 6 {
+			0 => format!("{} {}", PREFIXED_OPERATIONS[(prefixed >> 3) as usize & 0b111], register),
+			1 => format!("BIT {},{}", (prefixed >> 3) & 0b111, register),
+			2 => format!("RES {},{}", (prefixed >> 3) & 0b111, register),
+			3 => format!("SET {},{}", (prefixed >> 3) & 0b111, register),
+			_ => unreachable!(),
+		};
+
+		return Instruction { address, opcode, length: 2, template, operand: Operand::None };
+	}
+
+	let template = OPCODES[opcode as usize];
+
+	let (length, operand) = if template.contains("u16") {
+		(3, Operand::U16(imm_u16))
+	} else if template.contains("u8") {
+		(2, Operand::U8(imm_u8))
+	} else if template.starts_with("JR") {
+		let target = address.wrapping_add(2).wrapping_add_signed(imm_u8 as i8 as i16);
+		(2, Operand::Relative(imm_u8 as i8, target))
+	} else if template.contains("i8") {
+		(2, Operand::Signed(imm_u8 as i8))
+	} else if opcode == 0x10 {
+		// STOP is followed by a byte which is skipped over
+		(2, Operand::None)
+	} else {
+		(1, Operand::None)
+	};
+
+	Instruction { address, opcode, length, template: template.to_string(), operand }
+}
+
+#[test]
+fn test_disassemble() {
+	let rom = [0x00, 0xC3, 0x50, 0x01, 0x18, 0xFE, 0xCB, 0x7C, 0xE0, 0x40, 0xE8, 0xF0];
+	let read = |address: u16| rom.get(address as usize).copied().unwrap_or(0xFF);
+
+	let nop = disassemble(0, read);
+	assert_eq!((nop.length, nop.to_string().as_str()), (1, "NOP"));
+
+	let jp = disassemble(1, read);
+	assert_eq!((jp.length, jp.to_string().as_str()), (3, "JP $0150"));
+	assert_eq!(jp.referenced_address(), Some(0x150));
+	assert_eq!(
+		jp.format_with(|address| (address == 0x150).then(|| String::from("Main"))),
+		"JP Main"
+	);
+
+	let jr = disassemble(4, read);
+	assert_eq!((jr.length, jr.to_string().as_str()), (2, "JR $0004"));
+
+	let bit = disassemble(6, read);
+	assert_eq!((bit.length, bit.to_string().as_str()), (2, "BIT 7,H"));
+
+	let ldh = disassemble(8, read);
+	assert_eq!(ldh.to_string(), "LDH ($FF40),A");
+	assert_eq!(ldh.referenced_address(), Some(0xFF40));
+
+	assert_eq!(disassemble(10, read).to_string(), "ADD SP,-16");
+}
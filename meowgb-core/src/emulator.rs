@@ -0,0 +1,192 @@
+//! A high level interface for embedding the emulator, running it by frames,
+//! M-cycles or instructions without touching the hardware it is made of
+
+use crate::gameboy::{
+	movie::{FrameClock, Movie, MovieError, MoviePlayer, MovieRecorder},
+	serial::LinkDevice,
+	state::StateError,
+	Gameboy,
+};
+
+pub struct Emulator<S: LinkDevice> {
+	gameboy: Gameboy<S>,
+	clock: FrameClock,
+	/// The last frame the PPU finished drawing
+	framebuffer: Vec<u8>,
+}
+
+impl<S: LinkDevice> Emulator<S> {
+	pub fn new(link_device: S, rom: Option<Vec<u8>>) -> Self {
+		Self::from_gameboy(Gameboy::new(link_device, rom))
+	}
+
+	pub fn from_gameboy(gameboy: Gameboy<S>) -> Self {
+		let framebuffer = gameboy.bus.ppu.write_fb();
+		Self { gameboy, clock: FrameClock::default(), framebuffer }
+	}
+
+	/// The emulated hardware, for debuggers and anything else this interface
+	/// does not cover
+	pub fn gameboy(&self) -> &Gameboy<S> {
+		&self.gameboy
+	}
+
+	pub fn gameboy_mut(&mut self) -> &mut Gameboy<S> {
+		&mut self.gameboy
+	}
+
+	pub fn into_gameboy(self) -> Gameboy<S> {
+		self.gameboy
+	}
+
+	/// Runs for an M-cycle, returning whether the PPU finished drawing a frame
+	pub fn tick(&mut self) -> bool {
+		let redraw = self.gameboy.tick_4();
+		self.ticked(redraw);
+		redraw
+	}
+
+	/// Runs for an M-cycle while recording a movie, see
+	/// [`MovieRecorder::tick_4`]
+	pub fn tick_recording(&mut self, recorder: &mut MovieRecorder) -> bool {
+		let redraw = recorder.tick_4(&mut self.gameboy);
+		self.ticked(redraw);
+		redraw
+	}
+
+	/// Runs for an M-cycle while playing a movie back, see
+	/// [`MoviePlayer::tick_4`]
+	pub fn tick_playing(&mut self, player: &mut MoviePlayer) -> Result<bool, MovieError> {
+		let result = player.tick_4(&mut self.gameboy);
+		self.ticked(match &result {
+			Ok(redraw) => *redraw,
+			// Desyncs are only found at the end of a frame
+			Err(_) => true,
+		});
+		result
+	}
+
+	fn ticked(&mut self, redraw: bool) {
+		if redraw {
+			self.framebuffer = self.gameboy.bus.ppu.write_fb();
+		}
+		self.clock.tick(redraw);
+	}
+
+	/// Runs for at least one M-cycle and at most `max_m_cycles`, more than one
+	/// only while the CPU is halted with nothing else to do, when they are all
+	/// skipped at once. Returns how many M-cycles ran and whether the PPU
+	/// finished drawing a frame
+	pub fn advance(&mut self, max_m_cycles: u64) -> (u64, bool) {
+		let skipped = self.gameboy.skip_halted(max_m_cycles.min(self.clock.until_frame_end()));
+		if skipped != 0 {
+			self.clock.skip(skipped);
+			return (skipped, false);
+		}
+
+		(1, self.tick())
+	}
+
+	/// Runs until the end of the current frame, which is when the PPU finishes
+	/// drawing one, or after a frame's worth of M-cycles while the LCD is off.
+	/// Returns whether a frame was drawn
+	pub fn run_frame(&mut self) -> bool {
+		loop {
+			let (_, redraw) = self.advance(u64::MAX);
+			if self.clock.at_frame_start() {
+				return redraw;
+			}
+		}
+	}
+
+	/// Runs for `m_cycles` M-cycles, returning how many frames the PPU
+	/// finished drawing
+	pub fn run_cycles(&mut self, m_cycles: u64) -> usize {
+		let mut ran = 0;
+		let mut frames = 0;
+
+		while ran < m_cycles {
+			let (advanced, redraw) = self.advance(m_cycles - ran);
+			ran += advanced;
+			frames += redraw as usize;
+		}
+
+		frames
+	}
+
+	/// Runs until the CPU is about to start its next instruction or interrupt
+	/// dispatch, returning how many M-cycles that took. While halted this is
+	/// a single M-cycle
+	pub fn step_instruction(&mut self) -> u64 {
+		let mut m_cycles = 0;
+		loop {
+			self.tick();
+			m_cycles += 1;
+
+			let registers = &self.gameboy.registers;
+			if registers.cycle == 0 && registers.current_opcode.is_none() {
+				return m_cycles;
+			}
+		}
+	}
+
+	/// Holds exactly the buttons in `buttons`, a mask of [`Joypad::A`] and
+	/// friends
+	pub fn set_buttons(&mut self, buttons: u8) {
+		self.gameboy.bus.joypad.set_buttons(buttons);
+	}
+
+	pub fn buttons(&self) -> u8 {
+		self.gameboy.bus.joypad.buttons()
+	}
+
+	/// The last frame the PPU finished drawing, as RGBA rows of
+	/// [`FB_WIDTH`](crate::gameboy::ppu::FB_WIDTH) pixels
+	pub fn framebuffer(&self) -> &[u8] {
+		&self.framebuffer
+	}
+
+	/// Takes the stereo samples produced since the last call. The APU is not
+	/// emulated yet, so there are never any
+	pub fn audio_samples(&mut self) -> Vec<[i16; 2]> {
+		Vec::new()
+	}
+
+	/// The cartridge's RAM, for writing battery backed saves to disk
+	pub fn cartridge_ram(&self) -> Option<&[u8]> {
+		self.gameboy.bus.cartridge.as_ref().and_then(|cartridge| cartridge.ram())
+	}
+
+	/// Turns the console off and on again, see [`Gameboy::reset`]
+	pub fn reset(&mut self) {
+		self.gameboy.reset();
+		self.restart_frame();
+	}
+
+	/// See [`Gameboy::save_state`]
+	pub fn save_state(&self) -> Vec<u8> {
+		self.gameboy.save_state()
+	}
+
+	/// Restores a state from [`Emulator::save_state`], starting a new frame
+	/// from it
+	pub fn load_state(&mut self, state: &[u8]) -> Result<(), StateError> {
+		self.gameboy.load_state(state)?;
+		self.restart_frame();
+		Ok(())
+	}
+
+	/// Puts the emulator at the start of `movie` to play it back with
+	/// [`Emulator::tick_playing`], see [`MoviePlayer::new`]
+	pub fn play_movie(&mut self, movie: Movie) -> Result<MoviePlayer, MovieError> {
+		let player = MoviePlayer::new(movie, &mut self.gameboy)?;
+		self.restart_frame();
+		Ok(player)
+	}
+
+	/// Starts the next frame from the current state, after it was replaced
+	fn restart_frame(&mut self) {
+		self.clock = FrameClock::default();
+		self.framebuffer = self.gameboy.bus.ppu.write_fb();
+	}
+}
@@ -1,12 +1,21 @@
+pub mod call_stack;
 pub mod cpu;
 pub mod dma;
 pub mod interrupts;
+pub mod io;
 pub mod joypad;
+pub mod link;
 pub mod mapper;
 pub mod memory;
+pub mod movie;
 pub mod ppu;
+pub mod printer;
+pub mod profile;
+pub mod scheduler;
 pub mod serial;
 pub mod sound;
+pub mod state;
+pub mod test_bus;
 pub mod timer;
 
 use interrupts::Interrupts;
@@ -14,73 +23,60 @@ use joypad::Joypad;
 use mapper::Mapper;
 use memory::Memory;
 use ppu::Ppu;
+use profile::TickProfile;
+use scheduler::Scheduler;
 use timer::Timer;
 
 use self::{
-	cpu::Registers,
+	cpu::{CpuBus, Sm83},
 	dma::DmaState,
 	mapper::{mbc1::MBC1, NoMBC},
-	serial::{Serial, SerialWriter},
+	serial::{LinkDevice, Serial, SerialState},
 	sound::Sound,
 };
-#[cfg(feature = "instr-dbg")]
-use crate::ringbuffer::RingBuffer;
 
 pub type GenericCartridge = dyn Mapper + Send + Sync;
 
-pub struct Gameboy<S: SerialWriter> {
+/// A DMG, the SM83 CPU attached to the rest of the hardware through its memory
+/// bus
+pub type Gameboy<S> = Sm83<GameboyBus<S>>;
+
+/// Everything on the DMG which the CPU accesses through its memory bus
+pub struct GameboyBus<S: LinkDevice> {
 	pub ppu: Ppu,
 	pub memory: Memory,
 	pub cartridge: Option<Box<GenericCartridge>>,
-	pub interrupts: Interrupts,
 	pub timer: Timer,
-	pub registers: Registers,
 	pub joypad: Joypad,
 	pub serial: Serial<S>,
 	pub dma: DmaState,
 	pub sound: Sound,
 
-	pub halt: bool,
-	pub halt_bug: bool,
-	pub used_halt_bug: bool,
-	pub stop: bool,
-
 	pub tick_count: u8,
-
-	pub last_read: Option<(u16, u8)>,
-	pub last_write: Option<(u16, u8)>,
-
-	#[cfg(feature = "instr-dbg")]
-	pub pc_history: RingBuffer<u16, 0x1000>,
+	pub scheduler: Scheduler,
+	/// When set, the time spent ticking each subsystem is added to this
+	pub profile: Option<Box<TickProfile>>,
 }
 
-impl<S: SerialWriter> Gameboy<S> {
-	pub fn new(serial_writer: S, rom: Option<Vec<u8>>) -> Self {
-		Self::new_with_cartridge(serial_writer, rom.map(Self::parse_rom))
+impl<S: LinkDevice> Gameboy<S> {
+	pub fn new(link_device: S, rom: Option<Vec<u8>>) -> Self {
+		Self::new_with_cartridge(link_device, rom.map(Self::parse_rom))
 	}
 
-	pub fn new_with_cartridge(serial_writer: S, cartridge: Option<Box<GenericCartridge>>) -> Self {
-		let mut out = Self {
+	pub fn new_with_cartridge(link_device: S, cartridge: Option<Box<GenericCartridge>>) -> Self {
+		let mut out = Self::with_bus(GameboyBus {
 			memory: Memory::new(),
 			cartridge,
-			interrupts: Interrupts::new(),
 			timer: Timer::new(),
 			joypad: Joypad::new(),
-			serial: Serial::new(serial_writer),
+			serial: Serial::new(link_device),
 			dma: DmaState::new(),
 			ppu: Ppu::new(),
-			registers: Registers::default(),
 			sound: Sound::new(),
-			halt: false,
-			halt_bug: false,
-			used_halt_bug: false,
-			stop: false,
 			tick_count: 0,
-			last_read: None,
-			last_write: None,
-			#[cfg(feature = "instr-dbg")]
-			pc_history: RingBuffer::new(),
-		};
+			scheduler: Scheduler::new(),
+			profile: None,
+		});
 
 		out.run_bootrom();
 		out.registers.set_post_rom();
@@ -88,6 +84,17 @@ impl<S: SerialWriter> Gameboy<S> {
 		out
 	}
 
+	/// Turns the console off and on again, running the boot sequence once more.
+	/// The cartridge keeps its ROM and RAM, and whatever is plugged into the
+	/// link port stays plugged in
+	pub fn reset(&mut self) {
+		self.bus.reset();
+		self.reset_cpu();
+
+		self.run_bootrom();
+		self.registers.set_post_rom();
+	}
+
 	pub fn run_bootrom(&mut self) {
 		macro_rules! push8 {
 			($byte:expr) => {
@@ -250,25 +257,259 @@ impl<S: SerialWriter> Gameboy<S> {
 	}
 
 	pub fn tick(&mut self) -> bool {
-		if self.tick_count == 0 {
-			self.dma.tick_dma(&mut self.ppu, &self.memory, self.cartridge.as_deref());
-			cpu::tick_cpu(self);
-			let redraw_requested = self.ppu.tick(&self.dma, &mut self.interrupts);
-			self.serial.tick(&mut self.interrupts);
-			self.timer.tick(&mut self.interrupts);
-
-			self.tick_count += 1;
+		// Taken out for the duration of the tick so the CPU can borrow all of
+		// `self`
+		match self.bus.profile.take() {
+			Some(mut profile) => {
+				let redraw_requested = self.tick_profiled::<true>(&mut profile);
+				self.bus.profile = Some(profile);
+				redraw_requested
+			}
+			None => self.tick_profiled::<false>(&mut TickProfile::new()),
+		}
+	}
+
+	/// Monomorphised on whether the time is recorded, so ticking without a
+	/// profile costs nothing extra
+	#[inline(always)]
+	fn tick_profiled<const PROFILE: bool>(&mut self, profile: &mut TickProfile) -> bool {
+		let redraw_requested = if self.bus.tick_count == 0 {
+			let bus = &mut self.bus;
+			if bus.dma.is_active() {
+				profile::time::<PROFILE, _>(&mut profile.dma, || {
+					bus.dma.tick_dma(&mut bus.ppu, &bus.memory, bus.cartridge.as_deref())
+				});
+			}
+
+			if bus.joypad.interrupt_triggered {
+				bus.joypad.interrupt_triggered = false;
+				self.interrupts.write_if_joypad(true);
+			}
+
+			profile::time::<PROFILE, _>(&mut profile.cpu, || cpu::tick_cpu(self));
+
+			let (bus, interrupts) = (&mut self.bus, &mut self.interrupts);
+			let redraw_requested =
+				profile::time::<PROFILE, _>(&mut profile.ppu, || bus.tick_ppu(interrupts));
+			profile::time::<PROFILE, _>(&mut profile.serial, || bus.serial.tick(interrupts));
+			profile::time::<PROFILE, _>(&mut profile.timer, || bus.tick_timer(interrupts));
+
+			bus.tick_count += 1;
 			redraw_requested
 		} else {
-			let redraw_requested = self.ppu.tick(&self.dma, &mut self.interrupts);
-			self.timer.tick(&mut self.interrupts);
-			self.tick_count += 1;
-			self.tick_count %= 4;
+			let (bus, interrupts) = (&mut self.bus, &mut self.interrupts);
+			let redraw_requested =
+				profile::time::<PROFILE, _>(&mut profile.ppu, || bus.tick_ppu(interrupts));
+			profile::time::<PROFILE, _>(&mut profile.timer, || bus.tick_timer(interrupts));
+			bus.tick_count += 1;
+			bus.tick_count %= 4;
 			redraw_requested
+		};
+
+		self.bus.scheduler.now += 1;
+		redraw_requested
+	}
+
+	/// While the CPU is halted or stopped and nothing else needs ticking, jumps
+	/// straight to the next point something does, skipping at most
+	/// `max_m_cycles` M-cycles. Returns how many were skipped, which is none
+	/// whenever the next M-cycle could change anything
+	pub fn skip_halted(&mut self, max_m_cycles: u64) -> u64 {
+		let bus = &self.bus;
+		if !(self.halt || self.stop)
+			|| self.registers.cycle != 0
+			|| self.interrupts.pending()
+			|| bus.tick_count != 0
+			|| bus.dma.is_active()
+			|| bus.joypad.interrupt_triggered
+			|| bus.profile.is_some()
+		{
+			return 0;
+		}
+
+		let Some(serial_idle) = bus.serial.idle_m_cycles() else {
+			return 0;
+		};
+		let scheduler = &bus.scheduler;
+		let until_deadline = scheduler.next_deadline().saturating_sub(scheduler.now) / 4;
+		let m_cycles = until_deadline.min(serial_idle).min(max_m_cycles);
+
+		// Everything a halted M-cycle does outside of the lazily ticked
+		// components
+		self.bus.serial.skip(m_cycles);
+		self.bus.scheduler.now += m_cycles * 4;
+		self.cycle_count += m_cycles;
+		if m_cycles != 0 {
+			self.registers.mem_op_happened = false;
+			self.last_read = None;
+			self.last_write = None;
+		}
+
+		m_cycles
+	}
+
+	/// Brings every lazily ticked component up to date, for inspecting their
+	/// state directly rather than through the memory bus
+	pub fn catch_up(&mut self) {
+		self.bus.sync_ppu();
+		self.bus.sync_timer();
+	}
+
+	pub fn dump_memory(&self) -> [u8; 0xFFFF] {
+		let mut out = [0u8; 0xFFFF];
+
+		for address in 0..0xFFFF {
+			out[address as usize] = self.debug_read_u8(address);
+		}
+
+		out
+	}
+
+	/// Warning: This bypasses the memory bus and only exists for
+	/// debugging/testing purposes
+	pub fn debug_read_u8(&self, address: u16) -> u8 {
+		match address {
+			0..=0x7FFF => match self.bus.cartridge.as_ref() {
+				Some(mapper) => mapper.read_rom_u8(address),
+				None => 0xFF,
+			},
+			0x8000..=0x9FFF => self.bus.ppu.cpu_read_vram(address),
+			0xA000..=0xBFFF => match self.bus.cartridge.as_ref() {
+				Some(mapper) => mapper.read_eram_u8(address - 0xA000),
+				None => 0xFF,
+			},
+			0xC000..=0xDFFF => self.bus.memory.wram[address as usize - 0xC000],
+			0xE000..=0xFDFF => self.bus.memory.wram[address as usize - 0xE000],
+			0xFE00..=0xFE9F => self.bus.ppu.cpu_read_oam(address),
+			0xFEA0..=0xFEFF => 0,
+			0xFF04 => self.bus.timer_now().read_div(),
+			0xFF05 => self.bus.timer_now().read_tima(),
+			0xFF06 => self.bus.timer_now().read_tma(),
+			0xFF07 => self.bus.timer_now().read_tac(),
+			0xFF00..=0xFF7F => self.bus.read_io(&self.interrupts, address),
+			0xFF80..=0xFFFE => self.bus.memory.hram[address as usize - 0xFF80],
+			0xFFFF => self.interrupts.interrupt_enable,
+		}
+	}
+
+	/// Warning: This bypasses the memory bus and only exists for
+	/// debugging/testing purposes
+	#[allow(unused)]
+	pub fn debug_write_u8(&mut self, address: u16, value: u8) {
+		self.bus.sync_for(address);
+
+		match address {
+			0..=0x7FFF => {
+				if let Some(mapper) = self.bus.cartridge.as_mut() {
+					mapper.write_rom_u8(address, value)
+				}
+			}
+			0x8000..=0x9FFF => self.bus.ppu.cpu_write_vram(address, value),
+			0xA000..=0xBFFF => {
+				if let Some(mapper) = self.bus.cartridge.as_mut() {
+					mapper.write_eram_u8(address - 0xA000, value)
+				}
+			}
+			0xC000..=0xDFFF => self.bus.memory.wram[address as usize - 0xC000] = value,
+			0xE000..=0xFDFF => self.bus.memory.wram[address as usize - 0xE000] = value,
+			0xFE00..=0xFE9F => self.bus.ppu.cpu_write_oam(address, value),
+			0xFEA0..=0xFEFF => {}
+			0xFF00..=0xFF7F => self.bus.write_io(&mut self.interrupts, address, value),
+			0xFF80..=0xFFFE => self.bus.memory.hram[address as usize - 0xFF80] = value,
+			0xFFFF => self.interrupts.cpu_set_interrupt_enable(value),
+		}
+	}
+
+	/// Returns the bank currently mapped in at `address`, numbered the same way
+	/// as RGBDS symbol files. Only ROM and ERAM are banked on the DMG, every
+	/// other region is always bank 0
+	pub fn bank_at(&self, address: u16) -> u16 {
+		match (address, self.bus.cartridge.as_ref()) {
+			(0..=0x7FFF, Some(mapper)) => mapper.rom_bank(address) as u16,
+			(0..=0x7FFF, None) => address >> 14,
+			(0xA000..=0xBFFF, Some(mapper)) => mapper.eram_bank() as u16,
+			_ => 0,
+		}
+	}
+}
+
+impl<S: LinkDevice> GameboyBus<S> {
+	/// Puts every component back how it is at power on, except for the
+	/// cartridge's RAM
+	fn reset(&mut self) {
+		let scanline_renderer = self.ppu.scanline_renderer;
+		self.ppu = Ppu::new();
+		self.ppu.scanline_renderer = scanline_renderer;
+		self.memory = Memory::new();
+		if let Some(cartridge) = self.cartridge.as_mut() {
+			cartridge.reset();
 		}
+		self.timer = Timer::new();
+		self.joypad = Joypad::new();
+		self.serial.restore(SerialState::default());
+		self.dma = DmaState::new();
+		self.sound = Sound::new();
+		self.tick_count = 0;
+		self.scheduler = Scheduler::new();
+	}
+
+	/// Ticks the PPU if it is due, skipping the dots it spent idle before that
+	fn tick_ppu(&mut self, interrupts: &mut Interrupts) -> bool {
+		let now = self.scheduler.now;
+		if !self.scheduler.ppu.due(now) {
+			return false;
+		}
+
+		self.ppu.skip_idle_dots(self.scheduler.ppu.behind(now));
+		let redraw_requested = self.ppu.tick(&self.dma, interrupts);
+		self.scheduler.ppu.ticked(now, self.ppu.idle_dots(self.dma.is_active()));
+		redraw_requested
 	}
 
-	fn cpu_read_io(&self, address: u16) -> u8 {
+	/// Ticks the timer if it is due, skipping the cycles it spent idle before
+	/// that
+	fn tick_timer(&mut self, interrupts: &mut Interrupts) {
+		let now = self.scheduler.now;
+		if !self.scheduler.timer.due(now) {
+			return;
+		}
+
+		self.timer.skip(self.scheduler.timer.behind(now));
+		self.timer.tick(interrupts);
+		self.scheduler.timer.ticked(now, self.timer.idle_cycles());
+	}
+
+	fn sync_ppu(&mut self) {
+		let now = self.scheduler.now;
+		self.ppu.skip_idle_dots(self.scheduler.ppu.behind(now));
+		self.scheduler.ppu.caught_up(now);
+	}
+
+	fn sync_timer(&mut self) {
+		let now = self.scheduler.now;
+		self.timer.skip(self.scheduler.timer.behind(now));
+		self.scheduler.timer.caught_up(now);
+	}
+
+	/// The timer as it would be if it was caught up, for reading it without
+	/// changing anything
+	pub fn timer_now(&self) -> Timer {
+		let mut timer = self.timer.clone();
+		timer.skip(self.scheduler.timer.behind(self.scheduler.now));
+		timer
+	}
+
+	/// Catches up the lazily ticked component which `address` belongs to,
+	/// before it is accessed
+	fn sync_for(&mut self, address: u16) {
+		match address {
+			0x8000..=0x9FFF | 0xFE00..=0xFEFF | 0xFF40..=0xFF4B => self.sync_ppu(),
+			0xFF04..=0xFF07 => self.sync_timer(),
+			_ => {}
+		}
+	}
+
+	fn read_io(&self, interrupts: &Interrupts, address: u16) -> u8 {
 		match address {
 			0xFF00 => self.joypad.cpu_read(),
 			0xFF01 => self.serial.sb,
@@ -279,7 +520,7 @@ impl<S: SerialWriter> Gameboy<S> {
 			0xFF06 => self.timer.read_tma(),
 			0xFF07 => self.timer.read_tac(),
 			0xFF08..=0xFF0E => 0xFF, // Unused
-			0xFF0F => self.interrupts.interrupt_flag,
+			0xFF0F => interrupts.interrupt_flag,
 			0xFF10 => self.sound.nr10,
 			0xFF11 => self.sound.nr11,
 			0xFF12 => self.sound.nr12,
@@ -330,7 +571,11 @@ impl<S: SerialWriter> Gameboy<S> {
 		}
 	}
 
-	fn cpu_write_io(&mut self, address: u16, value: u8) {
+	fn write_io(&mut self, interrupts: &mut Interrupts, address: u16, value: u8) {
+		if let 0xFF40..=0xFF4B = address {
+			self.ppu.undefer_line();
+		}
+
 		match address {
 			0xFF00 => self.joypad.cpu_write(value),
 			0xFF01 => self.serial.sb = value,
@@ -341,7 +586,7 @@ impl<S: SerialWriter> Gameboy<S> {
 			0xFF06 => self.timer.write_tma(value),
 			0xFF07 => self.timer.write_tac(value),
 			0xFF08..=0xFF0E => {} // Unused
-			0xFF0F => self.interrupts.interrupt_flag = value | !0b1_1111,
+			0xFF0F => interrupts.interrupt_flag = value | !0b1_1111,
 			0xFF10 => {} //self.sound.nr10 = value, - Unwritable on DMG
 			0xFF11 => self.sound.nr11 = value,
 			0xFF12 => self.sound.nr12 = value,
@@ -374,14 +619,14 @@ impl<S: SerialWriter> Gameboy<S> {
 				if value >> 7 == 0 && old_value >> 7 == 1 {
 					self.ppu.stop();
 				} else if value >> 7 == 1 && old_value >> 7 == 0 {
-					self.ppu.start(&mut self.interrupts);
+					self.ppu.start(interrupts);
 				}
 			}
-			0xFF41 => self.ppu.set_stat(&mut self.interrupts, value),
+			0xFF41 => self.ppu.set_stat(interrupts, value),
 			0xFF42 => self.ppu.registers.scy = value,
 			0xFF43 => self.ppu.registers.scx = value,
 			0xFF44 => {} // LY is read only
-			0xFF45 => self.ppu.set_lyc(&mut self.interrupts, value),
+			0xFF45 => self.ppu.set_lyc(interrupts, value),
 			0xFF46 => self.dma.init_request(value),
 			0xFF47 => self.ppu.bgp.write(value),
 			0xFF48 => self.ppu.obp[0].write(value),
@@ -400,83 +645,21 @@ impl<S: SerialWriter> Gameboy<S> {
 			_ => unreachable!("IO Read Invalid"),
 		}
 	}
+}
 
-	pub fn dump_memory(&self) -> [u8; 0xFFFF] {
-		let mut out = [0u8; 0xFFFF];
-
-		for address in 0..0xFFFF {
-			out[address as usize] = self.debug_read_u8(address);
-		}
-
-		out
-	}
-
-	/// Warning: This bypasses the memory bus and only exists for
-	/// debugging/testing purposes
-	pub fn debug_read_u8(&self, address: u16) -> u8 {
-		match address {
-			0..=0x7FFF => match self.cartridge.as_ref() {
-				Some(mapper) => mapper.read_rom_u8(address),
-				None => 0xFF,
-			},
-			0x8000..=0x9FFF => self.ppu.cpu_read_vram(address),
-			0xA000..=0xBFFF => match self.cartridge.as_ref() {
-				Some(mapper) => mapper.read_eram_u8(address - 0xA000),
-				None => 0xFF,
-			},
-			0xC000..=0xDFFF => self.memory.wram[address as usize - 0xC000],
-			0xE000..=0xFDFF => self.memory.wram[address as usize - 0xE000],
-			0xFE00..=0xFE9F => self.ppu.cpu_read_oam(address),
-			0xFEA0..=0xFEFF => 0,
-			0xFF00..=0xFF7F => self.cpu_read_io(address),
-			0xFF80..=0xFFFE => self.memory.hram[address as usize - 0xFF80],
-			0xFFFF => self.interrupts.interrupt_enable,
-		}
-	}
-
-	/// Warning: This bypasses the memory bus and only exists for
-	/// debugging/testing purposes
-	#[allow(unused)]
-	pub fn debug_write_u8(&mut self, address: u16, value: u8) {
-		match address {
-			0..=0x7FFF => {
-				if let Some(mapper) = self.cartridge.as_mut() {
-					mapper.write_rom_u8(address, value)
-				}
-			}
-			0x8000..=0x9FFF => self.ppu.cpu_write_vram(address, value),
-			0xA000..=0xBFFF => {
-				if let Some(mapper) = self.cartridge.as_mut() {
-					mapper.write_eram_u8(address - 0xA000, value)
-				}
-			}
-			0xC000..=0xDFFF => self.memory.wram[address as usize - 0xC000] = value,
-			0xE000..=0xFDFF => self.memory.wram[address as usize - 0xE000] = value,
-			0xFE00..=0xFE9F => self.ppu.cpu_write_oam(address, value),
-			0xFEA0..=0xFEFF => {}
-			0xFF00..=0xFF7F => self.cpu_write_io(address, value),
-			0xFF80..=0xFFFE => self.memory.hram[address as usize - 0xFF80] = value,
-			0xFFFF => self.interrupts.cpu_set_interrupt_enable(value),
-		}
-	}
-
-	pub fn cpu_read_u8(&mut self, address: u16) {
-		self.cpu_read_u8_internal(address, false);
-	}
+impl<S: LinkDevice> CpuBus for GameboyBus<S> {
+	fn read_u8(&mut self, interrupts: &mut Interrupts, address: u16) -> u8 {
+		self.sync_for(address);
 
-	pub fn cpu_read_u8_internal(&mut self, address: u16, is_next_pc: bool) {
-		assert!(!self.registers.mem_op_happened);
-		assert!(self.registers.mem_read_hold.is_none());
-		self.registers.mem_op_happened = true;
-		let value = match self.dma.is_conflict(address) {
+		match self.dma.is_conflict(address) {
 			true => match address {
 				0..=0xFDFF => {
 					self.dma.read_next_byte(&self.ppu, &self.memory, self.cartridge.as_deref())
 				}
 				0xFE00..=0xFEFF => 0xFF,
-				0xFF00..=0xFF7F => self.cpu_read_io(address),
+				0xFF00..=0xFF7F => self.read_io(interrupts, address),
 				0xFF80..=0xFFFE => self.memory.hram[address as usize - 0xFF80],
-				0xFFFF => self.interrupts.interrupt_enable,
+				0xFFFF => interrupts.interrupt_enable,
 			},
 			false => match address {
 				0..=0x7FFF => match self.cartridge.as_ref() {
@@ -492,28 +675,22 @@ impl<S: SerialWriter> Gameboy<S> {
 				0xE000..=0xFDFF => self.memory.wram[address as usize - 0xE000],
 				0xFE00..=0xFE9F => self.ppu.cpu_read_oam(address),
 				0xFEA0..=0xFEFF => 0,
-				0xFF00..=0xFF7F => self.cpu_read_io(address),
+				0xFF00..=0xFF7F => self.read_io(interrupts, address),
 				0xFF80..=0xFFFE => self.memory.hram[address as usize - 0xFF80],
-				0xFFFF => self.interrupts.interrupt_enable,
+				0xFFFF => interrupts.interrupt_enable,
 			},
-		};
-		if !is_next_pc {
-			self.last_read = Some((address, value));
 		}
-		self.registers.mem_read_hold = Some(value);
 	}
 
-	pub fn cpu_write_u8(&mut self, address: u16, value: u8) {
-		assert!(!self.registers.mem_op_happened);
-		self.registers.mem_op_happened = true;
-		self.last_write = Some((address, value));
+	fn write_u8(&mut self, interrupts: &mut Interrupts, address: u16, value: u8) {
+		self.sync_for(address);
 
 		match self.dma.is_conflict(address) {
 			true => match address {
 				0..=0xFEFF => {}
-				0xFF00..=0xFF7F => self.cpu_write_io(address, value),
+				0xFF00..=0xFF7F => self.write_io(interrupts, address, value),
 				0xFF80..=0xFFFE => self.memory.hram[address as usize - 0xFF80] = value,
-				0xFFFF => self.interrupts.cpu_set_interrupt_enable(value),
+				0xFFFF => interrupts.cpu_set_interrupt_enable(value),
 			},
 			false => match address {
 				0..=0x7FFF => {
@@ -531,20 +708,19 @@ impl<S: SerialWriter> Gameboy<S> {
 				0xE000..=0xFDFF => self.memory.wram[address as usize - 0xE000] = value,
 				0xFE00..=0xFE9F => self.ppu.cpu_write_oam(address, value),
 				0xFEA0..=0xFEFF => {}
-				0xFF00..=0xFF7F => self.cpu_write_io(address, value),
+				0xFF00..=0xFF7F => self.write_io(interrupts, address, value),
 				0xFF80..=0xFFFE => self.memory.hram[address as usize - 0xFF80] = value,
-				0xFFFF => self.interrupts.cpu_set_interrupt_enable(value),
+				0xFFFF => interrupts.cpu_set_interrupt_enable(value),
 			},
 		}
 	}
 
-	pub fn cpu_push_stack(&mut self, byte: u8) {
-		self.registers.sp = self.registers.sp.overflowing_sub(1).0;
-		self.cpu_write_u8(self.registers.sp, byte)
+	fn stop_button_held(&self) -> bool {
+		self.joypad.lines() != 0b1111
 	}
 
-	pub fn cpu_pop_stack(&mut self) {
-		self.cpu_read_u8(self.registers.sp);
-		self.registers.sp = self.registers.sp.overflowing_add(1).0;
+	fn stop(&mut self) {
+		self.sync_timer();
+		self.timer.write_div();
 	}
 }
@@ -0,0 +1,116 @@
+//! A shadow call stack and interrupt history kept alongside the CPU for
+//! debugging. Neither affects emulation.
+
+use std::collections::VecDeque;
+
+use super::interrupts::InterruptSource;
+
+/// The deepest the shadow call stack will grow before the outermost frames
+/// are discarded, this keeps code which never returns (e.g. jumping out of a
+/// subroutine after popping its return address) from growing it forever
+pub const MAX_CALL_DEPTH: usize = 0x100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallKind {
+	Call,
+	Rst,
+	/// An interrupt dispatch, `None` if it was cancelled by IE being written
+	/// during the dispatch and PC was set to 0x0000
+	Interrupt(Option<InterruptSource>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CallFrame {
+	pub kind: CallKind,
+	/// The address of the instruction which made the call, or the address of
+	/// the instruction which was interrupted
+	pub origin: u16,
+	pub target: u16,
+	pub return_address: u16,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct CallStack(VecDeque<CallFrame>);
+
+impl CallStack {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn push(&mut self, frame: CallFrame) {
+		if self.0.len() == MAX_CALL_DEPTH {
+			self.0.pop_front();
+		}
+
+		self.0.push_back(frame);
+	}
+
+	/// Pops frames for a return to `address`. If a frame further down the stack
+	/// would return there then every frame above it is discarded as well,
+	/// otherwise only the innermost frame is popped
+	pub fn ret(&mut self, address: u16) {
+		match self.0.iter().rposition(|frame| frame.return_address == address) {
+			Some(idx) => self.0.truncate(idx),
+			None => {
+				self.0.pop_back();
+			}
+		}
+	}
+
+	pub fn clear(&mut self) {
+		self.0.clear();
+	}
+
+	pub fn len(&self) -> usize {
+		self.0.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.0.is_empty()
+	}
+
+	/// Iterates over the frames from the outermost to the innermost
+	pub fn frames(&self) -> impl DoubleEndedIterator<Item = &CallFrame> {
+		self.0.iter()
+	}
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct InterruptRecord {
+	/// `None` if the dispatch was cancelled
+	pub source: Option<InterruptSource>,
+	pub vector: u16,
+	/// The PC the interrupt was dispatched from
+	pub origin: u16,
+	/// The M-cycle the dispatch completed on
+	pub cycle: u64,
+}
+
+impl std::fmt::Display for CallFrame {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self.kind {
+			CallKind::Call => f.write_fmt(format_args!("CALL {:#06X}", self.target))?,
+			CallKind::Rst => f.write_fmt(format_args!("RST {:#04X}", self.target))?,
+			CallKind::Interrupt(Some(source)) => {
+				f.write_fmt(format_args!("{:?} interrupt {:#04X}", source, self.target))?
+			}
+			CallKind::Interrupt(None) => f.write_str("Cancelled interrupt 0x0000")?,
+		}
+
+		f.write_fmt(format_args!(" from {:#06X}", self.origin))
+	}
+}
+
+impl std::fmt::Display for InterruptRecord {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self.source {
+			Some(source) => f.write_fmt(format_args!("{:?}", source))?,
+			None => f.write_str("Cancelled")?,
+		}
+
+		f.write_fmt(format_args!(
+			" -> {:#06X} from {:#06X} at M-cycle {}",
+			self.vector, self.origin, self.cycle
+		))
+	}
+}
@@ -4,7 +4,13 @@ mod load_store_move;
 mod misc;
 mod prefixed;
 
-use super::{serial::SerialWriter, Gameboy};
+use serde::{Deserialize, Serialize};
+
+use super::{
+	call_stack::{CallFrame, CallKind, CallStack, InterruptRecord},
+	interrupts::{InterruptSource, Interrupts},
+};
+use crate::ringbuffer::RingBuffer;
 
 macro_rules! define_register {
 	($lident:ident, $rident:ident) => {
@@ -43,7 +49,7 @@ pub enum CycleResult {
 	FinishedKeepPc,
 }
 
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
 pub struct Registers {
 	pub a: u8,
 	pub f: u8,
@@ -137,16 +143,181 @@ impl Registers {
 	}
 }
 
-pub fn tick_cpu(state: &mut Gameboy<impl SerialWriter>) {
+/// Everything the CPU can reach through its memory bus. The Game Boy's memory
+/// map is one implementation, tests and embedders can provide their own
+pub trait CpuBus {
+	/// Reads the byte at `address`, as seen by the CPU
+	fn read_u8(&mut self, interrupts: &mut Interrupts, address: u16) -> u8;
+
+	/// Writes `value` to `address`, as done by the CPU
+	fn write_u8(&mut self, interrupts: &mut Interrupts, address: u16, value: u8);
+
+	/// Whether any joypad line is low, which makes STOP behave like HALT
+	fn stop_button_held(&self) -> bool {
+		false
+	}
+
+	/// Called when STOP is executed, which resets DIV on a Game Boy
+	fn stop(&mut self) {}
+}
+
+/// The SM83 CPU, connected to its memory bus
+pub struct Sm83<B> {
+	pub registers: Registers,
+	pub interrupts: Interrupts,
+	pub bus: B,
+
+	pub halt: bool,
+	pub halt_bug: bool,
+	pub used_halt_bug: bool,
+	pub stop: bool,
+	/// Set when `LD B,B` is executed, which test ROMs use as a software
+	/// breakpoint. It is never cleared by the emulator itself
+	pub software_breakpoint: bool,
+
+	/// M-cycles elapsed since power on
+	pub cycle_count: u64,
+
+	pub last_read: Option<(u16, u8)>,
+	pub last_write: Option<(u16, u8)>,
+
+	pub call_stack: CallStack,
+	pub interrupt_history: RingBuffer<InterruptRecord, 0x40>,
+
+	#[cfg(feature = "instr-dbg")]
+	pub pc_history: RingBuffer<u16, 0x1000>,
+}
+
+impl<B> Sm83<B> {
+	/// A CPU with all registers cleared, attached to `bus`
+	pub fn with_bus(bus: B) -> Self {
+		Self {
+			registers: Registers::default(),
+			interrupts: Interrupts::new(),
+			bus,
+			halt: false,
+			halt_bug: false,
+			used_halt_bug: false,
+			stop: false,
+			software_breakpoint: false,
+			cycle_count: 0,
+			last_read: None,
+			last_write: None,
+			call_stack: CallStack::new(),
+			interrupt_history: RingBuffer::new(),
+			#[cfg(feature = "instr-dbg")]
+			pc_history: RingBuffer::new(),
+		}
+	}
+
+	/// Clears the registers and everything recorded about the code run so far,
+	/// leaving the bus alone
+	pub fn reset_cpu(&mut self) {
+		self.registers = Registers::default();
+		self.interrupts = Interrupts::new();
+		self.halt = false;
+		self.halt_bug = false;
+		self.used_halt_bug = false;
+		self.stop = false;
+		self.software_breakpoint = false;
+		self.cycle_count = 0;
+		self.last_read = None;
+		self.last_write = None;
+		self.call_stack = CallStack::new();
+		self.interrupt_history = RingBuffer::new();
+		#[cfg(feature = "instr-dbg")]
+		{
+			self.pc_history = RingBuffer::new();
+		}
+	}
+
+	/// Describes the CPU state, call stack and recent interrupts, for including
+	/// in crash reports
+	pub fn crash_report(&self) -> String {
+		let mut out = format!(
+			"PC: {:#06X} SP: {:#06X} AF: {:#06X} BC: {:#06X} DE: {:#06X} HL: {:#06X}\nM-cycle: {}\n",
+			self.registers.pc,
+			self.registers.sp,
+			self.registers.get_af(),
+			self.registers.get_bc(),
+			self.registers.get_de(),
+			self.registers.get_hl(),
+			self.cycle_count
+		);
+
+		out.push_str("Call stack (innermost first):\n");
+		for frame in self.call_stack.frames().rev() {
+			out.push_str(format!("\t{}\n", frame).as_str());
+		}
+
+		out.push_str("Recent interrupts (newest last):\n");
+		for record in self.interrupt_history.to_vec() {
+			out.push_str(format!("\t{}\n", record).as_str());
+		}
+
+		out
+	}
+}
+
+impl<B: CpuBus> Sm83<B> {
+	pub fn cpu_read_u8(&mut self, address: u16) {
+		self.cpu_read_u8_internal(address, false);
+	}
+
+	pub fn cpu_read_u8_internal(&mut self, address: u16, is_next_pc: bool) {
+		assert!(!self.registers.mem_op_happened);
+		assert!(self.registers.mem_read_hold.is_none());
+		self.registers.mem_op_happened = true;
+		let value = self.bus.read_u8(&mut self.interrupts, address);
+		if !is_next_pc {
+			self.last_read = Some((address, value));
+		}
+		self.registers.mem_read_hold = Some(value);
+	}
+
+	pub fn cpu_write_u8(&mut self, address: u16, value: u8) {
+		assert!(!self.registers.mem_op_happened);
+		self.registers.mem_op_happened = true;
+		self.last_write = Some((address, value));
+		self.bus.write_u8(&mut self.interrupts, address, value);
+	}
+
+	pub fn cpu_push_stack(&mut self, byte: u8) {
+		self.registers.sp = self.registers.sp.overflowing_sub(1).0;
+		self.cpu_write_u8(self.registers.sp, byte)
+	}
+
+	pub fn cpu_pop_stack(&mut self) {
+		self.cpu_read_u8(self.registers.sp);
+		self.registers.sp = self.registers.sp.overflowing_add(1).0;
+	}
+}
+
+fn track_call_stack(state: &mut Sm83<impl CpuBus>, opcode: u8, origin: u16) {
+	let (kind, length) = match opcode {
+		0xCD | 0xC4 | 0xCC | 0xD4 | 0xDC => (CallKind::Call, 3),
+		0xC7 | 0xCF | 0xD7 | 0xDF | 0xE7 | 0xEF | 0xF7 | 0xFF => (CallKind::Rst, 1),
+		0xC9 | 0xD9 | 0xC0 | 0xC8 | 0xD0 | 0xD8 => {
+			state.call_stack.ret(state.registers.pc);
+			return;
+		}
+		_ => return,
+	};
+
+	state.call_stack.push(CallFrame {
+		kind,
+		origin,
+		target: state.registers.pc,
+		return_address: origin.wrapping_add(length),
+	});
+}
+
+pub fn tick_cpu(state: &mut Sm83<impl CpuBus>) {
+	state.cycle_count += 1;
 	state.registers.mem_op_happened = false;
 	state.last_read = None;
 	state.last_write = None;
 
-	if state.joypad.interrupt_triggered {
-		state.joypad.interrupt_triggered = false;
-		state.interrupts.write_if_joypad(true);
-	}
-
 	if state.registers.cycle == 0 && (state.halt || state.stop) {
 		if (state.interrupts.read_ie_vblank() && state.interrupts.read_if_vblank())
 			|| (state.interrupts.read_ie_lcd_stat() && state.interrupts.read_if_lcd_stat())
@@ -202,39 +373,32 @@ pub fn tick_cpu(state: &mut Gameboy<impl SerialWriter>) {
 			}
 			4 => {
 				let original_pc = state.registers.pc;
-				let idx;
+				let source;
 
 				if state.interrupts.read_ie_vblank() && state.interrupts.read_if_vblank() {
-					idx = 0;
+					source = Some(InterruptSource::VBlank);
 					state.interrupts.write_if_vblank(false);
 				} else if state.interrupts.read_ie_lcd_stat() && state.interrupts.read_if_lcd_stat()
 				{
-					idx = 1;
+					source = Some(InterruptSource::LcdStat);
 					state.interrupts.write_if_lcd_stat(false);
 				} else if state.interrupts.read_ie_timer() && state.interrupts.read_if_timer() {
-					idx = 2;
+					source = Some(InterruptSource::Timer);
 					state.interrupts.write_if_timer(false);
 				} else if state.interrupts.read_ie_serial() && state.interrupts.read_if_serial() {
-					idx = 3;
+					source = Some(InterruptSource::Serial);
 					state.interrupts.write_if_serial(false);
 				} else if state.interrupts.read_ie_joypad() && state.interrupts.read_if_joypad() {
-					idx = 4;
+					source = Some(InterruptSource::Joypad);
 					state.interrupts.write_if_joypad(false);
 				} else {
-					idx = 5;
+					source = None;
 					println!("IRQ disabled!");
 				}
 
-				// assert_eq!(vector, idx);
-
-				state.registers.pc = match idx {
-					0 => 0x40,
-					1 => 0x48,
-					2 => 0x50,
-					3 => 0x58,
-					4 => 0x60,
-					5 => 0x00,
-					_ => unreachable!(),
+				state.registers.pc = match source {
+					Some(source) => source.vector(),
+					None => 0x00,
 				};
 				state.registers.in_interrupt_vector = false;
 				log::debug!(
@@ -242,6 +406,18 @@ pub fn tick_cpu(state: &mut Gameboy<impl SerialWriter>) {
 					state.registers.pc,
 					original_pc
 				);
+				state.call_stack.push(CallFrame {
+					kind: CallKind::Interrupt(source),
+					origin: original_pc,
+					target: state.registers.pc,
+					return_address: original_pc,
+				});
+				state.interrupt_history.push(InterruptRecord {
+					source,
+					vector: state.registers.pc,
+					origin: original_pc,
+					cycle: state.cycle_count,
+				});
 				CycleResult::FinishedKeepPc
 			}
 			_ => unreachable!(),
@@ -261,6 +437,7 @@ pub fn tick_cpu(state: &mut Gameboy<impl SerialWriter>) {
 			},
 		};
 
+		let origin_pc = state.registers.pc;
 		let result: CycleResult = match opcode {
 			0x00 => misc::nop,
 			0x01 => load_store_move::ld_bc_imm_u16,
@@ -528,6 +705,16 @@ pub fn tick_cpu(state: &mut Gameboy<impl SerialWriter>) {
 			panic!("Forgot to set opcode len for {:#X}", opcode)
 		}
 
+		// Only taken calls and returns keep PC, so not taken conditional ones are
+		// never tracked
+		if result == CycleResult::FinishedKeepPc {
+			track_call_stack(state, opcode, origin_pc);
+		}
+
+		if opcode == 0x40 && result == CycleResult::Finished {
+			state.software_breakpoint = true;
+		}
+
 		result
 	};
 
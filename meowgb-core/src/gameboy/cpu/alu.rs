@@ -1,7 +1,6 @@
 use meowgb_opcode::opcode;
 
-use super::CycleResult;
-use crate::gameboy::Gameboy;
+use super::{CycleResult, Sm83};
 
 #[derive(Debug)]
 pub struct CarryResult {
@@ -1,7 +1,6 @@
 use meowgb_opcode::opcode;
 
-use super::CycleResult;
-use crate::gameboy::Gameboy;
+use super::{CycleResult, Sm83};
 
 opcode!(jr_nz_i8, 0x20, "JR NZ,i8", false, 2, {
 	0 => {
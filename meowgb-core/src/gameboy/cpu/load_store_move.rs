@@ -1,6 +1,6 @@
 use meowgb_opcode::opcode;
 
-use crate::gameboy::{cpu::CycleResult, Gameboy};
+use crate::gameboy::cpu::{CycleResult, Sm83};
 
 macro_rules! define_ld_reg_imm_u16 {
 	($op:literal, $reg:ident) => {
@@ -1,7 +1,6 @@
 use meowgb_opcode::opcode;
 
-use super::CycleResult;
-use crate::gameboy::Gameboy;
+use super::{CycleResult, Sm83};
 
 opcode!(nop, 0x00, "NOP", false, 1, {
 	0 => {
@@ -39,7 +38,7 @@ opcode!(stop, 0x10, "STOP", false, 1, {
 		CycleResult::NeedsMore
 	},
 	1 => {
-		let button_held = state.joypad.cpu_read() & 0b1111 != 0;
+		let button_held = state.bus.stop_button_held();
 		let interrupt_pending = state.interrupts.interrupt_enable & state.interrupts.interrupt_flag != 0;
 
 		match button_held {
@@ -56,12 +55,12 @@ opcode!(stop, 0x10, "STOP", false, 1, {
 				true => {
 					state.registers.pc = state.registers.pc.wrapping_add(1);
 					state.stop = true;
-					state.timer.write_div();
+					state.bus.stop();
 				},
 				false => {
 					state.registers.pc = state.registers.pc.wrapping_add(2);
 					state.stop = true;
-					state.timer.write_div();
+					state.bus.stop();
 				}
 			},
 		}
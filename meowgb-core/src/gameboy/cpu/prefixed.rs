@@ -1,9 +1,8 @@
 use meowgb_opcode::opcode;
 
-use super::CycleResult;
-use crate::gameboy::{serial::SerialWriter, Gameboy};
+use super::{CpuBus, CycleResult, Sm83};
 
-pub fn prefixed_handler(state: &mut Gameboy<impl SerialWriter>) -> CycleResult {
+pub fn prefixed_handler(state: &mut Sm83<impl CpuBus>) -> CycleResult {
 	let opcode = match state.registers.current_prefixed_opcode {
 		Some(prefixed_opcode) => prefixed_opcode,
 		None => match state.registers.mem_read_hold.take() {
@@ -1,3 +1,5 @@
+use serde::{Deserialize, Serialize};
+
 use super::{memory::Memory, ppu::Ppu, GenericCartridge};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -33,7 +35,7 @@ impl DmaMemoryBus {
 	}
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct DmaState {
 	original_base: u8,
 	pub dma_in_progress: Option<u16>,
@@ -54,6 +56,11 @@ impl DmaState {
 		}
 	}
 
+	/// Whether `tick_dma` has anything to do, it can be skipped otherwise
+	pub fn is_active(&self) -> bool {
+		self.restarting.is_some() || self.remaining_cycles > 0 || self.dma_in_progress.is_some()
+	}
+
 	pub fn new() -> Self {
 		Self {
 			dma_in_progress: None,
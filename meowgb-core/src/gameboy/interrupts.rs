@@ -1,3 +1,5 @@
+use serde::{Deserialize, Serialize};
+
 macro_rules! define_bitfield_u8_gs {
 	($name:ident, $offset:literal, $loc:ident) => {
 		paste::paste! {
@@ -15,6 +17,29 @@ macro_rules! define_bitfield_u8_gs {
 	};
 }
 
+/// The five interrupt sources, in order of priority
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterruptSource {
+	VBlank,
+	LcdStat,
+	Timer,
+	Serial,
+	Joypad,
+}
+
+impl InterruptSource {
+	pub fn vector(&self) -> u16 {
+		match self {
+			InterruptSource::VBlank => 0x40,
+			InterruptSource::LcdStat => 0x48,
+			InterruptSource::Timer => 0x50,
+			InterruptSource::Serial => 0x58,
+			InterruptSource::Joypad => 0x60,
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize)]
 pub struct Interrupts {
 	pub ime: bool,
 	pub ei_queued: bool,
@@ -45,6 +70,12 @@ impl Interrupts {
 	define_bitfield_u8_gs!(if_serial, 3, interrupt_flag);
 	define_bitfield_u8_gs!(if_joypad, 4, interrupt_flag);
 
+	/// Whether an enabled interrupt has been requested, which wakes the CPU
+	/// from HALT
+	pub fn pending(&self) -> bool {
+		self.interrupt_enable & self.interrupt_flag & 0b1_1111 != 0
+	}
+
 	pub fn cpu_set_ime(&mut self, val: bool) {
 		self.ei_queued = val;
 		if !val {
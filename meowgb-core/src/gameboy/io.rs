@@ -0,0 +1,268 @@
+//! Names and bitfield layouts of the memory mapped IO registers, for use by
+//! debugging tools
+
+/// Returns the conventional (Pan Docs) name of the IO register at `address`,
+/// or `None` if there is no register mapped there on the DMG
+pub fn register_name(address: u16) -> Option<&'static str> {
+	Some(match address {
+		0xFF00 => "JOYP",
+		0xFF01 => "SB",
+		0xFF02 => "SC",
+		0xFF04 => "DIV",
+		0xFF05 => "TIMA",
+		0xFF06 => "TMA",
+		0xFF07 => "TAC",
+		0xFF0F => "IF",
+		0xFF10 => "NR10",
+		0xFF11 => "NR11",
+		0xFF12 => "NR12",
+		0xFF13 => "NR13",
+		0xFF14 => "NR14",
+		0xFF16 => "NR21",
+		0xFF17 => "NR22",
+		0xFF18 => "NR23",
+		0xFF19 => "NR24",
+		0xFF1A => "NR30",
+		0xFF1B => "NR31",
+		0xFF1C => "NR32",
+		0xFF1D => "NR33",
+		0xFF1E => "NR34",
+		0xFF20 => "NR41",
+		0xFF21 => "NR42",
+		0xFF22 => "NR43",
+		0xFF23 => "NR44",
+		0xFF24 => "NR50",
+		0xFF25 => "NR51",
+		0xFF26 => "NR52",
+		0xFF30..=0xFF3F => "WAVE",
+		0xFF40 => "LCDC",
+		0xFF41 => "STAT",
+		0xFF42 => "SCY",
+		0xFF43 => "SCX",
+		0xFF44 => "LY",
+		0xFF45 => "LYC",
+		0xFF46 => "DMA",
+		0xFF47 => "BGP",
+		0xFF48 => "OBP0",
+		0xFF49 => "OBP1",
+		0xFF4A => "WY",
+		0xFF4B => "WX",
+		0xFF50 => "BOOT",
+		0xFFFF => "IE",
+		_ => return None,
+	})
+}
+
+/// A named group of bits within an IO register
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Field {
+	pub name: &'static str,
+	/// The lowest bit of the field
+	pub shift: u8,
+	/// How many bits the field spans
+	pub width: u8,
+	/// Names for each value of the field, empty if the value is a plain number
+	pub values: &'static [&'static str],
+}
+
+impl Field {
+	const fn new(name: &'static str, shift: u8, width: u8) -> Self {
+		Self { name, shift, width, values: &[] }
+	}
+
+	const fn flag(name: &'static str, shift: u8) -> Self {
+		Self::new(name, shift, 1)
+	}
+
+	const fn named(
+		name: &'static str,
+		shift: u8,
+		width: u8,
+		values: &'static [&'static str],
+	) -> Self {
+		Self { name, shift, width, values }
+	}
+
+	/// The largest value the field can hold
+	pub fn max(&self) -> u8 {
+		((1u16 << self.width) - 1) as u8
+	}
+
+	/// Extracts the field from a register value
+	pub fn get(&self, register: u8) -> u8 {
+		(register >> self.shift) & self.max()
+	}
+
+	/// Returns `register` with the field replaced by `value`
+	pub fn set(&self, register: u8, value: u8) -> u8 {
+		(register & !(self.max() << self.shift)) | ((value & self.max()) << self.shift)
+	}
+
+	/// Formats the field's value in `register`, using its name if it has one
+	pub fn describe(&self, register: u8) -> String {
+		let value = self.get(register);
+		match self.values.get(value as usize) {
+			Some(name) => String::from(*name),
+			None => format!("{}", value),
+		}
+	}
+}
+
+const INTERRUPT_FIELDS: &[Field] = &[
+	Field::flag("VBlank", 0),
+	Field::flag("LCD STAT", 1),
+	Field::flag("Timer", 2),
+	Field::flag("Serial", 3),
+	Field::flag("Joypad", 4),
+];
+
+const PALETTE_FIELDS: &[Field] = &[
+	Field::new("ID 0", 0, 2),
+	Field::new("ID 1", 2, 2),
+	Field::new("ID 2", 4, 2),
+	Field::new("ID 3", 6, 2),
+];
+
+const DUTY_LENGTH_FIELDS: &[Field] = &[
+	Field::new("Initial length timer", 0, 6),
+	Field::named("Wave duty", 6, 2, &["12.5%", "25%", "50%", "75%"]),
+];
+
+const ENVELOPE_FIELDS: &[Field] = &[
+	Field::new("Sweep pace", 0, 3),
+	Field::named("Envelope direction", 3, 1, &["Decrease", "Increase"]),
+	Field::new("Initial volume", 4, 4),
+];
+
+const PERIOD_HIGH_CONTROL_FIELDS: &[Field] =
+	&[Field::new("Period high", 0, 3), Field::flag("Length enable", 6), Field::flag("Trigger", 7)];
+
+const PERIOD_LOW_FIELDS: &[Field] = &[Field::new("Period low", 0, 8)];
+
+const JOYP_FIELDS: &[Field] = &[
+	Field::new("Inputs (0 = pressed)", 0, 4),
+	Field::named("Direction select", 4, 1, &["Selected", "Not selected"]),
+	Field::named("Action select", 5, 1, &["Selected", "Not selected"]),
+];
+
+const SC_FIELDS: &[Field] = &[
+	Field::named("Clock select", 0, 1, &["External", "Internal"]),
+	Field::flag("Transfer enable", 7),
+];
+
+const TAC_FIELDS: &[Field] = &[
+	Field::named("Clock select", 0, 2, &["4096 Hz", "262144 Hz", "65536 Hz", "16384 Hz"]),
+	Field::flag("Enable", 2),
+];
+
+const NR10_FIELDS: &[Field] = &[
+	Field::new("Sweep step", 0, 3),
+	Field::named("Sweep direction", 3, 1, &["Increase", "Decrease"]),
+	Field::new("Sweep pace", 4, 3),
+];
+
+const NR30_FIELDS: &[Field] = &[Field::flag("DAC enable", 7)];
+
+const NR31_FIELDS: &[Field] = &[Field::new("Initial length timer", 0, 8)];
+
+const NR32_FIELDS: &[Field] =
+	&[Field::named("Output level", 5, 2, &["Mute", "100%", "50%", "25%"])];
+
+const NR41_FIELDS: &[Field] = &[Field::new("Initial length timer", 0, 6)];
+
+const NR43_FIELDS: &[Field] = &[
+	Field::new("Clock divider", 0, 3),
+	Field::named("LFSR width", 3, 1, &["15 bit", "7 bit"]),
+	Field::new("Clock shift", 4, 4),
+];
+
+const NR44_FIELDS: &[Field] = &[Field::flag("Length enable", 6), Field::flag("Trigger", 7)];
+
+const NR50_FIELDS: &[Field] = &[
+	Field::new("Right volume", 0, 3),
+	Field::flag("VIN right", 3),
+	Field::new("Left volume", 4, 3),
+	Field::flag("VIN left", 7),
+];
+
+const NR51_FIELDS: &[Field] = &[
+	Field::flag("CH1 right", 0),
+	Field::flag("CH2 right", 1),
+	Field::flag("CH3 right", 2),
+	Field::flag("CH4 right", 3),
+	Field::flag("CH1 left", 4),
+	Field::flag("CH2 left", 5),
+	Field::flag("CH3 left", 6),
+	Field::flag("CH4 left", 7),
+];
+
+const NR52_FIELDS: &[Field] = &[
+	Field::flag("CH1 on", 0),
+	Field::flag("CH2 on", 1),
+	Field::flag("CH3 on", 2),
+	Field::flag("CH4 on", 3),
+	Field::flag("Audio enable", 7),
+];
+
+const LCDC_FIELDS: &[Field] = &[
+	Field::flag("BG & Window enable", 0),
+	Field::flag("OBJ enable", 1),
+	Field::named("OBJ size", 2, 1, &["8x8", "8x16"]),
+	Field::named("BG tile map", 3, 1, &["9800", "9C00"]),
+	Field::named("BG & Window tiles", 4, 1, &["8800", "8000"]),
+	Field::flag("Window enable", 5),
+	Field::named("Window tile map", 6, 1, &["9800", "9C00"]),
+	Field::flag("LCD enable", 7),
+];
+
+const STAT_FIELDS: &[Field] = &[
+	Field::named("Mode", 0, 2, &["HBlank", "VBlank", "OAM Scan", "Drawing"]),
+	Field::flag("LY = LYC", 2),
+	Field::flag("Mode 0 interrupt", 3),
+	Field::flag("Mode 1 interrupt", 4),
+	Field::flag("Mode 2 interrupt", 5),
+	Field::flag("LYC interrupt", 6),
+];
+
+/// Returns the bitfields making up the IO register at `address`, registers
+/// which are a single value have no fields
+pub fn register_fields(address: u16) -> &'static [Field] {
+	match address {
+		0xFF00 => JOYP_FIELDS,
+		0xFF02 => SC_FIELDS,
+		0xFF07 => TAC_FIELDS,
+		0xFF0F | 0xFFFF => INTERRUPT_FIELDS,
+		0xFF10 => NR10_FIELDS,
+		0xFF11 | 0xFF16 => DUTY_LENGTH_FIELDS,
+		0xFF12 | 0xFF17 | 0xFF21 => ENVELOPE_FIELDS,
+		0xFF13 | 0xFF18 | 0xFF1D => PERIOD_LOW_FIELDS,
+		0xFF14 | 0xFF19 | 0xFF1E => PERIOD_HIGH_CONTROL_FIELDS,
+		0xFF1A => NR30_FIELDS,
+		0xFF1B => NR31_FIELDS,
+		0xFF1C => NR32_FIELDS,
+		0xFF20 => NR41_FIELDS,
+		0xFF22 => NR43_FIELDS,
+		0xFF23 => NR44_FIELDS,
+		0xFF24 => NR50_FIELDS,
+		0xFF25 => NR51_FIELDS,
+		0xFF26 => NR52_FIELDS,
+		0xFF40 => LCDC_FIELDS,
+		0xFF41 => STAT_FIELDS,
+		0xFF47..=0xFF49 => PALETTE_FIELDS,
+		_ => &[],
+	}
+}
+
+#[test]
+fn test_register_fields() {
+	let tac = register_fields(0xFF07);
+	assert_eq!(tac[0].describe(0b1111_1101), "262144 Hz");
+	assert_eq!(tac[1].get(0b1111_1101), 1);
+	assert_eq!(tac[0].set(0b1111_1101, 3), 0b1111_1111);
+
+	let stat = register_fields(0xFF41);
+	assert_eq!(stat[0].describe(0b1000_0011), "Drawing");
+	assert_eq!(stat[5].set(0b1000_0011, 1), 0b1100_0011);
+
+	assert!(register_fields(0xFF42).is_empty());
+}
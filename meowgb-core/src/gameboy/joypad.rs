@@ -1,97 +1,112 @@
-#[derive(Debug, PartialEq, Eq)]
-pub enum JoypadMode {
-	Action,
-	Direction,
-	Both,
-}
-
-macro_rules! joypad_input {
-	($input:ident, $mode:ident) => {
-		paste::paste! {
-			pub fn [<set_ $input>](&mut self, val: bool) {
-				if val && (self.mode == JoypadMode::$mode || self.mode == JoypadMode::Both) {
-					self.interrupt_triggered = true;
-				}
-				self.$input = val;
-			}
-
-			pub fn [<invert_ $input>](&mut self) {
-				let val = !self.$input;
-				if val && (self.mode == JoypadMode::$mode || self.mode == JoypadMode::Both) {
-					self.interrupt_triggered = true;
-				}
-				self.$input = val;
-			}
-		}
-	};
-}
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Joypad {
-	mode: JoypadMode,
-	pub down: bool,
-	pub up: bool,
-	pub left: bool,
-	pub right: bool,
-	pub start: bool,
-	pub select: bool,
-	pub b: bool,
-	pub a: bool,
+	/// Mask of [`Joypad::A`] and friends for the buttons currently held
+	buttons: u8,
+	/// Bits 4 and 5 of P1, a line group is selected while its bit is 0
+	select: u8,
 	pub interrupt_triggered: bool,
 }
 
 impl Joypad {
+	// Bits of the mask returned by [`Joypad::buttons`]
+	pub const A: u8 = 1 << 0;
+	pub const B: u8 = 1 << 1;
+	pub const SELECT: u8 = 1 << 2;
+	pub const START: u8 = 1 << 3;
+	pub const RIGHT: u8 = 1 << 4;
+	pub const LEFT: u8 = 1 << 5;
+	pub const UP: u8 = 1 << 6;
+	pub const DOWN: u8 = 1 << 7;
+
+	const SELECT_DIRECTION: u8 = 1 << 4;
+	const SELECT_ACTION: u8 = 1 << 5;
+
 	pub fn new() -> Self {
-		Self {
-			mode: JoypadMode::Both,
-			down: false,
-			up: false,
-			left: false,
-			right: false,
-			start: false,
-			select: false,
-			b: false,
-			a: false,
-			interrupt_triggered: false,
+		Self { buttons: 0, select: 0, interrupt_triggered: false }
+	}
+
+	/// The state of the 4 input lines, a line is low while a button on it is
+	/// held in a selected group
+	pub fn lines(&self) -> u8 {
+		let mut held = 0;
+		if self.select & Self::SELECT_ACTION == 0 {
+			held |= self.buttons & 0b1111;
+		}
+		if self.select & Self::SELECT_DIRECTION == 0 {
+			held |= self.buttons >> 4;
 		}
+		!held & 0b1111
 	}
 
 	pub fn cpu_read(&self) -> u8 {
-		(0b11 << 6)
-			| match self.mode {
-				JoypadMode::Action => {
-					(1 << 4)
-						| ((!self.start as u8) << 3)
-						| ((!self.select as u8) << 2)
-						| ((!self.b as u8) << 1) | (!self.a as u8)
-				}
-				JoypadMode::Direction => {
-					(1 << 5)
-						| ((!self.down as u8) << 3)
-						| ((!self.up as u8) << 2)
-						| ((!self.left as u8) << 1)
-						| (!self.right as u8)
-				}
-				JoypadMode::Both => 0b1111,
-			}
+		(0b11 << 6) | self.select | self.lines()
 	}
 
-	joypad_input!(a, Action);
-	joypad_input!(b, Action);
-	joypad_input!(start, Action);
-	joypad_input!(select, Action);
-	joypad_input!(up, Direction);
-	joypad_input!(down, Direction);
-	joypad_input!(left, Direction);
-	joypad_input!(right, Direction);
-
 	pub fn cpu_write(&mut self, content: u8) {
-		if (content >> 5) & 0b1 == 0 && (content >> 4) & 0b1 == 1 {
-			self.mode = JoypadMode::Action;
-		} else if (content >> 4) & 0b1 == 0 && (content >> 5) & 0b1 == 1 {
-			self.mode = JoypadMode::Direction;
-		} else {
-			self.mode = JoypadMode::Both;
+		self.update(|joypad| {
+			joypad.select = content & (Self::SELECT_ACTION | Self::SELECT_DIRECTION)
+		});
+	}
+
+	/// The buttons currently held, as a mask of [`Joypad::A`] and friends
+	pub fn buttons(&self) -> u8 {
+		self.buttons
+	}
+
+	/// Holds exactly the buttons in `mask`, releasing every other button
+	pub fn set_buttons(&mut self, mask: u8) {
+		self.update(|joypad| joypad.buttons = mask);
+	}
+
+	/// Applies `change`, requesting the joypad interrupt if it pulled any
+	/// line from high to low
+	fn update(&mut self, change: impl FnOnce(&mut Self)) {
+		let before = self.lines();
+		change(self);
+		if before & !self.lines() != 0 {
+			self.interrupt_triggered = true;
 		}
 	}
 }
+
+#[test]
+fn test_read_selected_lines() {
+	let mut joypad = Joypad::new();
+	assert_eq!(joypad.cpu_read(), 0xCF);
+
+	joypad.set_buttons(Joypad::A | Joypad::DOWN);
+	assert_eq!(joypad.cpu_read(), 0xC6);
+	joypad.cpu_write(0x10);
+	assert_eq!(joypad.cpu_read(), 0xDE);
+	joypad.cpu_write(0x20);
+	assert_eq!(joypad.cpu_read(), 0xE7);
+	joypad.cpu_write(0x30);
+	assert_eq!(joypad.cpu_read(), 0xFF);
+}
+
+#[test]
+fn test_interrupt_on_falling_edge() {
+	let mut joypad = Joypad::new();
+	joypad.cpu_write(0x20);
+
+	// Action buttons are not selected, so their lines stay high
+	joypad.set_buttons(Joypad::A);
+	assert!(!joypad.interrupt_triggered);
+
+	joypad.set_buttons(Joypad::A | Joypad::RIGHT);
+	assert!(joypad.interrupt_triggered);
+	joypad.interrupt_triggered = false;
+
+	// Holding the same buttons or releasing one is no edge
+	joypad.set_buttons(Joypad::A | Joypad::RIGHT);
+	joypad.set_buttons(Joypad::A);
+	assert!(!joypad.interrupt_triggered);
+
+	// Selecting the action buttons pulls A's line low
+	joypad.set_buttons(Joypad::A);
+	joypad.interrupt_triggered = false;
+	joypad.cpu_write(0x00);
+	assert!(joypad.interrupt_triggered);
+}
@@ -1,5 +1,7 @@
 pub mod mbc1;
 
+use super::state::StateError;
+
 pub trait Mapper {
 	fn read_rom_u8(&self, address: u16) -> u8;
 	fn write_rom_u8(&mut self, address: u16, value: u8);
@@ -8,6 +10,22 @@ pub trait Mapper {
 	fn read_eram_u8(&self, address: u16) -> u8;
 	/// The address passed into this function MUST be zero indexed
 	fn write_eram_u8(&mut self, address: u16, value: u8);
+
+	/// The ROM bank currently mapped in at `address` (0x0000-0x7FFF)
+	fn rom_bank(&self, address: u16) -> usize;
+	/// The ERAM bank currently mapped in at 0xA000-0xBFFF
+	fn eram_bank(&self) -> usize;
+
+	/// The whole ROM, used to tell which game a save state belongs to
+	fn rom(&self) -> &[u8];
+	/// The whole cartridge RAM, `None` when the cartridge has none
+	fn ram(&self) -> Option<&[u8]>;
+	/// The RAM and mapper registers, for save states
+	fn save_state(&self) -> Vec<u8>;
+	/// Restores a state from [`Mapper::save_state`]
+	fn load_state(&mut self, state: &[u8]) -> Result<(), StateError>;
+	/// Puts the mapper registers back how they are at power on, keeping the RAM
+	fn reset(&mut self);
 }
 
 pub struct NoMBC {
@@ -52,4 +70,38 @@ impl Mapper for NoMBC {
 			ram[address as usize] = value;
 		}
 	}
+
+	fn rom_bank(&self, address: u16) -> usize {
+		(address >> 14) as usize
+	}
+
+	fn eram_bank(&self) -> usize {
+		0
+	}
+
+	fn rom(&self) -> &[u8] {
+		&self.rom
+	}
+
+	fn ram(&self) -> Option<&[u8]> {
+		self.ram.as_ref().map(|ram| ram.as_slice())
+	}
+
+	fn save_state(&self) -> Vec<u8> {
+		bincode::serialize(&self.ram.as_ref().map(|ram| ram.as_slice())).unwrap()
+	}
+
+	fn load_state(&mut self, state: &[u8]) -> Result<(), StateError> {
+		let saved: Option<Vec<u8>> = bincode::deserialize(state)?;
+
+		match (&mut self.ram, saved) {
+			(Some(ram), Some(saved)) if saved.len() == ram.len() => ram.copy_from_slice(&saved),
+			(None, None) => {}
+			_ => return Err(StateError::Cartridge),
+		}
+
+		Ok(())
+	}
+
+	fn reset(&mut self) {}
 }
@@ -1,4 +1,7 @@
-use super::Mapper;
+use super::{Mapper, StateError};
+
+/// Everything which changes while the game runs, as kept in save states
+type Mbc1State = (Option<Vec<u8>>, bool, u8, u8, bool);
 
 pub struct MBC1 {
 	rom: Vec<u8>,
@@ -75,7 +78,6 @@ impl MBC1 {
 		self.rom_bank_count >= 5
 	}
 
-	#[allow(unused)]
 	fn is_large_ram(&self) -> bool {
 		self.ram_bank_count >= 4
 	}
@@ -83,31 +85,7 @@ impl MBC1 {
 
 impl Mapper for MBC1 {
 	fn read_rom_u8(&self, address: u16) -> u8 {
-		let mask = match self.rom_bank_count {
-			0 => 0b1,
-			1 => 0b11,
-			2 => 0b111,
-			3 => 0b1111,
-			4 => 0b11111,
-			5 => 0b111111,
-			6 => 0b1111111,
-			_ => unreachable!(),
-		};
-		let rom_bank = match address <= 0x3FFF {
-			true if self.banking_mode_select && self.is_mbc1m => {
-				(self.extra_2_bit_reg << 4) as usize
-			}
-			true if self.is_large_rom() && self.banking_mode_select && !self.is_mbc1m => {
-				((self.extra_2_bit_reg as usize) << 5) & mask
-			}
-			true => 0,
-			false if self.is_mbc1m => {
-				((self.rom_bank_number & 0b1111) | (self.extra_2_bit_reg << 4)) as usize
-			}
-			false => (self.rom_bank_number | (self.extra_2_bit_reg << 5)) as usize,
-		} & mask;
-
-		let real_address = rom_bank << 14 | (address as usize & 0x3FFF);
+		let real_address = self.rom_bank(address) << 14 | (address as usize & 0x3FFF);
 
 		self.rom[real_address]
 	}
@@ -153,4 +131,81 @@ impl Mapper for MBC1 {
 			None => {}
 		}
 	}
+
+	fn rom_bank(&self, address: u16) -> usize {
+		let mask = match self.rom_bank_count {
+			0 => 0b1,
+			1 => 0b11,
+			2 => 0b111,
+			3 => 0b1111,
+			4 => 0b11111,
+			5 => 0b111111,
+			6 => 0b1111111,
+			_ => unreachable!(),
+		};
+
+		(match address <= 0x3FFF {
+			true if self.banking_mode_select && self.is_mbc1m => {
+				(self.extra_2_bit_reg << 4) as usize
+			}
+			true if self.is_large_rom() && self.banking_mode_select && !self.is_mbc1m => {
+				((self.extra_2_bit_reg as usize) << 5) & mask
+			}
+			true => 0,
+			false if self.is_mbc1m => {
+				((self.rom_bank_number & 0b1111) | (self.extra_2_bit_reg << 4)) as usize
+			}
+			false => (self.rom_bank_number | (self.extra_2_bit_reg << 5)) as usize,
+		}) & mask
+	}
+
+	fn eram_bank(&self) -> usize {
+		match self.is_large_ram() && self.banking_mode_select {
+			true => self.extra_2_bit_reg as usize,
+			false => 0,
+		}
+	}
+
+	fn rom(&self) -> &[u8] {
+		&self.rom
+	}
+
+	fn ram(&self) -> Option<&[u8]> {
+		self.ram.as_deref()
+	}
+
+	fn save_state(&self) -> Vec<u8> {
+		bincode::serialize(&(
+			&self.ram,
+			self.ram_enabled,
+			self.rom_bank_number,
+			self.extra_2_bit_reg,
+			self.banking_mode_select,
+		))
+		.unwrap()
+	}
+
+	fn load_state(&mut self, state: &[u8]) -> Result<(), StateError> {
+		let (ram, ram_enabled, rom_bank_number, extra_2_bit_reg, banking_mode_select): Mbc1State =
+			bincode::deserialize(state)?;
+
+		if ram.as_ref().map(Vec::len) != self.ram.as_ref().map(Vec::len) {
+			return Err(StateError::Cartridge);
+		}
+
+		self.ram = ram;
+		self.ram_enabled = ram_enabled;
+		self.rom_bank_number = rom_bank_number;
+		self.extra_2_bit_reg = extra_2_bit_reg;
+		self.banking_mode_select = banking_mode_select;
+
+		Ok(())
+	}
+
+	fn reset(&mut self) {
+		self.ram_enabled = false;
+		self.rom_bank_number = 1;
+		self.extra_2_bit_reg = 0;
+		self.banking_mode_select = false;
+	}
 }
@@ -1,5 +1,11 @@
+use serde::{Deserialize, Serialize};
+use serde_big_array::BigArray;
+
+#[derive(Serialize, Deserialize)]
 pub struct Memory {
+	#[serde(with = "BigArray")]
 	pub wram: [u8; 0x2000],
+	#[serde(with = "BigArray")]
 	pub hram: [u8; 0xAF],
 }
 
@@ -0,0 +1,275 @@
+//! Input movies, the joypad state for every frame from a known start along
+//! with a hash of every frame, so playing one back can tell exactly which
+//! frame stopped matching the recording
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::{serial::LinkDevice, state::StateError, Gameboy};
+
+const MAGIC: &[u8; 8] = b"MEOWMOVI";
+const VERSION: u8 = 1;
+
+/// M-cycles in one frame while the LCD is on
+pub const M_CYCLES_PER_FRAME: u64 = 17_556;
+
+#[derive(Debug, thiserror::Error)]
+pub enum MovieError {
+	#[error("Movie IO error: {0}")]
+	Io(#[from] std::io::Error),
+	#[error("Not a movie")]
+	Magic,
+	#[error("Movie version {0} is not supported")]
+	Version(u8),
+	#[error("Corrupt movie: {0}")]
+	Decode(#[from] bincode::Error),
+	#[error("Movie was recorded with a different game")]
+	WrongGame,
+	#[error("Movie starts at power on but the emulator has already run")]
+	NotAtPowerOn,
+	#[error("Error loading the movie's save state: {0}")]
+	State(#[from] StateError),
+	#[error("Desync on frame {frame}, expected hash {expected:#010X} but found {found:#010X}")]
+	Desync { frame: usize, expected: u32, found: u32 },
+}
+
+/// Splits emulation into frames, each ending when the PPU requests a redraw,
+/// or after a frame's worth of M-cycles without one while the LCD is off
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FrameClock {
+	m_cycles: u64,
+}
+
+impl FrameClock {
+	/// Whether the next M-cycle is the first of a frame
+	pub fn at_frame_start(&self) -> bool {
+		self.m_cycles == 0
+	}
+
+	/// Counts an M-cycle, returning whether it ended the frame
+	pub fn tick(&mut self, redraw: bool) -> bool {
+		self.m_cycles += 1;
+
+		match redraw || self.m_cycles >= M_CYCLES_PER_FRAME {
+			true => {
+				self.m_cycles = 0;
+				true
+			}
+			false => false,
+		}
+	}
+
+	/// How many M-cycles can pass before the next one might end the frame
+	pub fn until_frame_end(&self) -> u64 {
+		M_CYCLES_PER_FRAME - 1 - self.m_cycles
+	}
+
+	/// Counts `m_cycles` M-cycles in which nothing was drawn, which must be no
+	/// more than [`FrameClock::until_frame_end`]
+	pub fn skip(&mut self, m_cycles: u64) {
+		self.m_cycles += m_cycles;
+	}
+}
+
+/// Hash of the framebuffer, CPU registers and RAM, checked at the end of every
+/// frame during playback
+pub fn frame_hash<S: LinkDevice>(gameboy: &Gameboy<S>) -> u32 {
+	let registers = &gameboy.registers;
+
+	let mut hasher = crc32fast::Hasher::new();
+	hasher.update(&gameboy.bus.ppu.write_fb());
+	hasher.update(&gameboy.bus.memory.wram);
+	hasher.update(&gameboy.bus.memory.hram);
+	hasher.update(&[
+		registers.a,
+		registers.f,
+		registers.b,
+		registers.c,
+		registers.d,
+		registers.e,
+		registers.h,
+		registers.l,
+	]);
+	hasher.update(&registers.sp.to_le_bytes());
+	hasher.update(&registers.pc.to_le_bytes());
+	hasher.finalize()
+}
+
+/// Where playback of a movie begins
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MovieStart {
+	PowerOn,
+	/// A state from [`Gameboy::save_state`]
+	SaveState(#[serde(with = "serde_bytes")] Vec<u8>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MovieFrame {
+	/// The buttons held for the whole frame, as a
+	/// [`Joypad::buttons`](super::joypad::Joypad::buttons) mask
+	pub buttons: u8,
+	/// [`frame_hash`] at the end of the frame
+	pub hash: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Movie {
+	/// [`Gameboy::rom_checksum`] of the game it was recorded with
+	pub rom_checksum: u32,
+	pub start: MovieStart,
+	pub frames: Vec<MovieFrame>,
+}
+
+impl Movie {
+	pub fn encode(&self) -> Vec<u8> {
+		let mut out = MAGIC.to_vec();
+		out.push(VERSION);
+		bincode::serialize_into(&mut out, self).expect("serializing to a Vec cannot fail");
+		out
+	}
+
+	pub fn decode(bytes: &[u8]) -> Result<Self, MovieError> {
+		let Some(body) = bytes.strip_prefix(MAGIC.as_slice()) else {
+			return Err(MovieError::Magic);
+		};
+
+		match body.split_first() {
+			Some((&VERSION, body)) => Ok(bincode::deserialize(body)?),
+			Some((version, _)) => Err(MovieError::Version(*version)),
+			None => Err(MovieError::Magic),
+		}
+	}
+
+	pub fn read(path: &Path) -> Result<Self, MovieError> {
+		Self::decode(&std::fs::read(path)?)
+	}
+
+	pub fn write(&self, path: &Path) -> Result<(), MovieError> {
+		Ok(std::fs::write(path, self.encode())?)
+	}
+}
+
+/// Records the buttons held at the start of each frame. They must not change
+/// anywhere else in the frame, so input should only be applied while
+/// [`MovieRecorder::at_frame_start`]
+pub struct MovieRecorder {
+	movie: Movie,
+	clock: FrameClock,
+	buttons: u8,
+}
+
+impl MovieRecorder {
+	/// Starts recording from the emulator's current state, which is embedded
+	/// in the movie unless the emulator has only just been powered on
+	pub fn new<S: LinkDevice>(gameboy: &Gameboy<S>) -> Self {
+		let start = match gameboy.cycle_count {
+			0 => MovieStart::PowerOn,
+			_ => MovieStart::SaveState(gameboy.save_state()),
+		};
+
+		Self {
+			movie: Movie { rom_checksum: gameboy.rom_checksum(), start, frames: Vec::new() },
+			clock: FrameClock::default(),
+			buttons: 0,
+		}
+	}
+
+	pub fn at_frame_start(&self) -> bool {
+		self.clock.at_frame_start()
+	}
+
+	/// How many frames have been recorded
+	pub fn frames(&self) -> usize {
+		self.movie.frames.len()
+	}
+
+	/// Ticks the emulator for an M-cycle, returning whether it requested a
+	/// redraw
+	pub fn tick_4<S: LinkDevice>(&mut self, gameboy: &mut Gameboy<S>) -> bool {
+		if self.clock.at_frame_start() {
+			self.buttons = gameboy.bus.joypad.buttons();
+		}
+
+		let redraw = gameboy.tick_4();
+
+		if self.clock.tick(redraw) {
+			self.movie.frames.push(MovieFrame { buttons: self.buttons, hash: frame_hash(gameboy) });
+		}
+
+		redraw
+	}
+
+	/// The movie recorded so far, leaving out a frame which has not finished
+	pub fn finish(self) -> Movie {
+		self.movie
+	}
+}
+
+/// Plays a movie back, holding the recorded buttons and checking the hash at
+/// the end of every frame
+pub struct MoviePlayer {
+	movie: Movie,
+	clock: FrameClock,
+	frame: usize,
+}
+
+impl MoviePlayer {
+	/// Puts the emulator at the start of `movie`. A movie starting at power on
+	/// needs an emulator which has not been ticked yet
+	pub fn new<S: LinkDevice>(movie: Movie, gameboy: &mut Gameboy<S>) -> Result<Self, MovieError> {
+		if movie.rom_checksum != gameboy.rom_checksum() {
+			return Err(MovieError::WrongGame);
+		}
+
+		match &movie.start {
+			MovieStart::PowerOn if gameboy.cycle_count != 0 => {
+				return Err(MovieError::NotAtPowerOn)
+			}
+			MovieStart::PowerOn => {}
+			MovieStart::SaveState(state) => gameboy.load_state(state)?,
+		}
+
+		Ok(Self { movie, clock: FrameClock::default(), frame: 0 })
+	}
+
+	/// The frame being played, counting from 0
+	pub fn frame(&self) -> usize {
+		self.frame
+	}
+
+	pub fn frames(&self) -> usize {
+		self.movie.frames.len()
+	}
+
+	pub fn finished(&self) -> bool {
+		self.frame >= self.movie.frames.len()
+	}
+
+	/// Ticks the emulator for an M-cycle, returning whether it requested a
+	/// redraw. Once the movie has finished the joypad is left alone
+	pub fn tick_4<S: LinkDevice>(&mut self, gameboy: &mut Gameboy<S>) -> Result<bool, MovieError> {
+		let recorded = self.movie.frames.get(self.frame).copied();
+
+		if let (true, Some(recorded)) = (self.clock.at_frame_start(), recorded) {
+			gameboy.bus.joypad.set_buttons(recorded.buttons);
+		}
+
+		let redraw = gameboy.tick_4();
+
+		if let (true, Some(recorded)) = (self.clock.tick(redraw), recorded) {
+			let found = frame_hash(gameboy);
+			if found != recorded.hash {
+				return Err(MovieError::Desync {
+					frame: self.frame,
+					expected: recorded.hash,
+					found,
+				});
+			}
+
+			self.frame += 1;
+		}
+
+		Ok(redraw)
+	}
+}
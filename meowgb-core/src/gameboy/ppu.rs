@@ -1,3 +1,6 @@
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+use serde_big_array::BigArray;
+
 use super::{dma::DmaState, interrupts::Interrupts};
 
 pub const FB_HEIGHT: u32 = 144;
@@ -8,7 +11,14 @@ pub const PIXEL_SIZE: usize = 4; // RGBA
 /// currently occuring which is blocking access to the memory bus.
 const OVERRIDE_PPU_MEMORY_ACCESS: bool = false;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Number of tiles stored in VRAM
+pub const TILE_COUNT: usize = 384;
+/// Width in tiles of the buffer produced by [Ppu::render_tiles]
+pub const TILE_SHEET_WIDTH: usize = 16;
+/// Width and height in pixels of a tile map
+pub const TILE_MAP_SIZE: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Palette {
 	id3: Color,
 	id2: Color,
@@ -28,7 +38,7 @@ impl Palette {
 	}
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 enum LineDrawingState {
 	/// (Cycles left, SCX, SCY)
 	BackgroundScrolling(usize, u8, u8),
@@ -64,7 +74,9 @@ impl Palette {
 	}
 }
 
-pub struct WrappedBuffer<const SIZE: usize>([u8; SIZE]);
+/// Boxed as framebuffers are too large to move around on the stack, which
+/// loading a save state would otherwise do
+pub struct WrappedBuffer<const SIZE: usize>(Box<[u8; SIZE]>);
 
 impl<const SIZE: usize> std::ops::Index<usize> for WrappedBuffer<SIZE> {
 	type Output = u8;
@@ -82,11 +94,29 @@ impl<const SIZE: usize> std::ops::IndexMut<usize> for WrappedBuffer<SIZE> {
 
 impl<const SIZE: usize> WrappedBuffer<SIZE> {
 	pub fn empty() -> Self {
-		Self([0; SIZE])
+		Self(vec![0; SIZE].into_boxed_slice().try_into().unwrap())
 	}
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+impl<const SIZE: usize> Serialize for WrappedBuffer<SIZE> {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serde_bytes::Bytes::new(self.0.as_slice()).serialize(serializer)
+	}
+}
+
+impl<'de, const SIZE: usize> Deserialize<'de> for WrappedBuffer<SIZE> {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let bytes = serde_bytes::ByteBuf::deserialize(deserializer)?.into_vec();
+		let length = bytes.len();
+
+		match bytes.into_boxed_slice().try_into() {
+			Ok(buffer) => Ok(Self(buffer)),
+			Err(_) => Err(D::Error::invalid_length(length, &"a whole framebuffer")),
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PPUMode {
 	/// Mode 0
 	HBlank = 0,
@@ -98,7 +128,7 @@ pub enum PPUMode {
 	TransferringData,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Color {
 	White,
 	LGray,
@@ -173,7 +203,25 @@ impl Color {
 	}
 }
 
-#[derive(Debug, Clone, Copy)]
+/// One of the two 32x32 tile maps in VRAM
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TileMap {
+	/// The tile map at 0x9800
+	Low,
+	/// The tile map at 0x9C00
+	High,
+}
+
+impl TileMap {
+	pub fn base_address(&self) -> u16 {
+		match self {
+			TileMap::Low => 0x9800,
+			TileMap::High => 0x9C00,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct OAMEntry {
 	pub y: u8,
 	pub x: u8,
@@ -220,7 +268,7 @@ impl PPUMode {
 	}
 }
 
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
 pub struct StatFlags {
 	pub lyc_int: bool,
 	pub mode2_int: bool,
@@ -244,7 +292,7 @@ impl StatFlags {
 	}
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct PpuRegisters {
 	pub lcdc: u8,
 	pub stat_flags: StatFlags,
@@ -263,9 +311,12 @@ pub struct PpuRegisters {
 	pub ly_lyc: bool,
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct Ppu {
 	pub registers: PpuRegisters,
+	#[serde(with = "BigArray")]
 	pub vram: [u8; 0x2000],
+	#[serde(with = "BigArray")]
 	pub oam: [u8; 0xA0],
 
 	pub bgp: Palette,
@@ -274,6 +325,11 @@ pub struct Ppu {
 	pub framebuffer: WrappedBuffer<{ FB_WIDTH as usize * FB_HEIGHT as usize * PIXEL_SIZE }>,
 	pub sprite_framebuffer: WrappedBuffer<{ FB_WIDTH as usize * FB_HEIGHT as usize * PIXEL_SIZE }>,
 
+	/// Render each line in one go at the end of mode 3 rather than a pixel per
+	/// dot, falling back to the latter for lines where a PPU register is
+	/// written during mode 3
+	pub scanline_renderer: bool,
+
 	// Internals
 	current_dot: u16,
 	dot_target: u16,
@@ -292,6 +348,12 @@ pub struct Ppu {
 
 	is_irq_high: bool,
 	window_counter: usize,
+
+	/// The dot mode 3 will end on, when the current line is being rendered by
+	/// the scanline renderer
+	deferred_line_end: Option<u16>,
+	/// The sprites drawn on the current line, collected when mode 3 starts
+	line_sprites: Vec<OAMEntry>,
 }
 
 impl Ppu {
@@ -314,6 +376,7 @@ impl Ppu {
 		self.first_line = true;
 		self.total_dots = 0;
 		self.window_counter = 0;
+		self.deferred_line_end = None;
 	}
 
 	pub fn start(&mut self, interrupts: &mut Interrupts) {
@@ -343,6 +406,7 @@ impl Ppu {
 			oam: [0; 0xA0],
 			framebuffer: WrappedBuffer::empty(),
 			sprite_framebuffer: WrappedBuffer::empty(),
+			scanline_renderer: false,
 			bgp: Palette::new_bgp(),
 			obp: [Palette::new_obp(), Palette::new_obp()],
 
@@ -358,6 +422,8 @@ impl Ppu {
 			first_line: true,
 			is_irq_high: false,
 			window_counter: 0,
+			deferred_line_end: None,
+			line_sprites: Vec::with_capacity(10),
 		}
 	}
 
@@ -451,8 +517,8 @@ impl Ppu {
 		((high & 0b1) << 1) | low & 0b1
 	}
 
-	fn internal_read_oam(&mut self, dma_state: &DmaState, offset: usize) -> u8 {
-		match dma_state.in_progress().is_some() && !OVERRIDE_PPU_MEMORY_ACCESS {
+	fn internal_read_oam(&self, dma_in_progress: bool, offset: usize) -> u8 {
+		match dma_in_progress && !OVERRIDE_PPU_MEMORY_ACCESS {
 			true => 0xFF,
 			false => self.oam[offset as usize],
 		}
@@ -542,6 +608,11 @@ impl Ppu {
 			}
 			self.current_draw_state = None;
 			self.dot_target = 160 + 12;
+			self.collect_line_sprites();
+
+			if self.scanline_renderer {
+				self.deferred_line_end = Some(self.dot_target + self.predicted_extra_dots());
+			}
 		}
 
 		self.registers.mode = mode;
@@ -577,6 +648,98 @@ impl Ppu {
 		self.registers.ly_lyc = self.registers.ly == self.registers.lyc;
 	}
 
+	/// Checks the OAM entry mode 2 reads on `dot` against the current line,
+	/// which reads as 0xFF while a DMA transfer is in progress
+	fn search_oam(&mut self, dot: u16, dma_in_progress: bool) {
+		if !self.first_frame && dot % 2 == 0 {
+			let oam_item_idx: usize = (dot as usize / 2) * 4;
+
+			let oam_entry = OAMEntry::parse([
+				self.internal_read_oam(dma_in_progress, oam_item_idx),
+				self.internal_read_oam(dma_in_progress, oam_item_idx + 1),
+				self.internal_read_oam(dma_in_progress, oam_item_idx + 2),
+				self.internal_read_oam(dma_in_progress, oam_item_idx + 3),
+			]);
+
+			let sprite_height = self.sprite_height();
+
+			let real_oam_y = oam_entry.y.wrapping_sub(16).wrapping_add(sprite_height as u8);
+
+			if oam_entry.x > 0
+				&& self.registers.ly < real_oam_y
+				&& self.registers.ly >= oam_entry.y.wrapping_sub(16)
+				&& self.sprite_count < 10
+			{
+				self.sprite_buffer[self.sprite_count] = Some(oam_entry);
+				self.sprite_count += 1;
+			}
+		}
+	}
+
+	/// How many ticks from now only advance the dot counters, which
+	/// [`Ppu::skip_idle_dots`] can apply in one go. HBlank and VBlank are idle,
+	/// as is mode 3 when the line is left to the scanline renderer. Mode 2 is
+	/// too after its first dot, with the OAM search done as the dots are
+	/// skipped, unless a DMA transfer is running which changes what it reads
+	pub fn idle_dots(&self, dma_active: bool) -> u64 {
+		if !self.enabled() {
+			return u64::MAX;
+		}
+
+		if self.last_mode.is_some() {
+			return 0;
+		}
+
+		match self.mode() {
+			PPUMode::HBlank => {
+				let target = match self.first_line && self.dot_target == 0 {
+					true => 76,
+					false => self.dot_target,
+				};
+
+				match target {
+					0 => 0,
+					target => target.saturating_sub(self.current_dot + 1) as u64,
+				}
+			}
+			PPUMode::VBlank => 455 - (self.current_dot % 456) as u64,
+			PPUMode::TransferringData => match self.deferred_line_end {
+				Some(end) => end.saturating_sub(self.current_dot + 1) as u64,
+				None => 0,
+			},
+			PPUMode::SearchingOAM if dma_active || self.current_dot == 0 => 0,
+			PPUMode::SearchingOAM => 79u16.saturating_sub(self.current_dot) as u64,
+		}
+	}
+
+	/// Applies `dots` ticks at once, which must be no more than
+	/// [`Ppu::idle_dots`]
+	pub fn skip_idle_dots(&mut self, dots: u64) {
+		if !self.enabled() || dots == 0 {
+			return;
+		}
+
+		let mode = self.mode();
+		if mode == PPUMode::SearchingOAM {
+			for dot in self.current_dot..self.current_dot + dots as u16 {
+				self.search_oam(dot, false);
+			}
+		}
+
+		self.registers.cycles_since_last_ly_increment += dots;
+		self.registers.cycles_since_stat_mode_0 += dots;
+		self.registers.cycles_since_stat_mode_2 += dots;
+		self.registers.cycles_since_stat_mode_3 += dots;
+		self.registers.cycles_since_last_last_mode_start_increment[mode.mode_flag() as usize] +=
+			dots;
+		self.current_dot += dots as u16;
+
+		match mode {
+			PPUMode::VBlank => self.window_counter = 0,
+			_ => self.total_dots += dots as u16,
+		}
+	}
+
 	pub fn tick(&mut self, dma_state: &DmaState, interrupts: &mut Interrupts) -> bool {
 		if self.enabled() {
 			self.registers.cycles_since_last_ly_increment += 1;
@@ -600,30 +763,7 @@ impl Ppu {
 						self.sprite_count = 0;
 					}
 
-					if !self.first_frame && self.current_dot % 2 == 0 {
-						let oam_item_idx: usize = (self.current_dot as usize / 2) * 4;
-
-						let oam_entry = OAMEntry::parse([
-							self.internal_read_oam(dma_state, oam_item_idx),
-							self.internal_read_oam(dma_state, oam_item_idx + 1),
-							self.internal_read_oam(dma_state, oam_item_idx + 2),
-							self.internal_read_oam(dma_state, oam_item_idx + 3),
-						]);
-
-						let sprite_height = self.sprite_height();
-
-						let real_oam_y =
-							oam_entry.y.wrapping_sub(16).wrapping_add(sprite_height as u8);
-
-						if oam_entry.x > 0
-							&& self.registers.ly < real_oam_y
-							&& self.registers.ly >= oam_entry.y.wrapping_sub(16)
-							&& self.sprite_count < 10
-						{
-							self.sprite_buffer[self.sprite_count] = Some(oam_entry);
-							self.sprite_count += 1;
-						}
-					}
+					self.search_oam(self.current_dot, dma_state.in_progress().is_some());
 
 					self.current_dot += 1;
 					self.total_dots += 1;
@@ -644,7 +784,7 @@ impl Ppu {
 					}
 					// assert!(self.current_dot < self.dot_target);
 
-					if self.current_dot >= 12 {
+					if self.current_dot >= 12 && self.deferred_line_end.is_none() {
 						match self.current_draw_state {
 							Some(LineDrawingState::Finished) => {}
 							_ => {
@@ -656,6 +796,10 @@ impl Ppu {
 					self.current_dot += 1;
 					self.total_dots += 1;
 
+					if self.deferred_line_end == Some(self.current_dot) {
+						self.render_deferred_line();
+					}
+
 					let left = self.current_dot == self.dot_target;
 					let right = matches!(self.current_draw_state, Some(LineDrawingState::Finished));
 
@@ -751,6 +895,202 @@ impl Ppu {
 		}
 	}
 
+	/// Collects the sprites found during the OAM search which are on this
+	/// line, at most one per X position and sorted from right to left, the
+	/// order they are drawn in
+	fn collect_line_sprites(&mut self) {
+		self.line_sprites.clear();
+		for sprite_idx in 0..self.sprite_count {
+			// WARNING: Sprites are not scrolled, they have an absolute position!
+			let sprite = self.sprite_buffer[sprite_idx]
+				.as_ref()
+				.expect("within the sprite count there should be no `None`s");
+
+			let y_valid =
+				self.registers.ly < sprite.y && self.registers.ly.wrapping_add(16) >= sprite.y;
+
+			if !self.line_sprites.iter().any(|existing| existing.x == sprite.x) && y_valid {
+				self.line_sprites.push(*sprite);
+			}
+		}
+
+		self.line_sprites.sort_by(|l, r| r.x.cmp(&l.x));
+	}
+
+	/// Draws pixel `x` of the current line, returning whether it was part of
+	/// the window
+	#[inline(always)]
+	fn render_pixel(&mut self, x: u8, scx: u8, scy: u8, draw_only_sprites: bool) -> bool {
+		let mut window_drawn = false;
+		let wx_match = (x as usize + 7) >= self.registers.wx as usize;
+		let scrolled_y = self.registers.ly.wrapping_add(scy) as usize;
+		let scrolled_x = x.wrapping_add(scx) as usize;
+
+		let (bg_color_id, bg_color) = match draw_only_sprites {
+			true => (0, Color::White),
+			false => {
+				let tilemap_idx = scrolled_x / 8 + ((scrolled_y / 8) * 32);
+				let tilemap_value = self.read_tile_map()[tilemap_idx];
+				let (mut bg_color_id, mut bg_color) = Self::parse_tile_color(
+					self.read_bg_win_tile(tilemap_value),
+					scrolled_x % 8,
+					scrolled_y % 8,
+					&self.bgp,
+				);
+
+				if self.window_enabled() && wx_match && self.wy_match {
+					window_drawn = true;
+					let window_x = x.wrapping_sub(self.registers.wx.wrapping_sub(7)) as usize;
+					let window_y = self.registers.ly.wrapping_sub(self.registers.wy) as usize;
+					let tilemap_idx = window_x / 8 + ((self.window_counter / 8) * 32);
+					let tilemap_value = self.read_window_tile_map()[tilemap_idx];
+					let (window_color_id, window_color) = Self::parse_tile_color(
+						self.read_bg_win_tile(tilemap_value),
+						window_x % 8,
+						window_y % 8,
+						&self.bgp,
+					);
+					bg_color_id = window_color_id;
+					bg_color = window_color;
+				}
+
+				(bg_color_id, bg_color)
+			}
+		};
+
+		let framebuffer_offset =
+			((self.registers.ly as usize * FB_WIDTH as usize) + x as usize) * PIXEL_SIZE;
+		for (idx, byte) in bg_color.rgba().iter().enumerate() {
+			self.framebuffer[framebuffer_offset + idx] = *byte;
+		}
+
+		if (self.registers.lcdc >> 1) & 0b1 == 1 {
+			let sprite_buffer = self
+				.line_sprites
+				.iter()
+				.filter(|sprite| x < sprite.x && x.wrapping_add(8) >= sprite.x);
+
+			// TODO: Adjust mode length based on sprites
+			for sprite in sprite_buffer {
+				let mut sprite_x_idx = x.wrapping_sub(sprite.x.wrapping_sub(8)) as usize;
+				let mut sprite_y_idx =
+					self.registers.ly.wrapping_sub(sprite.y.wrapping_sub(16)) as usize;
+
+				if sprite.y_flip() {
+					let sprite_offset = match self.sprite_height() {
+						SpriteHeight::Eight => 7,
+						SpriteHeight::Sixteen => 15,
+					};
+					sprite_y_idx = sprite_offset - sprite_y_idx;
+				}
+
+				if sprite.x_flip() {
+					sprite_x_idx = 7 - sprite_x_idx;
+				}
+
+				let tile_idx = match self.sprite_height() {
+					SpriteHeight::Eight => sprite.tile_idx,
+					SpriteHeight::Sixteen => match sprite_y_idx >= 8 {
+						true => sprite.tile_idx | 1,
+						false => sprite.tile_idx & 0xFE,
+					},
+				};
+
+				if sprite_y_idx >= 8 {
+					sprite_y_idx -= 8;
+					assert!(sprite_y_idx < 8);
+				}
+
+				let palette_color_idx =
+					self.read_obj_tile_colour_id(tile_idx, sprite_x_idx, sprite_y_idx); // If the index is 0, it is just treated as being transparent
+				let sprite_covered = sprite.covered_by_bg_window() && bg_color_id != 0;
+
+				if palette_color_idx != 0 && !sprite_covered {
+					let palette = &self.obp[sprite.palette_number()];
+					let sprite_color = palette.color_from_2bit(palette_color_idx);
+
+					let [r, g, b, a] = *sprite_color.rgba();
+
+					self.sprite_framebuffer[framebuffer_offset + 0] = r;
+					self.sprite_framebuffer[framebuffer_offset + 1] = g;
+					self.sprite_framebuffer[framebuffer_offset + 2] = b;
+					self.sprite_framebuffer[framebuffer_offset + 3] = a;
+				}
+			}
+		}
+		window_drawn
+	}
+
+	/// How many dots longer than 172 mode 3 will be, if no registers are
+	/// written during it. Must never be more than the real length, a shorter
+	/// prediction is caught in [`Ppu::render_deferred_line`]
+	fn predicted_extra_dots(&self) -> u16 {
+		let draw_only_sprites = self.registers.lcdc & 0b1 == 0;
+		let window_drawn = !self.first_frame
+			&& !draw_only_sprites
+			&& self.window_enabled()
+			&& self.wy_match
+			&& self.registers.wx as usize <= FB_WIDTH as usize + 6;
+
+		(self.registers.scx % 8) as u16 + if window_drawn { 6 } else { 0 }
+	}
+
+	/// Renders the whole line at the end of mode 3, leaving the PPU in the
+	/// same state drawing it a pixel per dot would have. If mode 3 turns out
+	/// to be longer than predicted the line is redrawn a pixel per dot instead
+	fn render_deferred_line(&mut self) {
+		self.deferred_line_end = None;
+		self.clear_line_sprite_fb(self.registers.ly as usize);
+
+		let (dot_target, window_counter) = (self.dot_target, self.window_counter);
+
+		let (scx, scy) = (self.registers.scx, self.registers.scy);
+		let draw_only_sprites = self.registers.lcdc & 0b1 == 0;
+		let mut window_drawn = false;
+
+		if !self.first_frame {
+			for x in 0..FB_WIDTH as u8 {
+				window_drawn |= self.render_pixel(x, scx, scy, draw_only_sprites);
+			}
+		}
+
+		self.dot_target += (scx % 8) as u16;
+		if window_drawn {
+			self.window_counter += 1;
+			self.dot_target += 6;
+		}
+
+		debug_assert!(
+			self.current_dot <= self.dot_target,
+			"scanline renderer overpredicted mode 3"
+		);
+		if self.current_dot != self.dot_target {
+			self.dot_target = dot_target;
+			self.window_counter = window_counter;
+			self.draw_pixels_to_current_dot();
+			return;
+		}
+
+		self.current_draw_state = Some(LineDrawingState::Finished);
+	}
+
+	/// Stops rendering the current line in one go, drawing the pixels it would
+	/// have drawn so far a pixel per dot. Called before a register which
+	/// affects drawing is written
+	pub fn undefer_line(&mut self) {
+		if self.deferred_line_end.take().is_some() {
+			self.draw_pixels_to_current_dot();
+		}
+	}
+
+	/// Draws the pixels of the current line a pixel per dot would have drawn
+	/// by `current_dot`
+	fn draw_pixels_to_current_dot(&mut self) {
+		for _ in 12..self.current_dot {
+			self.draw_pixel();
+		}
+	}
+
 	fn draw_pixel(&mut self) {
 		let state = match self.current_draw_state.take() {
 			Some(state) => state,
@@ -801,122 +1141,7 @@ impl Ppu {
 				draw_only_sprites,
 			) => {
 				if !self.first_frame {
-					let wx_match = (drawn_pixels as usize + 7) >= self.registers.wx as usize;
-					let scrolled_y = self.registers.ly.wrapping_add(scy) as usize;
-					let scrolled_x = drawn_pixels.wrapping_add(scx) as usize;
-
-					let (bg_color_id, bg_color) = match draw_only_sprites {
-						true => (0, Color::White),
-						false => {
-							let tilemap_idx = scrolled_x / 8 + ((scrolled_y / 8) * 32);
-							let tilemap_value = self.read_tile_map()[tilemap_idx];
-							let (mut bg_color_id, mut bg_color) = Self::parse_tile_color(
-								self.read_bg_win_tile(tilemap_value),
-								scrolled_x % 8,
-								scrolled_y % 8,
-								&self.bgp,
-							);
-
-							if self.window_enabled() && wx_match && self.wy_match {
-								window_drawn = true;
-								let window_x = (drawn_pixels as u8)
-									.wrapping_sub(self.registers.wx.wrapping_sub(7))
-									as usize;
-								let window_y =
-									self.registers.ly.wrapping_sub(self.registers.wy) as usize;
-								let tilemap_idx = window_x / 8 + ((self.window_counter / 8) * 32);
-								let tilemap_value = self.read_window_tile_map()[tilemap_idx];
-								let (window_color_id, window_color) = Self::parse_tile_color(
-									self.read_bg_win_tile(tilemap_value),
-									window_x % 8,
-									window_y % 8,
-									&self.bgp,
-								);
-								bg_color_id = window_color_id;
-								bg_color = window_color;
-							}
-
-							(bg_color_id, bg_color)
-						}
-					};
-
-					let framebuffer_offset = ((self.registers.ly as usize * FB_WIDTH as usize)
-						+ drawn_pixels as usize) * PIXEL_SIZE;
-					for (idx, byte) in bg_color.rgba().iter().enumerate() {
-						self.framebuffer[framebuffer_offset + idx] = *byte;
-					}
-
-					if (self.registers.lcdc >> 1) & 0b1 == 1 {
-						let mut sprite_buffer: Vec<OAMEntry> = Vec::new();
-						for sprite_idx in 0..self.sprite_count {
-							// WARNING: Sprites are not scrolled, they have an absolute position!
-							let sprite = self.sprite_buffer[sprite_idx]
-								.as_ref()
-								.expect("within the sprite count there should be no `None`s");
-
-							let x_valid =
-								drawn_pixels < sprite.x && drawn_pixels.wrapping_add(8) >= sprite.x;
-							let y_valid = self.registers.ly < sprite.y
-								&& self.registers.ly.wrapping_add(16) >= sprite.y;
-
-							if !sprite_buffer.iter().any(|existing| existing.x == sprite.x)
-								&& x_valid && y_valid
-							{
-								sprite_buffer.push(*sprite);
-							}
-						}
-
-						sprite_buffer.sort_by(|l, r| r.x.cmp(&l.x));
-
-						// TODO: Adjust mode length based on sprites
-						for sprite in &sprite_buffer {
-							let mut sprite_x_idx =
-								drawn_pixels.wrapping_sub(sprite.x.wrapping_sub(8)) as usize;
-							let mut sprite_y_idx =
-								self.registers.ly.wrapping_sub(sprite.y.wrapping_sub(16)) as usize;
-
-							if sprite.y_flip() {
-								let sprite_offset = match self.sprite_height() {
-									SpriteHeight::Eight => 7,
-									SpriteHeight::Sixteen => 15,
-								};
-								sprite_y_idx = sprite_offset - sprite_y_idx;
-							}
-
-							if sprite.x_flip() {
-								sprite_x_idx = 7 - sprite_x_idx;
-							}
-
-							let tile_idx = match self.sprite_height() {
-								SpriteHeight::Eight => sprite.tile_idx,
-								SpriteHeight::Sixteen => match sprite_y_idx >= 8 {
-									true => sprite.tile_idx | 1,
-									false => sprite.tile_idx & 0xFE,
-								},
-							};
-
-							if sprite_y_idx >= 8 {
-								sprite_y_idx -= 8;
-								assert!(sprite_y_idx < 8);
-							}
-
-							let palette_color_idx =
-								self.read_obj_tile_colour_id(tile_idx, sprite_x_idx, sprite_y_idx); // If the index is 0, it is just treated as being transparent
-							let sprite_covered = sprite.covered_by_bg_window() && bg_color_id != 0;
-
-							if palette_color_idx != 0 && !sprite_covered {
-								let palette = &self.obp[sprite.palette_number()];
-								let sprite_color = palette.color_from_2bit(palette_color_idx);
-
-								let [r, g, b, a] = *sprite_color.rgba();
-
-								self.sprite_framebuffer[framebuffer_offset + 0] = r;
-								self.sprite_framebuffer[framebuffer_offset + 1] = g;
-								self.sprite_framebuffer[framebuffer_offset + 2] = b;
-								self.sprite_framebuffer[framebuffer_offset + 3] = a;
-							}
-						}
-					}
+					window_drawn |= self.render_pixel(drawn_pixels, scx, scy, draw_only_sprites);
 				}
 
 				drawn_pixels += 1;
@@ -1002,39 +1227,133 @@ impl Ppu {
 		file_name
 	}
 
-	pub fn dump_bg_tiles(&self) {
-		let mut image = bmp::Image::new(16 * 8, 16 * 9);
+	/// Returns the index (counting from 0x8000) of the BG/Window tile `idx`
+	/// using the addressing mode currently selected by LCDC.4
+	pub fn bg_win_tile_index(&self, idx: u8) -> usize {
+		match (self.registers.lcdc >> 4) & 0b1 == 1 || idx >= 128 {
+			true => idx as usize,
+			false => 256 + idx as usize,
+		}
+	}
+
+	/// The tile map currently used for the background
+	pub fn bg_tile_map(&self) -> TileMap {
+		match (self.registers.lcdc >> 3) & 0b1 == 1 {
+			true => TileMap::High,
+			false => TileMap::Low,
+		}
+	}
+
+	/// The tile map currently used for the window
+	pub fn window_tile_map(&self) -> TileMap {
+		match (self.registers.lcdc >> 6) & 0b1 == 1 {
+			true => TileMap::High,
+			false => TileMap::Low,
+		}
+	}
 
-		for tile_y in 0..16 {
-			for tile_x in 0..16 {
-				let tiledata = self.read_bg_win_tile(tile_y * 16 + tile_x);
-				for row in 0..8usize {
-					let base = row * 2;
+	/// Decodes row `y` of tile `tile` (counting from 0x8000) into colour ids
+	fn tile_row(&self, tile: usize, y: usize) -> [u8; 8] {
+		let offset = (tile * 16) + (y * 2);
+		let (low, high) = (self.vram[offset], self.vram[offset + 1]);
+		let mut out = [0; 8];
 
-					let pixels = Color::parse_bgp(tiledata[base], tiledata[base + 1], &self.bgp);
+		for (x, colour_id) in out.iter_mut().enumerate() {
+			let bitshift = 7 - x;
+			*colour_id = (((high >> bitshift) & 0b1) << 1) | ((low >> bitshift) & 0b1);
+		}
 
-					for (x, color) in pixels.iter().enumerate() {
-						let pixel = color.rgba();
-						image.set_pixel(
-							(tile_x as u32 * 8) + x as u32,
-							tile_y as u32 * 9 + row as u32,
-							bmp::Pixel::new(pixel[0], pixel[1], pixel[2]),
-						);
-					}
+		out
+	}
+
+	/// Renders all [TILE_COUNT] tiles in VRAM into an RGBA buffer
+	/// [TILE_SHEET_WIDTH] tiles wide, in the order they appear in memory
+	pub fn render_tiles(&self, palette: &Palette) -> Vec<u8> {
+		let width = TILE_SHEET_WIDTH * 8;
+		let mut out = vec![0; TILE_COUNT * 8 * 8 * PIXEL_SIZE];
+
+		for tile in 0..TILE_COUNT {
+			let (tile_x, tile_y) = ((tile % TILE_SHEET_WIDTH) * 8, (tile / TILE_SHEET_WIDTH) * 8);
+
+			for y in 0..8 {
+				for (x, colour_id) in self.tile_row(tile, y).into_iter().enumerate() {
+					let base = (((tile_y + y) * width) + tile_x + x) * PIXEL_SIZE;
+					out[base..base + PIXEL_SIZE]
+						.copy_from_slice(palette.color_from_2bit(colour_id).rgba());
 				}
+			}
+		}
 
-				for x in 0..8 {
-					let color = bmp::Pixel::new(255 / (x + 1), 255 / (x + 1), 255 / (x + 1));
-					image.set_pixel((tile_x as u32 * 8) + x as u32, 9 * tile_y as u32, color);
+		out
+	}
+
+	/// Renders a full 256x256 tile map into an RGBA buffer using the current
+	/// BG palette and tile addressing mode
+	pub fn render_tile_map(&self, map: TileMap) -> Vec<u8> {
+		let base_address = map.base_address() as usize - 0x8000;
+		let mut out = vec![0; TILE_MAP_SIZE * TILE_MAP_SIZE * PIXEL_SIZE];
+
+		for (idx, tile_idx) in self.vram[base_address..base_address + 0x400].iter().enumerate() {
+			let tile = self.bg_win_tile_index(*tile_idx);
+			let (tile_x, tile_y) = ((idx % 32) * 8, (idx / 32) * 8);
+
+			for y in 0..8 {
+				for (x, colour_id) in self.tile_row(tile, y).into_iter().enumerate() {
+					let base = (((tile_y + y) * TILE_MAP_SIZE) + tile_x + x) * PIXEL_SIZE;
+					out[base..base + PIXEL_SIZE]
+						.copy_from_slice(self.bgp.color_from_2bit(colour_id).rgba());
 				}
 			}
 		}
 
-		let now =
-			std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
-		std::fs::create_dir_all("./bmp").unwrap();
-		let file_name = format!("./bmp/bg-data-{}.bmp", now);
-		image.save(file_name.as_str()).unwrap();
+		out
+	}
+
+	/// Decodes all 40 entries in OAM
+	pub fn oam_entries(&self) -> [OAMEntry; 40] {
+		let mut out = [OAMEntry::parse([0; 4]); 40];
+
+		for (entry, raw) in out.iter_mut().zip(self.oam.chunks_exact(4)) {
+			*entry = OAMEntry::parse([raw[0], raw[1], raw[2], raw[3]]);
+		}
+
+		out
+	}
+
+	/// Renders a sprite into an RGBA buffer 8 pixels wide and as tall as the
+	/// current sprite height, applying its flips and palette. Colour 0 is left
+	/// transparent
+	pub fn render_sprite(&self, entry: &OAMEntry) -> Vec<u8> {
+		let height = self.sprite_height() as usize;
+		let palette = &self.obp[entry.palette_number()];
+		let first_tile = match height {
+			16 => entry.tile_idx as usize & !0b1,
+			_ => entry.tile_idx as usize,
+		};
+		let mut out = vec![0; 8 * height * PIXEL_SIZE];
+
+		for y in 0..height {
+			let row = self.tile_row(first_tile + (y / 8), y % 8);
+			let out_y = match entry.y_flip() {
+				true => height - 1 - y,
+				false => y,
+			};
+
+			for (x, colour_id) in row.into_iter().enumerate() {
+				let out_x = match entry.x_flip() {
+					true => 7 - x,
+					false => x,
+				};
+
+				if colour_id != 0 {
+					let base = ((out_y * 8) + out_x) * PIXEL_SIZE;
+					out[base..base + PIXEL_SIZE]
+						.copy_from_slice(palette.color_from_2bit(colour_id).rgba());
+				}
+			}
+		}
+
+		out
 	}
 
 	pub fn read_bg_win_tile(&self, idx: u8) -> &[u8] {
@@ -1049,3 +1368,81 @@ impl Ppu {
 		}
 	}
 }
+
+#[test]
+fn test_scanline_renderer_misprediction() {
+	let setup = || {
+		let mut ppu = Ppu::new();
+		let mut seed = 0x1234u16;
+		for byte in ppu.vram.iter_mut() {
+			seed = seed.wrapping_mul(25173).wrapping_add(13849);
+			*byte = (seed >> 8) as u8;
+		}
+		ppu.registers.scx = 3;
+		ppu
+	};
+
+	let mut dot = setup();
+	let mut scanline = setup();
+	scanline.scanline_renderer = true;
+
+	let dma = DmaState::new();
+	let mut interrupts = Interrupts::new();
+	let mut frames = 0;
+	while frames < 3 {
+		let redraw = dot.tick(&dma, &mut interrupts);
+		assert_eq!(redraw, scanline.tick(&dma, &mut interrupts));
+
+		// Predict every line to end well before it really does
+		if let Some(end) = scanline.deferred_line_end.as_mut() {
+			*end = end.saturating_sub(100).max(scanline.current_dot + 1);
+		}
+
+		if redraw {
+			assert!(dot.write_fb() == scanline.write_fb(), "frame {frames} differs");
+			frames += 1;
+		}
+		assert_eq!(dot.current_dot, scanline.current_dot);
+	}
+}
+
+#[test]
+fn test_skip_matches_ticking() {
+	let setup = || {
+		let mut ppu = Ppu::new();
+		let mut seed = 0x4321u16;
+		for byte in ppu.vram.iter_mut().chain(ppu.oam.iter_mut()) {
+			seed = seed.wrapping_mul(25173).wrapping_add(13849);
+			*byte = (seed >> 8) as u8;
+		}
+		ppu.registers.lcdc |= 0b10;
+		ppu
+	};
+
+	let mut ticked = setup();
+	let mut skipped = setup();
+
+	let dma = DmaState::new();
+	let mut interrupts = Interrupts::new();
+	let (mut idle, mut behind) = (0, 0);
+	let mut frames = 0;
+	while frames < 3 {
+		let redraw = ticked.tick(&dma, &mut interrupts);
+		if behind < idle {
+			assert!(!redraw);
+			behind += 1;
+			continue;
+		}
+
+		skipped.skip_idle_dots(behind);
+		assert_eq!(redraw, skipped.tick(&dma, &mut interrupts));
+		assert_eq!((ticked.current_dot, ticked.mode()), (skipped.current_dot, skipped.mode()));
+		assert_eq!(ticked.sprite_count, skipped.sprite_count);
+		(idle, behind) = (skipped.idle_dots(false), 0);
+
+		if redraw {
+			assert!(ticked.write_fb() == skipped.write_fb(), "frame {frames} differs");
+			frames += 1;
+		}
+	}
+}
@@ -0,0 +1,316 @@
+//! The Game Boy Printer, which prints tile data sent to it in packets over the
+//! link cable. The Game Boy always clocks the transfers, the printer answering
+//! each byte it receives
+
+use std::{io::Write, path::Path};
+
+use super::{
+	ppu::{Color, PIXEL_SIZE},
+	serial::LinkDevice,
+};
+
+/// Width of the paper in pixels, 20 tiles
+pub const PRINT_WIDTH: u32 = 160;
+
+const TILE_ROW_BYTES: usize = (PRINT_WIDTH as usize / 8) * 16;
+/// Size of the printer's image RAM
+const BUFFER_SIZE: usize = 0x2000;
+/// The largest data a packet is allowed to carry, two rows of tiles
+const MAX_PACKET_DATA: usize = 0x280;
+
+const MAGIC: [u8; 2] = [0x88, 0x33];
+/// Sent back in place of the first byte after a packet's checksum
+const ALIVE: u8 = 0x81;
+/// Palette 0 is treated as the usual shades in order
+const DEFAULT_PALETTE: u8 = 0xE4;
+/// How many status packets report the printer as busy after printing
+const BUSY_STATUS_PACKETS: u8 = 2;
+
+const STATUS_CHECKSUM_ERROR: u8 = 1 << 0;
+const STATUS_BUSY: u8 = 1 << 1;
+const STATUS_IMAGE_FULL: u8 = 1 << 2;
+const STATUS_UNPROCESSED: u8 = 1 << 3;
+const STATUS_PACKET_ERROR: u8 = 1 << 4;
+
+const COMMAND_INIT: u8 = 0x01;
+const COMMAND_PRINT: u8 = 0x02;
+const COMMAND_DATA: u8 = 0x04;
+const COMMAND_STATUS: u8 = 0x0F;
+
+/// An image printed by a single print command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrintedStrip {
+	/// Height in pixels, the width is always [`PRINT_WIDTH`]
+	pub height: u32,
+	pub rgba: Vec<u8>,
+	/// Paper fed before printing, in the units the game requested
+	pub margin_before: u8,
+	/// Paper fed after printing, in the units the game requested
+	pub margin_after: u8,
+}
+
+impl PrintedStrip {
+	pub fn encode_png(&self, writer: impl Write) -> Result<(), png::EncodingError> {
+		let mut encoder = png::Encoder::new(writer, PRINT_WIDTH, self.height);
+		encoder.set_color(png::ColorType::Rgba);
+		encoder.set_depth(png::BitDepth::Eight);
+
+		encoder.write_header()?.write_image_data(&self.rgba)
+	}
+
+	pub fn write_png(&self, path: &Path) -> Result<(), png::EncodingError> {
+		let file = std::fs::File::create(path)?;
+		self.encode_png(std::io::BufWriter::new(file))
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PacketState {
+	/// Waiting for the magic byte at this index
+	Magic(usize),
+	Command,
+	Compression,
+	LengthLow,
+	LengthHigh,
+	Data,
+	ChecksumLow,
+	ChecksumHigh,
+	Alive,
+	Status,
+}
+
+#[derive(Debug, Default)]
+struct Packet {
+	command: u8,
+	compressed: bool,
+	length: u16,
+	data: Vec<u8>,
+	/// Sum of every byte from the command to the end of the data
+	sum: u16,
+	checksum: u16,
+}
+
+impl Packet {
+	fn add_to_sum(&mut self, byte: u8) {
+		self.sum = self.sum.wrapping_add(byte as u16);
+	}
+}
+
+pub struct Printer {
+	state: PacketState,
+	packet: Packet,
+	/// Decompressed tile data waiting to be printed
+	buffer: Vec<u8>,
+	status: u8,
+	busy_for: u8,
+	/// Everything printed which has not been taken with
+	/// [`Printer::take_printed`]
+	pub printed: Vec<PrintedStrip>,
+}
+
+impl Printer {
+	pub fn new() -> Self {
+		Self {
+			state: PacketState::Magic(0),
+			packet: Packet::default(),
+			buffer: Vec::with_capacity(BUFFER_SIZE),
+			status: 0,
+			busy_for: 0,
+			printed: Vec::new(),
+		}
+	}
+
+	pub fn take_printed(&mut self) -> Vec<PrintedStrip> {
+		std::mem::take(&mut self.printed)
+	}
+
+	/// Moves on to the packet's data, or straight to its checksum if it has
+	/// none
+	fn after_length(&self) -> PacketState {
+		match self.packet.length {
+			0 => PacketState::ChecksumLow,
+			_ => PacketState::Data,
+		}
+	}
+
+	fn finish_packet(&mut self) {
+		let packet = std::mem::take(&mut self.packet);
+
+		if packet.sum != packet.checksum {
+			self.status |= STATUS_CHECKSUM_ERROR;
+			return;
+		}
+		self.status &= !(STATUS_CHECKSUM_ERROR | STATUS_PACKET_ERROR);
+
+		if packet.data.len() > MAX_PACKET_DATA {
+			self.status |= STATUS_PACKET_ERROR;
+			return;
+		}
+
+		match packet.command {
+			COMMAND_INIT => {
+				self.buffer.clear();
+				self.status = 0;
+				self.busy_for = 0;
+			}
+			COMMAND_DATA => {
+				match packet.compressed {
+					true => self.decompress(&packet.data),
+					false => self.buffer.extend_from_slice(&packet.data),
+				}
+
+				if self.buffer.len() >= BUFFER_SIZE {
+					self.buffer.truncate(BUFFER_SIZE);
+					self.status |= STATUS_IMAGE_FULL;
+				}
+				if !self.buffer.is_empty() {
+					self.status |= STATUS_UNPROCESSED;
+				}
+			}
+			COMMAND_PRINT => match packet.data[..] {
+				[sheets, margins, palette, _exposure, ..] => {
+					self.print(sheets, margins, palette);
+					self.status = STATUS_BUSY;
+					self.busy_for = BUSY_STATUS_PACKETS;
+				}
+				_ => self.status |= STATUS_PACKET_ERROR,
+			},
+			COMMAND_STATUS => {
+				if self.busy_for > 0 {
+					self.busy_for -= 1;
+					if self.busy_for == 0 {
+						self.status &= !STATUS_BUSY;
+					}
+				}
+			}
+			_ => self.status |= STATUS_PACKET_ERROR,
+		}
+	}
+
+	/// Decodes runs, where a control byte with the top bit set repeats the
+	/// next byte `(control & 0x7F) + 2` times and any other copies the next
+	/// `control + 1` bytes
+	fn decompress(&mut self, mut data: &[u8]) {
+		while let [control, rest @ ..] = data {
+			match control & 0x80 {
+				0 => {
+					let length = (*control as usize + 1).min(rest.len());
+					self.buffer.extend_from_slice(&rest[..length]);
+					data = &rest[length..];
+				}
+				_ => {
+					let Some(byte) = rest.first() else {
+						break;
+					};
+					let length = (control & 0x7F) as usize + 2;
+					self.buffer.extend(std::iter::repeat_n(*byte, length));
+					data = &rest[1..];
+				}
+			}
+		}
+	}
+
+	/// Prints every complete row of tiles in the buffer. No sheets only feeds
+	/// the paper, and more than one prints a single copy
+	fn print(&mut self, sheets: u8, margins: u8, palette: u8) {
+		let palette = match palette {
+			0 => DEFAULT_PALETTE,
+			palette => palette,
+		};
+
+		let tile_rows = self.buffer.len() / TILE_ROW_BYTES;
+		let height = tile_rows as u32 * 8;
+		let mut rgba = Vec::with_capacity(height as usize * PRINT_WIDTH as usize * PIXEL_SIZE);
+
+		for row in self.buffer.chunks_exact(TILE_ROW_BYTES) {
+			for y in 0..8 {
+				for tile in row.chunks_exact(16) {
+					let (low, high) = (tile[y * 2], tile[y * 2 + 1]);
+					for bit in (0..8).rev() {
+						let color_id = ((high >> bit) & 0b1) << 1 | ((low >> bit) & 0b1);
+						let shade = palette >> (color_id * 2);
+						rgba.extend_from_slice(Color::from_2bit(shade).rgba());
+					}
+				}
+			}
+		}
+
+		self.buffer.clear();
+
+		if sheets > 0 && height > 0 {
+			self.printed.push(PrintedStrip {
+				height,
+				rgba,
+				margin_before: margins >> 4,
+				margin_after: margins & 0xF,
+			});
+		}
+	}
+}
+
+impl Default for Printer {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl LinkDevice for Printer {
+	fn exchange(&mut self, byte: u8) -> u8 {
+		let reply = match self.state {
+			PacketState::Alive => ALIVE,
+			PacketState::Status => self.status,
+			_ => 0,
+		};
+
+		self.state = match self.state {
+			PacketState::Magic(0) if byte == MAGIC[0] => PacketState::Magic(1),
+			PacketState::Magic(1) if byte == MAGIC[1] => {
+				self.packet = Packet::default();
+				PacketState::Command
+			}
+			PacketState::Magic(_) if byte == MAGIC[0] => PacketState::Magic(1),
+			PacketState::Magic(_) => PacketState::Magic(0),
+			PacketState::Command => {
+				self.packet.command = byte;
+				self.packet.add_to_sum(byte);
+				PacketState::Compression
+			}
+			PacketState::Compression => {
+				self.packet.compressed = byte & 0b1 == 1;
+				self.packet.add_to_sum(byte);
+				PacketState::LengthLow
+			}
+			PacketState::LengthLow => {
+				self.packet.length = byte as u16;
+				self.packet.add_to_sum(byte);
+				PacketState::LengthHigh
+			}
+			PacketState::LengthHigh => {
+				self.packet.length |= (byte as u16) << 8;
+				self.packet.add_to_sum(byte);
+				self.after_length()
+			}
+			PacketState::Data => {
+				self.packet.data.push(byte);
+				self.packet.add_to_sum(byte);
+				match self.packet.data.len() == self.packet.length as usize {
+					true => PacketState::ChecksumLow,
+					false => PacketState::Data,
+				}
+			}
+			PacketState::ChecksumLow => {
+				self.packet.checksum = byte as u16;
+				PacketState::ChecksumHigh
+			}
+			PacketState::ChecksumHigh => {
+				self.packet.checksum |= (byte as u16) << 8;
+				self.finish_packet();
+				PacketState::Alive
+			}
+			PacketState::Alive => PacketState::Status,
+			PacketState::Status => PacketState::Magic(0),
+		};
+
+		reply
+	}
+}
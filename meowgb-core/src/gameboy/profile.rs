@@ -0,0 +1,38 @@
+//! Optional timing of each subsystem ticked by `Gameboy::tick`, for
+//! benchmarking. Nothing is timed unless a [`TickProfile`] is installed
+
+use std::time::{Duration, Instant};
+
+/// Time spent in each subsystem since the profile was installed
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TickProfile {
+	pub cpu: Duration,
+	pub ppu: Duration,
+	pub timer: Duration,
+	pub dma: Duration,
+	pub serial: Duration,
+}
+
+impl TickProfile {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn total(&self) -> Duration {
+		self.cpu + self.ppu + self.timer + self.dma + self.serial
+	}
+}
+
+/// Runs `f`, adding the time it took to `slot` if `ENABLED`
+#[inline(always)]
+pub(crate) fn time<const ENABLED: bool, T>(slot: &mut Duration, f: impl FnOnce() -> T) -> T {
+	match ENABLED {
+		true => {
+			let start = Instant::now();
+			let out = f();
+			*slot += start.elapsed();
+			out
+		}
+		false => f(),
+	}
+}
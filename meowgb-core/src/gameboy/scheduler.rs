@@ -0,0 +1,61 @@
+//! Tracks when each lazily ticked component next needs ticking. Between those
+//! points a component only advances internal counters, so its ticks are
+//! skipped and caught up in one go, either when it is next due or when the CPU
+//! accesses it
+
+use serde::{Deserialize, Serialize};
+
+/// A component which is only ticked when it might change observable state
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub struct LazyComponent {
+	/// Every tick before this T-cycle has been applied
+	synced: u64,
+	/// The next T-cycle at which the component must really be ticked
+	deadline: u64,
+}
+
+impl LazyComponent {
+	/// Whether the component must be ticked at `now`
+	pub fn due(&self, now: u64) -> bool {
+		now >= self.deadline
+	}
+
+	/// How many ticks have been skipped as of `now`
+	pub fn behind(&self, now: u64) -> u64 {
+		now - self.synced
+	}
+
+	/// Records the component being ticked at `now`, after which its next
+	/// `idle` ticks only advance internal counters
+	pub fn ticked(&mut self, now: u64, idle: u64) {
+		self.synced = now + 1;
+		self.deadline = self.synced.saturating_add(idle);
+	}
+
+	/// Records the component being caught up to `now`. It is due straight away
+	/// as whatever caught it up may have changed how long it stays idle
+	pub fn caught_up(&mut self, now: u64) {
+		self.synced = now;
+		self.deadline = now;
+	}
+}
+
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub struct Scheduler {
+	/// T-cycles elapsed since power on
+	pub now: u64,
+	pub ppu: LazyComponent,
+	pub timer: LazyComponent,
+}
+
+impl Scheduler {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// The first T-cycle at which any lazily ticked component must really be
+	/// ticked
+	pub fn next_deadline(&self) -> u64 {
+		self.ppu.deadline.min(self.timer.deadline)
+	}
+}
@@ -1,31 +1,78 @@
 use std::io::Write;
 
+use serde::{Deserialize, Serialize};
+
 use super::interrupts::Interrupts;
 
-pub trait SerialWriter {
-	fn write_byte(&mut self, byte: u8);
+/// The byte shifted in when nothing is driving the other end of the cable
+pub const DISCONNECTED: u8 = 0xFF;
+
+/// Whatever is plugged into the other end of the link cable. Each transfer
+/// exchanges a byte in both directions, clocked by one of the two sides
+pub trait LinkDevice {
+	/// Finishes a transfer clocked by the Game Boy, sending `byte` and
+	/// returning the byte shifted in from the device
+	fn exchange(&mut self, byte: u8) -> u8;
+
+	/// Polled every M-cycle while the Game Boy waits for the device to clock
+	/// a transfer. Once the device has, `byte` is sent to it and the byte
+	/// shifted in is returned
+	fn poll_external(&mut self, byte: u8) -> Option<u8> {
+		let _ = byte;
+		None
+	}
+
+	/// Called when the Game Boy stops waiting for the device to clock a
+	/// transfer without one having happened
+	fn stop_polling(&mut self) {}
 }
 
-impl<T: Write> SerialWriter for T {
-	fn write_byte(&mut self, byte: u8) {
+/// Treats the sink as a device which never drives the clock or sends anything
+/// back, so outgoing bytes can be logged
+impl<T: Write> LinkDevice for T {
+	fn exchange(&mut self, byte: u8) -> u8 {
 		self.write_all(&[byte])
 			.expect(format!("writing serial to {} failed", std::any::type_name::<T>()).as_str());
 		self.flush()
 			.expect(format!("flushing serial to {} failed", std::any::type_name::<T>()).as_str());
+		DISCONNECTED
 	}
 }
 
-pub struct Serial<S: SerialWriter> {
+/// The serial registers and transfer progress, without the device, as kept in
+/// save states
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub struct SerialState {
+	sb: u8,
+	sc: u8,
+	internal_tick: u16,
+}
+
+pub struct Serial<S: LinkDevice> {
 	pub sb: u8,
 	sc: u8,
 
 	internal_tick: u16,
-	writer: S,
+	pub device: S,
 }
 
-impl<S: SerialWriter> Serial<S> {
-	pub fn new(writer: S) -> Serial<S> {
-		Self { sb: 0, sc: 0, internal_tick: 0, writer }
+impl<S: LinkDevice> Serial<S> {
+	pub fn new(device: S) -> Serial<S> {
+		Self { sb: 0, sc: 0, internal_tick: 0, device }
+	}
+
+	pub fn state(&self) -> SerialState {
+		SerialState { sb: self.sb, sc: self.sc, internal_tick: self.internal_tick }
+	}
+
+	pub fn restore(&mut self, state: SerialState) {
+		let was_polling = self.is_polling();
+		self.sb = state.sb;
+		self.sc = state.sc;
+		self.internal_tick = state.internal_tick;
+		if was_polling && !self.is_polling() {
+			self.device.stop_polling();
+		}
 	}
 
 	pub fn set_transfer_in_process(&mut self, value: bool) {
@@ -41,8 +88,17 @@ impl<S: SerialWriter> Serial<S> {
 		self.sc & 0b1 == 1
 	}
 
+	/// Whether a transfer is waiting for the device to clock it
+	fn is_polling(&self) -> bool {
+		self.sc >> 7 == 1 && !self.is_conductor()
+	}
+
 	pub fn set_sc(&mut self, value: u8) {
+		let was_polling = self.is_polling();
 		self.sc = value | (0b0111_1110);
+		if was_polling && !self.is_polling() {
+			self.device.stop_polling();
+		}
 	}
 
 	pub fn get_sc(&self) -> u8 {
@@ -56,16 +112,44 @@ impl<S: SerialWriter> Serial<S> {
 	}
 
 	pub fn tick(&mut self, interrupts: &mut Interrupts) {
-		if self.get_transfer_in_process() && self.is_conductor() {
+		if !self.get_transfer_in_process() {
+			return;
+		}
+
+		if self.is_conductor() {
 			if self.internal_tick < 128 {
 				self.internal_tick += 1;
 			} else {
-				self.writer.write_byte(self.sb);
-				self.sb = 0;
-				self.set_transfer_in_process(false);
-				self.internal_tick = 0;
-				interrupts.write_if_serial(true);
+				self.sb = self.device.exchange(self.sb);
+				self.finish_transfer(interrupts);
 			}
+		} else if let Some(byte) = self.device.poll_external(self.sb) {
+			self.sb = byte;
+			self.finish_transfer(interrupts);
 		}
 	}
+
+	/// How many M-cycles [`Serial::tick`] would only count, `None` if the
+	/// device has to be polled every M-cycle
+	pub fn idle_m_cycles(&self) -> Option<u64> {
+		match (self.sc >> 7 == 1, self.is_conductor()) {
+			(false, _) => Some(u64::MAX),
+			(true, true) => Some(128u64.saturating_sub(self.internal_tick as u64)),
+			(true, false) => None,
+		}
+	}
+
+	/// Applies `m_cycles` ticks at once, which must be no more than
+	/// [`Serial::idle_m_cycles`]
+	pub fn skip(&mut self, m_cycles: u64) {
+		if self.sc >> 7 == 1 {
+			self.internal_tick += m_cycles as u16;
+		}
+	}
+
+	fn finish_transfer(&mut self, interrupts: &mut Interrupts) {
+		self.set_transfer_in_process(false);
+		self.internal_tick = 0;
+		interrupts.write_if_serial(true);
+	}
 }
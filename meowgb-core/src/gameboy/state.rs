@@ -0,0 +1,168 @@
+//! Save states, a snapshot of the whole console apart from the cartridge ROM
+//! and whatever is plugged into the link port
+
+use super::{
+	call_stack::CallStack,
+	cpu::Registers,
+	dma::DmaState,
+	interrupts::Interrupts,
+	joypad::Joypad,
+	memory::Memory,
+	ppu::Ppu,
+	scheduler::Scheduler,
+	serial::{LinkDevice, SerialState},
+	sound::Sound,
+	timer::Timer,
+	Gameboy,
+};
+use crate::ringbuffer::RingBuffer;
+
+const MAGIC: &[u8; 8] = b"MEOWSAVE";
+const VERSION: u8 = 2;
+/// The magic, version and ROM checksum
+const HEADER_LENGTH: usize = MAGIC.len() + 1 + 4;
+
+/// halt, halt_bug, used_halt_bug, stop and cycle_count
+type CpuFlags = (bool, bool, bool, bool, u64);
+
+/// Everything after the header, in the order it is written
+type StateBody = (
+	Registers,
+	Interrupts,
+	CpuFlags,
+	Ppu,
+	Memory,
+	Timer,
+	Joypad,
+	SerialState,
+	DmaState,
+	Sound,
+	u8,
+	Scheduler,
+	Option<Vec<u8>>,
+);
+
+#[derive(Debug, thiserror::Error)]
+pub enum StateError {
+	#[error("Not a save state")]
+	Magic,
+	#[error("Save state version {0} is not supported")]
+	Version(u8),
+	#[error("Save state is for a different game")]
+	WrongGame,
+	#[error("Save state does not match the cartridge's RAM or mapper")]
+	Cartridge,
+	#[error("Corrupt save state: {0}")]
+	Decode(#[from] bincode::Error),
+}
+
+impl<S: LinkDevice> Gameboy<S> {
+	/// CRC-32 of the cartridge ROM, 0 with no cartridge inserted
+	pub fn rom_checksum(&self) -> u32 {
+		self.bus
+			.cartridge
+			.as_ref()
+			.map(|cartridge| crc32fast::hash(cartridge.rom()))
+			.unwrap_or_default()
+	}
+
+	pub fn save_state(&self) -> Vec<u8> {
+		let mut out = MAGIC.to_vec();
+		out.push(VERSION);
+		out.extend_from_slice(&self.rom_checksum().to_le_bytes());
+
+		let cartridge = self.bus.cartridge.as_ref().map(|cartridge| cartridge.save_state());
+		bincode::serialize_into(
+			&mut out,
+			&(
+				&self.registers,
+				&self.interrupts,
+				(self.halt, self.halt_bug, self.used_halt_bug, self.stop, self.cycle_count),
+				&self.bus.ppu,
+				&self.bus.memory,
+				&self.bus.timer,
+				&self.bus.joypad,
+				self.bus.serial.state(),
+				&self.bus.dma,
+				&self.bus.sound,
+				self.bus.tick_count,
+				&self.bus.scheduler,
+				cartridge,
+			),
+		)
+		.expect("serializing to a Vec cannot fail");
+
+		out
+	}
+
+	/// Restores a state from [`Gameboy::save_state`], which must have been
+	/// saved with the same game inserted. Nothing is changed if it fails. The
+	/// call stack and interrupt history are cleared rather than restored
+	pub fn load_state(&mut self, state: &[u8]) -> Result<(), StateError> {
+		if state.len() < HEADER_LENGTH || &state[..MAGIC.len()] != MAGIC {
+			return Err(StateError::Magic);
+		}
+
+		let (header, body) = state.split_at(HEADER_LENGTH);
+		if header[MAGIC.len()] != VERSION {
+			return Err(StateError::Version(header[MAGIC.len()]));
+		}
+		let checksum = u32::from_le_bytes(header[MAGIC.len() + 1..].try_into().unwrap());
+		if checksum != self.rom_checksum() {
+			return Err(StateError::WrongGame);
+		}
+
+		let (
+			registers,
+			interrupts,
+			(halt, halt_bug, used_halt_bug, stop, cycle_count),
+			mut ppu,
+			memory,
+			timer,
+			joypad,
+			serial,
+			dma,
+			sound,
+			tick_count,
+			scheduler,
+			cartridge,
+		): StateBody = bincode::deserialize(body)?;
+
+		match (&mut self.bus.cartridge, cartridge) {
+			(Some(inserted), Some(cartridge)) => inserted.load_state(&cartridge)?,
+			(None, None) => {}
+			_ => return Err(StateError::Cartridge),
+		}
+
+		// A setting of this emulator rather than state of the console
+		ppu.scanline_renderer = self.bus.ppu.scanline_renderer;
+
+		self.registers = registers;
+		self.interrupts = interrupts;
+		self.halt = halt;
+		self.halt_bug = halt_bug;
+		self.used_halt_bug = used_halt_bug;
+		self.stop = stop;
+		self.cycle_count = cycle_count;
+		self.bus.ppu = ppu;
+		self.bus.memory = memory;
+		self.bus.timer = timer;
+		self.bus.joypad = joypad;
+		self.bus.serial.restore(serial);
+		self.bus.dma = dma;
+		self.bus.sound = sound;
+		self.bus.tick_count = tick_count;
+		self.bus.scheduler = scheduler;
+
+		self.last_read = None;
+		self.last_write = None;
+		self.call_stack = CallStack::new();
+		self.interrupt_history = RingBuffer::new();
+		#[cfg(feature = "instr-dbg")]
+		{
+			self.pc_history = RingBuffer::new();
+		}
+
+		Ok(())
+	}
+}
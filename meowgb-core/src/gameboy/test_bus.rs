@@ -0,0 +1,57 @@
+//! A flat 64 KiB memory bus for single instruction CPU tests, which replaces the
+//! whole memory map so tests can place code and data at any address
+
+use super::{cpu::CpuBus, interrupts::Interrupts};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BusAccess {
+	Read,
+	Write,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BusActivity {
+	pub address: u16,
+	pub value: u8,
+	pub access: BusAccess,
+}
+
+#[derive(Debug, Clone)]
+pub struct TestBus {
+	pub memory: Box<[u8; 0x10000]>,
+	/// Every access made by the CPU since this was last cleared, in order
+	pub activity: Vec<BusActivity>,
+}
+
+impl TestBus {
+	pub fn new() -> Self {
+		Self { memory: Box::new([0; 0x10000]), activity: Vec::new() }
+	}
+
+	pub fn read(&mut self, address: u16) -> u8 {
+		let value = self.memory[address as usize];
+		self.activity.push(BusActivity { address, value, access: BusAccess::Read });
+		value
+	}
+
+	pub fn write(&mut self, address: u16, value: u8) {
+		self.memory[address as usize] = value;
+		self.activity.push(BusActivity { address, value, access: BusAccess::Write });
+	}
+}
+
+impl CpuBus for TestBus {
+	fn read_u8(&mut self, _interrupts: &mut Interrupts, address: u16) -> u8 {
+		self.read(address)
+	}
+
+	fn write_u8(&mut self, _interrupts: &mut Interrupts, address: u16, value: u8) {
+		self.write(address, value)
+	}
+}
+
+impl Default for TestBus {
+	fn default() -> Self {
+		Self::new()
+	}
+}
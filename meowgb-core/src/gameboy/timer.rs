@@ -1,6 +1,8 @@
+use serde::{Deserialize, Serialize};
+
 use super::interrupts::Interrupts;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Timer {
 	enable: bool,
 	clock: TimerClock,
@@ -54,6 +56,47 @@ impl Timer {
 		}
 	}
 
+	/// How many ticks from now only advance DIV and TIMA without overflowing
+	/// it, which [`Timer::skip`] can apply in one go
+	pub fn idle_cycles(&self) -> u64 {
+		if self.overflow != 0 {
+			return 0;
+		}
+
+		if !self.enable {
+			return u64::MAX;
+		}
+
+		let period = self.clock.cycles() as u64;
+		let until_increment = period - self.div as u64 % period;
+		until_increment + (0xFF - self.tima as u64) * period - 1
+	}
+
+	/// Applies `cycles` ticks at once, which must be no more than
+	/// [`Timer::idle_cycles`]
+	pub fn skip(&mut self, cycles: u64) {
+		if self.enable {
+			let period = self.clock.cycles() as u64;
+			let increments = (self.div as u64 % period + cycles) / period;
+			debug_assert!(self.tima as u64 + increments <= 0xFF);
+			self.tima = self.tima.wrapping_add(increments as u8);
+		}
+
+		self.div = self.div.wrapping_add(cycles as u16);
+	}
+
+	/// The full internal divider counter, DIV is its upper byte
+	pub fn internal_div(&self) -> u16 {
+		self.div
+	}
+
+	/// How far through a TIMA overflow the timer is. 0 if none is in progress,
+	/// 1-3 whilst TIMA reads 0 and the reload is pending, and 4 on the cycle
+	/// after TMA was reloaded into TIMA
+	pub fn overflow_state(&self) -> u8 {
+		self.overflow
+	}
+
 	pub fn read_div(&self) -> u8 {
 		(self.div >> 8) as u8
 	}
@@ -124,7 +167,7 @@ impl Timer {
 	}
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TimerClock {
 	C16 = 1,
 	C64 = 2,
@@ -165,3 +208,23 @@ impl TimerClock {
 		self.cycles() / 2
 	}
 }
+
+#[test]
+fn test_skip_matches_ticking() {
+	for tac in [0b100, 0b101, 0b110, 0b111, 0b000] {
+		let mut ticked = Timer::new();
+		ticked.write_tac(tac);
+		ticked.write_tima(0xF0);
+		let mut skipped = ticked.clone();
+		let mut interrupts = Interrupts::new();
+
+		let idle = skipped.idle_cycles().min(5000);
+		for _ in 0..idle {
+			ticked.tick(&mut interrupts);
+		}
+		skipped.skip(idle);
+
+		assert_eq!((ticked.div, ticked.tima), (skipped.div, skipped.tima), "TAC {:#05b}", tac);
+		assert_eq!(interrupts.interrupt_flag & 0b100, 0);
+	}
+}
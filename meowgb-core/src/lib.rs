@@ -1,6 +1,8 @@
+pub mod disassembler;
+pub mod emulator;
 pub mod gameboy;
-#[cfg(feature = "instr-dbg")]
 pub mod ringbuffer;
+pub mod symbols;
 
 /// A helper for writing CPU tests in Rust, the emulator returned by this
 /// function has already fetched the first instruction. The next tick will be
@@ -8,12 +10,20 @@ pub mod ringbuffer;
 pub fn setup_test_emulator<const ROM_LENGTH: usize>(
 	test_opcodes: [u8; ROM_LENGTH],
 ) -> gameboy::Gameboy<std::io::Stdout> {
+	setup_linked_test_emulator(std::io::stdout(), test_opcodes)
+}
+
+/// [`setup_test_emulator`] with `link_device` plugged into the serial port
+pub fn setup_linked_test_emulator<S: gameboy::serial::LinkDevice, const ROM_LENGTH: usize>(
+	link_device: S,
+	test_opcodes: [u8; ROM_LENGTH],
+) -> gameboy::Gameboy<S> {
 	let mut cartridge = gameboy::mapper::NoMBC { rom: [0u8; 0x8000], ram: None };
 
 	(&mut cartridge.rom[0x100..ROM_LENGTH + 0x100]).copy_from_slice(&test_opcodes);
 
 	let mut gameboy =
-		gameboy::Gameboy::new_with_cartridge(std::io::stdout(), Some(Box::new(cartridge)));
+		gameboy::Gameboy::new_with_cartridge(link_device, Some(Box::new(cartridge)));
 
 	gameboy.tick_4(); // Prefetch instruction
 	assert!(gameboy.registers.mem_read_hold.is_some()); // Assert prefetch happened and opcode is now sitting in the memory bus
@@ -22,3 +32,10 @@ pub fn setup_test_emulator<const ROM_LENGTH: usize>(
 
 	gameboy
 }
+
+/// A helper for single instruction CPU tests, the CPU returned by this function is
+/// attached to a flat 64 KiB [`gameboy::test_bus::TestBus`] with all registers
+/// cleared
+pub fn setup_test_bus_emulator() -> gameboy::cpu::Sm83<gameboy::test_bus::TestBus> {
+	gameboy::cpu::Sm83::with_bus(gameboy::test_bus::TestBus::new())
+}
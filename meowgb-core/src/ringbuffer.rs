@@ -37,6 +37,12 @@ impl<T: std::fmt::Debug + Copy + Default, const SIZE: usize> RingBuffer<T, SIZE>
 	}
 }
 
+impl<T: std::fmt::Debug + Copy + Default, const SIZE: usize> Default for RingBuffer<T, SIZE> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
 #[test]
 fn test_ringbuffer() {
 	let mut ringbuffer: RingBuffer<u8, 16> = RingBuffer::new();
@@ -0,0 +1,152 @@
+//! Parsing and lookup of RGBDS `.sym` symbol files
+//!
+//! Each line of a symbol file is `bank:address label`, both numbers being
+//! hexadecimal, and anything after a `;` is a comment.
+
+use std::{
+	collections::{BTreeMap, HashMap},
+	path::Path,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum SymbolError {
+	#[error("Error reading symbol file: {0}")]
+	Read(std::io::Error),
+	#[error("Malformed symbol on line {0}: {1:?}")]
+	Malformed(usize, String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+	pub bank: u16,
+	pub address: u16,
+	pub name: String,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct SymbolTable {
+	symbols: Vec<Symbol>,
+	by_name: HashMap<String, usize>,
+	by_location: BTreeMap<(u16, u16), usize>,
+}
+
+impl SymbolTable {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn from_file(path: &Path) -> Result<Self, SymbolError> {
+		let source = std::fs::read_to_string(path).map_err(SymbolError::Read)?;
+		Self::parse(source.as_str())
+	}
+
+	pub fn parse(source: &str) -> Result<Self, SymbolError> {
+		let mut out = Self::new();
+
+		for (idx, line) in source.lines().enumerate() {
+			let line = match line.split_once(';') {
+				Some((content, _comment)) => content,
+				None => line,
+			}
+			.trim();
+
+			if line.is_empty() {
+				continue;
+			}
+
+			let malformed = || SymbolError::Malformed(idx + 1, line.to_string());
+
+			let (location, name) = line.split_once(char::is_whitespace).ok_or_else(malformed)?;
+			let (bank, address) = location.split_once(':').ok_or_else(malformed)?;
+			let bank = u16::from_str_radix(bank, 16).map_err(|_| malformed())?;
+			let address = u16::from_str_radix(address, 16).map_err(|_| malformed())?;
+
+			out.insert(Symbol { bank, address, name: name.trim().to_string() });
+		}
+
+		Ok(out)
+	}
+
+	pub fn insert(&mut self, symbol: Symbol) {
+		let idx = self.symbols.len();
+		self.by_name.insert(symbol.name.clone(), idx);
+		// When multiple labels share a location, the first one defined is kept as it
+		// is usually the global label rather than a local one
+		self.by_location.entry((symbol.bank, symbol.address)).or_insert(idx);
+		self.symbols.push(symbol);
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.symbols.is_empty()
+	}
+
+	pub fn len(&self) -> usize {
+		self.symbols.len()
+	}
+
+	pub fn lookup(&self, name: &str) -> Option<&Symbol> {
+		self.by_name.get(name).map(|idx| &self.symbols[*idx])
+	}
+
+	/// Returns the label placed exactly at `address` in `bank`
+	pub fn label_at(&self, bank: u16, address: u16) -> Option<&Symbol> {
+		self.by_location.get(&(bank, address)).map(|idx| &self.symbols[*idx])
+	}
+
+	/// Returns the closest label at or before `address` in `bank` along with
+	/// the offset of `address` from it. Labels are never resolved across a
+	/// memory region boundary, so an address in WRAM will not resolve to the
+	/// last label in VRAM.
+	pub fn nearest(&self, bank: u16, address: u16) -> Option<(&Symbol, u16)> {
+		let region_start = Self::region_start(address);
+
+		self.by_location
+			.range((bank, region_start)..=(bank, address))
+			.next_back()
+			.map(|(_, idx)| &self.symbols[*idx])
+			.map(|symbol| (symbol, address - symbol.address))
+	}
+
+	/// Formats `address` as `Label` or `Label+offset`, returning `None` if no
+	/// label precedes it
+	pub fn describe(&self, bank: u16, address: u16) -> Option<String> {
+		self.nearest(bank, address).map(|(symbol, offset)| match offset {
+			0 => symbol.name.clone(),
+			offset => format!("{}+{:#X}", symbol.name, offset),
+		})
+	}
+
+	fn region_start(address: u16) -> u16 {
+		match address {
+			0..=0x3FFF => 0,
+			0x4000..=0x7FFF => 0x4000,
+			0x8000..=0x9FFF => 0x8000,
+			0xA000..=0xBFFF => 0xA000,
+			0xC000..=0xDFFF => 0xC000,
+			0xE000..=0xFDFF => 0xE000,
+			0xFE00..=0xFEFF => 0xFE00,
+			0xFF00..=0xFF7F => 0xFF00,
+			0xFF80..=0xFFFF => 0xFF80,
+		}
+	}
+}
+
+#[test]
+fn test_parse_symbols() {
+	let table = SymbolTable::parse(
+		"; File generated by rgblink\n00:0150 Main\n00:0158 Main.loop\n03:4000 \
+		 BankedData ; trailing comment\n\n00:c000 wCounter\n",
+	)
+	.unwrap();
+
+	assert_eq!(table.len(), 4);
+	assert_eq!(table.lookup("Main.loop").map(|symbol| symbol.address), Some(0x158));
+	assert_eq!(table.label_at(3, 0x4000).map(|symbol| symbol.name.as_str()), Some("BankedData"));
+	assert_eq!(table.label_at(1, 0x4000), None);
+	assert_eq!(table.describe(0, 0x15A).as_deref(), Some("Main.loop+0x2"));
+	assert_eq!(table.describe(3, 0x4010).as_deref(), Some("BankedData+0x10"));
+	assert_eq!(table.describe(0, 0xD000).as_deref(), Some("wCounter+0x1000"));
+	assert_eq!(table.describe(0, 0x8000), None);
+
+	assert!(matches!(SymbolTable::parse("00:zz Broken"), Err(SymbolError::Malformed(1, _))));
+}
@@ -0,0 +1,67 @@
+use meowgb_core::{
+	gameboy::{
+		call_stack::{CallFrame, CallKind},
+		interrupts::InterruptSource,
+	},
+	setup_test_emulator,
+};
+
+#[test]
+fn test_call_and_ret_tracked() {
+	// 0x100: CALL 0x0105, 0x103: NOP, 0x104: NOP, 0x105: RET
+	let mut emulator = setup_test_emulator([0xCD, 0x05, 0x01, 0x00, 0x00, 0xC9]);
+
+	for _ in 0..6 {
+		emulator.tick_4();
+	}
+
+	assert_eq!(emulator.registers.pc, 0x105);
+	assert_eq!(
+		emulator.call_stack.frames().copied().collect::<Vec<_>>(),
+		[CallFrame { kind: CallKind::Call, origin: 0x100, target: 0x105, return_address: 0x103 }]
+	);
+
+	for _ in 0..4 {
+		emulator.tick_4();
+	}
+
+	assert_eq!(emulator.registers.pc, 0x103);
+	assert!(emulator.call_stack.is_empty());
+}
+
+#[test]
+fn test_untaken_call_not_tracked() {
+	// CALL NZ,0x0105 with Z set
+	let mut emulator = setup_test_emulator([0xC4, 0x05, 0x01]);
+	emulator.registers.set_zero(true);
+
+	for _ in 0..3 {
+		emulator.tick_4();
+	}
+
+	assert_eq!(emulator.registers.pc, 0x103);
+	assert!(emulator.call_stack.is_empty());
+}
+
+#[test]
+fn test_interrupt_dispatch_tracked() {
+	let mut emulator = setup_test_emulator([0x00, 0x00]);
+	emulator.interrupts.ime = true;
+	emulator.interrupts.interrupt_enable = 0b0000_0100;
+	emulator.interrupts.interrupt_flag = 0b0000_0100;
+
+	for _ in 0..5 {
+		emulator.tick_4();
+	}
+
+	assert_eq!(emulator.registers.pc, 0x50);
+
+	let frame = *emulator.call_stack.frames().last().unwrap();
+	assert_eq!(frame.kind, CallKind::Interrupt(Some(InterruptSource::Timer)));
+	assert_eq!((frame.origin, frame.return_address), (0x100, 0x100));
+
+	let record = *emulator.interrupt_history.to_vec().last().unwrap();
+	assert_eq!(record.source, Some(InterruptSource::Timer));
+	assert_eq!((record.vector, record.origin), (0x50, 0x100));
+	assert_eq!(record.cycle, emulator.cycle_count);
+}
@@ -0,0 +1,155 @@
+use std::path::Path;
+
+use meowgb_core::{emulator::Emulator, gameboy::movie::M_CYCLES_PER_FRAME, setup_test_emulator};
+
+fn read_rom(rom: &str) -> Vec<u8> {
+	std::fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("..").join(rom)).unwrap()
+}
+
+/// A cartridge with RAM which writes 0x42 to the start of it forever
+fn ram_program() -> Vec<u8> {
+	let mut rom = vec![0; 0x8000];
+	rom[0x149] = 2;
+	rom[0x100..0x107].copy_from_slice(&[
+		0x3E, 0x42, // LD A,0x42
+		0xEA, 0x00, 0xA0, // LD (0xA000),A
+		0x18, 0xFB, // JR -5
+	]);
+	rom
+}
+
+#[test]
+fn test_run_frame() {
+	let mut emulator = Emulator::new(
+		std::io::sink(),
+		Some(read_rom("test-roms/hacktix/framebuffer-roms/bully.gb")),
+	);
+
+	// The LCD is off for the first few frames, which end without drawing
+	assert!(!emulator.run_frame());
+	assert!((0..20).any(|_| emulator.run_frame()));
+	assert!(emulator.framebuffer() == emulator.gameboy().bus.ppu.write_fb());
+
+	let frames = emulator.run_cycles(M_CYCLES_PER_FRAME * 3);
+	assert!((2..=3).contains(&frames), "{} frames", frames);
+}
+
+#[test]
+fn test_step_instruction() {
+	let mut emulator = Emulator::from_gameboy(setup_test_emulator([
+		0x00, // NOP
+		0x3E, 0x20, // LD A,0x20
+		0xEA, 0x00, 0xC0, // LD (0xC000),A
+	]));
+
+	assert_eq!(emulator.step_instruction(), 1);
+	assert_eq!(emulator.step_instruction(), 2);
+	assert_eq!(emulator.step_instruction(), 4);
+	assert_eq!(emulator.gameboy().registers.pc, 0x106);
+	assert_eq!(emulator.gameboy().bus.memory.wram[0], 0x20);
+}
+
+#[test]
+fn test_reset() {
+	let mut emulator = Emulator::new(std::io::sink(), Some(ram_program()));
+	assert_eq!(emulator.cartridge_ram().map(<[u8]>::len), Some(0x2000));
+
+	emulator.run_cycles(100);
+	assert_eq!(emulator.cartridge_ram().unwrap()[0], 0x42);
+
+	emulator.reset();
+	assert_eq!(emulator.gameboy().registers.pc, 0x100);
+	assert_eq!(emulator.gameboy().cycle_count, 0);
+	assert_eq!(emulator.cartridge_ram().unwrap()[0], 0x42);
+
+	let rom = read_rom("test-roms/hacktix/framebuffer-roms/bully.gb");
+	let mut fresh = Emulator::new(std::io::sink(), Some(rom.clone()));
+	let mut reset = Emulator::new(std::io::sink(), Some(rom));
+	reset.run_cycles(12345);
+	reset.reset();
+
+	for _ in 0..5 {
+		fresh.run_frame();
+		reset.run_frame();
+		assert!(fresh.framebuffer() == reset.framebuffer());
+	}
+}
+
+#[test]
+fn test_reset_mapper() {
+	let mut rom = vec![0; 0x10000];
+	rom[0x147] = 3;
+	rom[0x148] = 1;
+	rom[0x149] = 2;
+	rom[0x100..0x111].copy_from_slice(&[
+		0x3E, 0x0A, // LD A,0x0A
+		0xEA, 0x00, 0x00, // LD (0x0000),A
+		0x3E, 0x02, // LD A,2
+		0xEA, 0x00, 0x20, // LD (0x2000),A
+		0x3E, 0x42, // LD A,0x42
+		0xEA, 0x00, 0xA0, // LD (0xA000),A
+		0x18, 0xFE, // JR -2
+	]);
+	let mut emulator = Emulator::new(std::io::sink(), Some(rom));
+	emulator.run_cycles(100);
+
+	let rom_bank = |emulator: &Emulator<_>| {
+		emulator.gameboy().bus.cartridge.as_ref().unwrap().rom_bank(0x4000)
+	};
+	assert_eq!(rom_bank(&emulator), 2);
+
+	emulator.reset();
+	assert_eq!(rom_bank(&emulator), 1);
+	assert_eq!(emulator.cartridge_ram().unwrap()[0], 0x42);
+	assert_eq!(emulator.gameboy().debug_read_u8(0xA000), 0xFF);
+}
+
+/// A cartridge which halts waiting for VBlank forever, like most games do
+/// once they are done with a frame
+fn halting_program() -> Vec<u8> {
+	let mut rom = vec![0; 0x8000];
+	rom[0x40] = 0xD9; // RETI
+	rom[0x100..0x108].copy_from_slice(&[
+		0x3E, 0x01, // LD A,0x01
+		0xE0, 0xFF, // LDH (0xFF),A
+		0xFB, // EI
+		0x76, // HALT
+		0x18, 0xFD, // JR -3
+	]);
+	rom
+}
+
+#[test]
+fn test_skip_halted() {
+	let mut skipping = Emulator::new(std::io::sink(), Some(halting_program()));
+	let mut ticking = Emulator::new(std::io::sink(), Some(halting_program()));
+	// Leaves most of each line idle rather than only HBlank
+	skipping.gameboy_mut().bus.ppu.scanline_renderer = true;
+	ticking.gameboy_mut().bus.ppu.scanline_renderer = true;
+
+	let mut advances = 0;
+	let mut ran = 0;
+	while ran < M_CYCLES_PER_FRAME * 3 {
+		let (advanced, redraw) = skipping.advance(M_CYCLES_PER_FRAME * 3 - ran);
+		ran += advanced;
+		advances += 1;
+		let mut ticked_redraw = false;
+		for _ in 0..advanced {
+			ticked_redraw |= ticking.tick();
+		}
+		assert_eq!(redraw, ticked_redraw);
+	}
+
+	assert_eq!(ran, M_CYCLES_PER_FRAME * 3);
+	assert!(advances < ran / 4, "{} advances for {} M-cycles", advances, ran);
+
+	skipping.gameboy_mut().catch_up();
+	ticking.gameboy_mut().catch_up();
+	let (skipped, ticked) = (skipping.gameboy(), ticking.gameboy());
+	assert_eq!(skipped.cycle_count, ticked.cycle_count);
+	assert_eq!(skipped.registers.pc, ticked.registers.pc);
+	assert_eq!(skipped.bus.scheduler.now, ticked.bus.scheduler.now);
+	assert_eq!(skipped.bus.ppu.registers.ly, ticked.bus.ppu.registers.ly);
+	assert_eq!(skipped.bus.timer.internal_div(), ticked.bus.timer.internal_div());
+	assert!(skipping.framebuffer() == ticking.framebuffer());
+}
@@ -0,0 +1,60 @@
+use meowgb_core::gameboy::link::LinkedPair;
+
+/// A ROM which transfers `sb` with SC set to `sc`, waits for the transfer to
+/// finish and stores what was shifted in to 0xC000
+fn transfer_rom(sb: u8, sc: u8) -> Vec<u8> {
+	#[rustfmt::skip]
+	let program = [
+		// 0x100: LD A,sb; LDH (SB),A; LD A,sc; LDH (SC),A
+		0x3E, sb, 0xE0, 0x01, 0x3E, sc, 0xE0, 0x02,
+		// 0x108: LDH A,(SC); BIT 7,A; JR NZ,0x108
+		0xF0, 0x02, 0xCB, 0x7F, 0x20, 0xFA,
+		// 0x10E: LDH A,(SB); LD (0xC000),A; JR -2
+		0xF0, 0x01, 0xEA, 0x00, 0xC0, 0x18, 0xFE,
+	];
+
+	let mut rom = vec![0; 0x8000];
+	rom[0x100..0x100 + program.len()].copy_from_slice(&program);
+	rom
+}
+
+#[test]
+fn test_transfer_in_both_directions() {
+	let mut pair = LinkedPair::new(Some(transfer_rom(0x42, 0x81)), Some(transfer_rom(0x99, 0x80)));
+
+	for _ in 0..1000 {
+		pair.tick_4();
+	}
+
+	assert_eq!(pair.left.debug_read_u8(0xC000), 0x99);
+	assert_eq!(pair.right.debug_read_u8(0xC000), 0x42);
+	assert_eq!(pair.left_sent(), [0x42]);
+	assert_eq!(pair.right_sent(), [0x99]);
+	assert!(pair.has_sent(&[0x42], &[0x99]));
+	assert!(!pair.has_sent(&[0x42], &[]));
+}
+
+#[test]
+fn test_right_clocks_transfer() {
+	let mut pair = LinkedPair::new(Some(transfer_rom(0x42, 0x80)), Some(transfer_rom(0x99, 0x81)));
+
+	for _ in 0..1000 {
+		pair.tick_4();
+	}
+
+	assert_eq!(pair.left.debug_read_u8(0xC000), 0x99);
+	assert_eq!(pair.right.debug_read_u8(0xC000), 0x42);
+}
+
+#[test]
+fn test_transfer_without_listener() {
+	let mut pair = LinkedPair::new(Some(transfer_rom(0x42, 0x81)), Some(transfer_rom(0x99, 0x01)));
+
+	for _ in 0..1000 {
+		pair.tick_4();
+	}
+
+	assert_eq!(pair.left.debug_read_u8(0xC000), 0xFF);
+	assert_eq!(pair.left_sent(), [0x42]);
+	assert!(pair.right_sent().is_empty());
+}
@@ -67,3 +67,14 @@ fn test_halt() {
 	assert_eq!(emulator.registers, expected_register_state);
 	assert!(emulator.halt);
 }
+
+#[test]
+fn test_ld_b_b_software_breakpoint() {
+	let mut emulator = setup_test_emulator([0x00, 0x40, 0x00]);
+
+	emulator.tick_4();
+	assert!(!emulator.software_breakpoint);
+	emulator.tick_4();
+	assert!(emulator.software_breakpoint);
+	assert_eq!(emulator.registers.pc, 0x102);
+}
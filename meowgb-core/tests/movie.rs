@@ -0,0 +1,175 @@
+use std::path::Path;
+
+use meowgb_core::{
+	emulator::Emulator,
+	gameboy::{
+		joypad::Joypad,
+		movie::{Movie, MovieError, MovieRecorder, MovieStart},
+		state::StateError,
+		Gameboy,
+	},
+	setup_test_emulator,
+};
+
+fn read_rom(rom: &str) -> Vec<u8> {
+	std::fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("..").join(rom)).unwrap()
+}
+
+fn run_frames<S: meowgb_core::gameboy::serial::LinkDevice>(
+	emulator: &mut Emulator<S>,
+	frames: usize,
+) -> Vec<Vec<u8>> {
+	let mut out = Vec::new();
+	while out.len() < frames {
+		if emulator.run_frame() {
+			out.push(emulator.framebuffer().to_vec());
+		}
+	}
+	out
+}
+
+/// Selects the directions then copies P1 into 0xC000 forever
+fn joypad_program() -> Emulator<std::io::Stdout> {
+	Emulator::from_gameboy(setup_test_emulator([
+		0x3E, 0x20, // LD A,0x20
+		0xE0, 0x00, // LDH (0x00),A
+		0xF0, 0x00, // LDH A,(0x00)
+		0xEA, 0x00, 0xC0, // LD (0xC000),A
+		0x18, 0xF9, // JR -7
+	]))
+}
+
+fn buttons_for_frame(frame: usize) -> u8 {
+	[0, Joypad::UP, Joypad::UP | Joypad::LEFT, 0, Joypad::DOWN, Joypad::RIGHT][frame / 3 % 6]
+}
+
+/// Records `frames` frames of [`joypad_program`], changing the buttons every
+/// few frames
+fn record_movie(frames: usize) -> Movie {
+	let mut emulator = joypad_program();
+	let mut recorder = MovieRecorder::new(emulator.gameboy());
+
+	while recorder.frames() < frames {
+		if recorder.at_frame_start() {
+			emulator.set_buttons(buttons_for_frame(recorder.frames()));
+		}
+		emulator.tick_recording(&mut recorder);
+	}
+
+	recorder.finish()
+}
+
+fn play_movie(movie: Movie) -> Result<(), MovieError> {
+	let mut emulator = joypad_program();
+	let mut player = emulator.play_movie(movie)?;
+
+	while !player.finished() {
+		emulator.tick_playing(&mut player)?;
+	}
+
+	Ok(())
+}
+
+#[test]
+fn test_save_state_round_trip() {
+	let mut emulator = Emulator::new(
+		std::io::sink(),
+		Some(read_rom("test-roms/hacktix/framebuffer-roms/bully.gb")),
+	);
+	run_frames(&mut emulator, 10);
+
+	let state = emulator.save_state();
+	let expected = run_frames(&mut emulator, 10);
+	let (registers, cycle_count) = (emulator.gameboy().registers, emulator.gameboy().cycle_count);
+
+	emulator.load_state(&state).unwrap();
+	assert!(run_frames(&mut emulator, 10) == expected);
+	assert_eq!(emulator.gameboy().registers, registers);
+	assert_eq!(emulator.gameboy().cycle_count, cycle_count);
+}
+
+#[test]
+fn test_load_state_restarts_frame() {
+	let rom = read_rom("test-roms/hacktix/framebuffer-roms/bully.gb");
+	let mut emulator = Emulator::new(std::io::sink(), Some(rom.clone()));
+	run_frames(&mut emulator, 10);
+	emulator.run_cycles(1000);
+	let state = emulator.save_state();
+
+	// Part way through a frame, showing a different one
+	let mut loaded = Emulator::new(std::io::sink(), Some(rom.clone()));
+	loaded.run_cycles(5000);
+	loaded.load_state(&state).unwrap();
+
+	let mut gameboy = Gameboy::new(std::io::sink(), Some(rom));
+	gameboy.load_state(&state).unwrap();
+	let mut fresh = Emulator::from_gameboy(gameboy);
+
+	assert!(loaded.framebuffer() == fresh.framebuffer());
+	for _ in 0..3 {
+		assert_eq!(loaded.run_frame(), fresh.run_frame());
+		assert_eq!(loaded.gameboy().cycle_count, fresh.gameboy().cycle_count);
+		assert!(loaded.framebuffer() == fresh.framebuffer());
+	}
+}
+
+#[test]
+fn test_load_state_errors() {
+	let bully = Gameboy::new(
+		std::io::sink(),
+		Some(read_rom("test-roms/hacktix/framebuffer-roms/bully.gb")),
+	);
+	let mut gameboy = Gameboy::new(
+		std::io::sink(),
+		Some(read_rom("test-roms/hacktix/framebuffer-roms/strikethrough.gb")),
+	);
+
+	let state = bully.save_state();
+	assert!(matches!(gameboy.load_state(&state), Err(StateError::WrongGame)));
+	assert!(matches!(gameboy.load_state(b"not a save state"), Err(StateError::Magic)));
+
+	let mut state = gameboy.save_state();
+	state.truncate(state.len() / 2);
+	assert!(matches!(gameboy.load_state(&state), Err(StateError::Decode(_))));
+}
+
+#[test]
+fn test_movie_playback() {
+	let movie = Movie::decode(&record_movie(40).encode()).unwrap();
+	assert!(matches!(movie.start, MovieStart::SaveState(_)));
+	assert_eq!(movie.frames.len(), 40);
+	assert_eq!(movie.frames[4].buttons, Joypad::UP);
+
+	play_movie(movie).unwrap();
+}
+
+#[test]
+fn test_movie_desync() {
+	let mut movie = record_movie(40);
+	movie.frames[10].buttons = Joypad::DOWN;
+
+	match play_movie(movie) {
+		Err(MovieError::Desync { frame, .. }) => assert_eq!(frame, 10),
+		other => panic!("expected a desync, got {:?}", other),
+	}
+}
+
+#[test]
+fn test_movie_power_on() {
+	let rom = read_rom("test-roms/hacktix/framebuffer-roms/bully.gb");
+
+	let mut emulator = Emulator::new(std::io::sink(), Some(rom.clone()));
+	let mut recorder = MovieRecorder::new(emulator.gameboy());
+	while recorder.frames() < 5 {
+		emulator.tick_recording(&mut recorder);
+	}
+	let movie = recorder.finish();
+	assert_eq!(movie.start, MovieStart::PowerOn);
+
+	let mut emulator = Emulator::new(std::io::sink(), Some(rom));
+	emulator.tick();
+	assert!(matches!(emulator.play_movie(movie.clone()), Err(MovieError::NotAtPowerOn)));
+
+	let mut wrong_game = joypad_program();
+	assert!(matches!(wrong_game.play_movie(movie), Err(MovieError::WrongGame)));
+}
@@ -0,0 +1,72 @@
+use meowgb_core::{
+	gameboy::ppu::{
+		Color, OAMEntry, Palette, TileMap, PIXEL_SIZE, TILE_MAP_SIZE, TILE_SHEET_WIDTH,
+	},
+	setup_test_emulator,
+};
+
+fn pixel(buffer: &[u8], width: usize, x: usize, y: usize) -> &[u8] {
+	let base = ((y * width) + x) * PIXEL_SIZE;
+	&buffer[base..base + PIXEL_SIZE]
+}
+
+#[test]
+fn test_render_tiles() {
+	let mut emulator = setup_test_emulator([0x00]);
+
+	// Tile 1, first row: colour ids 3 1 2 0 0 0 0 0
+	emulator.bus.ppu.vram[0x10] = 0b1100_0000;
+	emulator.bus.ppu.vram[0x11] = 0b1010_0000;
+	// Tile 383, last row: all colour 3
+	emulator.bus.ppu.vram[0x17FE] = 0xFF;
+	emulator.bus.ppu.vram[0x17FF] = 0xFF;
+
+	let tiles = emulator.bus.ppu.render_tiles(&Palette::new_obp());
+	let width = TILE_SHEET_WIDTH * 8;
+
+	assert_eq!(pixel(&tiles, width, 8, 0), Color::Black.rgba());
+	assert_eq!(pixel(&tiles, width, 9, 0), Color::LGray.rgba());
+	assert_eq!(pixel(&tiles, width, 10, 0), Color::DGray.rgba());
+	assert_eq!(pixel(&tiles, width, 11, 0), Color::White.rgba());
+	assert_eq!(pixel(&tiles, width, width - 1, 23 * 8 + 7), Color::Black.rgba());
+}
+
+#[test]
+fn test_render_tile_map() {
+	let mut emulator = setup_test_emulator([0x00]);
+	emulator.bus.ppu.bgp.write(0b1110_0100);
+	emulator.bus.ppu.registers.lcdc = 0b0001_0000;
+
+	// Tile 2 is solid colour 2, placed at (1, 1) in the high tile map
+	for row in 0..8 {
+		emulator.bus.ppu.vram[0x20 + (row * 2) + 1] = 0xFF;
+	}
+	emulator.bus.ppu.vram[0x1C00 + 33] = 2;
+
+	let map = emulator.bus.ppu.render_tile_map(TileMap::High);
+	assert_eq!(pixel(&map, TILE_MAP_SIZE, 8, 8), Color::DGray.rgba());
+	assert_eq!(pixel(&map, TILE_MAP_SIZE, 15, 15), Color::DGray.rgba());
+	assert_eq!(pixel(&map, TILE_MAP_SIZE, 16, 8), Color::White.rgba());
+	assert_eq!(emulator.bus.ppu.render_tile_map(TileMap::Low)[..PIXEL_SIZE], *Color::White.rgba());
+}
+
+#[test]
+fn test_render_sprite_flipped() {
+	let mut emulator = setup_test_emulator([0x00]);
+	emulator.bus.ppu.obp[0].write(0b0000_1100);
+	emulator.bus.ppu.obp[1].write(0b1110_0100);
+
+	// Tile 4, first row: only the leftmost pixel is colour 1
+	emulator.bus.ppu.vram[0x40] = 0b1000_0000;
+	emulator.bus.ppu.oam[4..8].copy_from_slice(&[16, 8, 4, 0b0111_0000]);
+
+	let entry = emulator.bus.ppu.oam_entries()[1];
+	assert_eq!((entry.y, entry.x, entry.tile_idx), (16, 8, 4));
+
+	let sprite = emulator.bus.ppu.render_sprite(&entry);
+	assert_eq!(pixel(&sprite, 8, 7, 7), Color::LGray.rgba());
+	assert_eq!(pixel(&sprite, 8, 0, 0), [0; PIXEL_SIZE]);
+
+	let unflipped = emulator.bus.ppu.render_sprite(&OAMEntry { flags: 0, ..entry });
+	assert_eq!(pixel(&unflipped, 8, 0, 0), Color::Black.rgba());
+}
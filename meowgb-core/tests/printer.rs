@@ -0,0 +1,139 @@
+use meowgb_core::gameboy::{
+	ppu::{Color, PIXEL_SIZE},
+	printer::{Printer, PRINT_WIDTH},
+	serial::LinkDevice,
+};
+
+/// Sends a packet to the printer, returning what it answered for each byte
+fn send_packet(printer: &mut Printer, command: u8, compressed: bool, data: &[u8]) -> Vec<u8> {
+	let mut bytes = vec![0x88, 0x33, command, compressed as u8];
+	bytes.extend_from_slice(&(data.len() as u16).to_le_bytes());
+	bytes.extend_from_slice(data);
+
+	let checksum = bytes[2..].iter().fold(0u16, |sum, byte| sum.wrapping_add(*byte as u16));
+	bytes.extend_from_slice(&checksum.to_le_bytes());
+	bytes.extend_from_slice(&[0x00, 0x00]);
+
+	bytes.into_iter().map(|byte| printer.exchange(byte)).collect()
+}
+
+/// The status the printer answered a packet with
+fn status(replies: &[u8]) -> u8 {
+	let [.., alive, status] = replies else {
+		unreachable!();
+	};
+	assert_eq!(*alive, 0x81);
+	*status
+}
+
+/// Two rows of tiles where every pixel of tile `n` has colour `n % 4`
+fn tile_rows() -> Vec<u8> {
+	(0..40usize)
+		.flat_map(|tile| {
+			let (low, high) = match tile % 4 {
+				0 => (0x00, 0x00),
+				1 => (0xFF, 0x00),
+				2 => (0x00, 0xFF),
+				_ => (0xFF, 0xFF),
+			};
+			[low, high].repeat(8)
+		})
+		.collect()
+}
+
+fn pixel(rgba: &[u8], x: usize, y: usize) -> &[u8] {
+	let base = (y * PRINT_WIDTH as usize + x) * PIXEL_SIZE;
+	&rgba[base..base + PIXEL_SIZE]
+}
+
+#[test]
+fn test_print() {
+	let mut printer = Printer::new();
+
+	assert_eq!(status(&send_packet(&mut printer, 0x01, false, &[])), 0x00);
+	assert_eq!(status(&send_packet(&mut printer, 0x04, false, &tile_rows())), 0x08);
+	assert_eq!(status(&send_packet(&mut printer, 0x04, false, &[])), 0x08);
+	assert_eq!(status(&send_packet(&mut printer, 0x02, false, &[1, 0x13, 0xE4, 0x40])), 0x02);
+
+	let printed = printer.take_printed();
+	assert_eq!(printed.len(), 1);
+	let strip = &printed[0];
+	assert_eq!((strip.height, strip.margin_before, strip.margin_after), (16, 1, 3));
+	assert_eq!(pixel(&strip.rgba, 0, 0), Color::White.rgba());
+	assert_eq!(pixel(&strip.rgba, 8, 3), Color::LGray.rgba());
+	assert_eq!(pixel(&strip.rgba, 16, 7), Color::DGray.rgba());
+	assert_eq!(pixel(&strip.rgba, 159, 15), Color::Black.rgba());
+
+	// Busy for a while after printing
+	assert_eq!(status(&send_packet(&mut printer, 0x0F, false, &[])), 0x02);
+	assert_eq!(status(&send_packet(&mut printer, 0x0F, false, &[])), 0x00);
+}
+
+#[test]
+fn test_print_palette() {
+	let mut printer = Printer::new();
+
+	send_packet(&mut printer, 0x04, false, &tile_rows());
+	send_packet(&mut printer, 0x02, false, &[1, 0x00, 0x1B, 0x40]);
+
+	let strip = &printer.take_printed()[0];
+	assert_eq!(pixel(&strip.rgba, 0, 0), Color::Black.rgba());
+	assert_eq!(pixel(&strip.rgba, 8, 0), Color::DGray.rgba());
+}
+
+#[test]
+fn test_compressed_data() {
+	// Tiles of a single byte repeated as runs, the rest as literals
+	let uncompressed = tile_rows();
+	let mut compressed = Vec::new();
+	for tile in uncompressed.chunks_exact(16) {
+		match tile.iter().all(|byte| *byte == tile[0]) {
+			true => compressed.extend_from_slice(&[0x80 | 14, tile[0]]),
+			false => {
+				compressed.push(15);
+				compressed.extend_from_slice(tile);
+			}
+		}
+	}
+
+	let mut expected = Printer::new();
+	send_packet(&mut expected, 0x04, false, &uncompressed);
+	send_packet(&mut expected, 0x02, false, &[1, 0x00, 0xE4, 0x40]);
+
+	let mut printer = Printer::new();
+	send_packet(&mut printer, 0x04, true, &compressed);
+	send_packet(&mut printer, 0x02, false, &[1, 0x00, 0xE4, 0x40]);
+
+	assert_eq!(printer.take_printed(), expected.take_printed());
+}
+
+#[test]
+fn test_checksum_error() {
+	let mut printer = Printer::new();
+
+	let mut replies = Vec::new();
+	for byte in [0x88, 0x33, 0x0F, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00] {
+		replies.push(printer.exchange(byte));
+	}
+
+	assert_eq!(status(&replies), 0x01);
+	assert_eq!(status(&send_packet(&mut printer, 0x0F, false, &[])), 0x00);
+}
+
+#[test]
+fn test_png() {
+	let mut printer = Printer::new();
+	send_packet(&mut printer, 0x04, false, &tile_rows());
+	send_packet(&mut printer, 0x02, false, &[1, 0x00, 0xE4, 0x40]);
+
+	let strip = &printer.take_printed()[0];
+	let mut png = Vec::new();
+	strip.encode_png(&mut png).unwrap();
+
+	let mut reader = png::Decoder::new(png.as_slice()).read_info().unwrap();
+	let mut decoded = vec![0; reader.output_buffer_size()];
+	let info = reader.next_frame(&mut decoded).unwrap();
+
+	assert_eq!((info.width, info.height), (PRINT_WIDTH, 16));
+	assert_eq!(decoded, strip.rgba);
+}
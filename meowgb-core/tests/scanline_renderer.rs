@@ -0,0 +1,93 @@
+use std::path::Path;
+
+use meowgb_core::{gameboy::Gameboy, setup_test_emulator};
+
+fn assert_frames_match<S: meowgb_core::gameboy::serial::LinkDevice>(
+	dot: &mut Gameboy<S>,
+	scanline: &mut Gameboy<S>,
+	frames: usize,
+) {
+	let mut frame = 0;
+	while frame < frames {
+		let redraw = dot.tick();
+		assert_eq!(redraw, scanline.tick());
+
+		if redraw {
+			assert!(dot.bus.ppu.write_fb() == scanline.bus.ppu.write_fb(), "frame {frame} differs");
+			frame += 1;
+		}
+	}
+}
+
+/// Runs `rom` with both PPU renderers side by side, checking every frame they
+/// draw is identical
+fn assert_renderers_match(rom: &str, frames: usize) {
+	let rom = std::fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("..").join(rom)).unwrap();
+
+	let mut dot = Gameboy::new(std::io::sink(), Some(rom.clone()));
+	let mut scanline = Gameboy::new(std::io::sink(), Some(rom));
+	scanline.bus.ppu.scanline_renderer = true;
+
+	assert_frames_match(&mut dot, &mut scanline, frames);
+}
+
+#[test]
+fn test_scanline_renderer_bully() {
+	assert_renderers_match("test-roms/hacktix/framebuffer-roms/bully.gb", 30);
+}
+
+#[test]
+fn test_scanline_renderer_strikethrough() {
+	assert_renderers_match("test-roms/hacktix/framebuffer-roms/strikethrough.gb", 30);
+}
+
+/// Every `framebuffer` ROM in the suite manifest, for long enough that each
+/// has finished drawing its result
+#[test]
+fn test_scanline_renderer_framebuffer_suite() {
+	let manifest =
+		std::fs::read_to_string(Path::new(env!("CARGO_MANIFEST_DIR")).join("../tests.toml"))
+			.unwrap();
+	let manifest: toml::Table = toml::from_str(&manifest).unwrap();
+
+	let roms = manifest["test"]
+		.as_array()
+		.unwrap()
+		.iter()
+		.filter(|test| test["mode"].as_str() == Some("framebuffer"))
+		.map(|test| test["rom"].as_str().unwrap())
+		.collect::<Vec<_>>();
+	assert!(!roms.is_empty());
+
+	for rom in roms {
+		assert_renderers_match(rom, 300);
+	}
+}
+
+#[test]
+fn test_scanline_renderer_mid_line_writes() {
+	#[rustfmt::skip]
+	let program = [
+		// 0x100: wait for mode 3
+		0xF0, 0x41, 0xE6, 0x03, 0xFE, 0x03, 0x20, 0xF8,
+		// 0x108: SCX += 1, BGP rotated left
+		0xF0, 0x43, 0x3C, 0xE0, 0x43, 0xF0, 0x47, 0x07, 0xE0, 0x47,
+		// 0x112: wait for HBlank, then loop
+		0xF0, 0x41, 0xE6, 0x03, 0x20, 0xFA, 0x18, 0xE6,
+	];
+
+	let mut dot = setup_test_emulator(program);
+	let mut scanline = setup_test_emulator(program);
+	scanline.bus.ppu.scanline_renderer = true;
+
+	// Tile data and a background map which differ from pixel to pixel
+	for emulator in [&mut dot, &mut scanline] {
+		let mut seed = 0x1234u16;
+		for byte in emulator.bus.ppu.vram.iter_mut() {
+			seed = seed.wrapping_mul(25173).wrapping_add(13849);
+			*byte = (seed >> 8) as u8;
+		}
+	}
+
+	assert_frames_match(&mut dot, &mut scanline, 5);
+}
@@ -0,0 +1,91 @@
+use meowgb_core::{gameboy::serial::LinkDevice, setup_linked_test_emulator};
+
+/// A device which answers every transfer with `reply`, clocking one itself
+/// after `clock_after` polls
+#[derive(Default)]
+struct ScriptedDevice {
+	reply: u8,
+	clock_after: Option<usize>,
+	polls: usize,
+	stopped: usize,
+	received: Vec<u8>,
+}
+
+impl LinkDevice for ScriptedDevice {
+	fn exchange(&mut self, byte: u8) -> u8 {
+		self.received.push(byte);
+		self.reply
+	}
+
+	fn poll_external(&mut self, byte: u8) -> Option<u8> {
+		self.polls += 1;
+		match self.clock_after {
+			Some(polls) if self.polls > polls => {
+				self.clock_after = None;
+				Some(self.exchange(byte))
+			}
+			_ => None,
+		}
+	}
+
+	fn stop_polling(&mut self) {
+		self.stopped += 1;
+	}
+}
+
+/// LD A,0x42; LDH (SB),A; LD A,`sc`; LDH (SC),A; JR -2
+fn transfer_program(sc: u8) -> [u8; 10] {
+	[0x3E, 0x42, 0xE0, 0x01, 0x3E, sc, 0xE0, 0x02, 0x18, 0xFE]
+}
+
+#[test]
+fn test_internal_clock_exchanges_bytes() {
+	let device = ScriptedDevice { reply: 0x99, ..Default::default() };
+	let mut emulator = setup_linked_test_emulator(device, transfer_program(0x81));
+
+	for _ in 0..200 {
+		emulator.tick_4();
+	}
+
+	assert_eq!(emulator.bus.serial.device.received, [0x42]);
+	assert_eq!(emulator.bus.serial.sb, 0x99);
+	assert!(emulator.interrupts.read_if_serial());
+	assert_eq!(emulator.bus.serial.get_sc() & 0x80, 0);
+}
+
+#[test]
+fn test_external_clock_waits_for_device() {
+	let device = ScriptedDevice { reply: 0x99, clock_after: Some(500), ..Default::default() };
+	let mut emulator = setup_linked_test_emulator(device, transfer_program(0x80));
+
+	for _ in 0..400 {
+		emulator.tick_4();
+	}
+
+	assert!(emulator.bus.serial.device.received.is_empty());
+	assert_eq!(emulator.bus.serial.sb, 0x42);
+	assert!(!emulator.interrupts.read_if_serial());
+
+	for _ in 0..200 {
+		emulator.tick_4();
+	}
+
+	assert_eq!(emulator.bus.serial.device.received, [0x42]);
+	assert_eq!(emulator.bus.serial.sb, 0x99);
+	assert!(emulator.interrupts.read_if_serial());
+}
+
+#[test]
+fn test_clearing_transfer_stops_polling() {
+	// LD A,0x42; LDH (SB),A; LD A,0x80; LDH (SC),A; XOR A; LDH (SC),A; JR -2
+	let program = [0x3E, 0x42, 0xE0, 0x01, 0x3E, 0x80, 0xE0, 0x02, 0xAF, 0xE0, 0x02, 0x18, 0xFE];
+	let mut emulator = setup_linked_test_emulator(ScriptedDevice::default(), program);
+
+	for _ in 0..200 {
+		emulator.tick_4();
+	}
+
+	assert!(emulator.bus.serial.device.polls > 0);
+	assert_eq!(emulator.bus.serial.device.stopped, 1);
+	assert!(emulator.bus.serial.device.received.is_empty());
+}
@@ -0,0 +1,27 @@
+#!/bin/bash
+# Copies the first few cases of a handful of opcode files from a checkout of
+# https://github.com/SingleStepTests/sm83 into tests/sm83/subset, which
+# sm83_single_step.rs runs on every `cargo test`
+
+set -e
+
+if [ -z "$1" ] ; then
+   echo "Usage: $0 PATH_TO_SM83_V1 [CASES_PER_FILE]"
+   exit 1
+fi
+
+SOURCE="$1"
+COUNT="${2:-25}"
+DEST="$(dirname "$0")/subset"
+
+mkdir -p "$DEST"
+
+# Loads, ALU, 16-bit arithmetic, jumps, calls, returns, stack and CB opcodes
+for FILE in "00" "06" "0a" "20" "22" "3e" "80" "c1" "c4" "c9" "e0" "f8" "cb 11" ; do
+   echo "Vendoring $FILE.json"
+   {
+      echo "["
+      jq -c ".[:$COUNT][]" "$SOURCE/$FILE.json" | sed '$!s/$/,/'
+      echo "]"
+   } > "$DEST/$FILE.json"
+done
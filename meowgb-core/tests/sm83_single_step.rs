@@ -0,0 +1,261 @@
+//! Runner for the SM83 "SingleStepTests" JSON format. Each test sets up the
+//! registers and memory, runs a single instruction on a flat 64 KiB bus and
+//! checks the final state and the bus activity of every M-cycle.
+//!
+//! The handwritten `tests/sm83/fixtures.json` is always run, as is the subset
+//! of the real suite `tests/sm83/vendor-subset.sh` copies into
+//! `tests/sm83/subset`, which fails if it has not been vendored. The full
+//! suite isn't vendored, its test is ignored unless asked for with
+//! `cargo test -- --ignored` and reads it from the `v1` directory in
+//! `SM83_TESTS_DIR`, or from `meowgb-core/tests/sm83/v1`.
+
+use std::path::{Path, PathBuf};
+
+use meowgb_core::{
+	gameboy::{
+		cpu::{tick_cpu, Sm83},
+		test_bus::{BusAccess, BusActivity, TestBus},
+	},
+	setup_test_bus_emulator,
+};
+use serde::Deserialize;
+
+/// STOP and HALT wait on hardware state outside of the CPU, which the tests
+/// don't model
+const SKIPPED_OPCODES: &[u8] = &[0x10, 0x76];
+
+/// Longer than any instruction, in case one never finishes
+const MAX_M_CYCLES: usize = 8;
+
+/// How many failures are listed before the rest are only counted
+const MAX_REPORTED_FAILURES: usize = 20;
+
+#[derive(Debug, Deserialize)]
+struct SingleStepTest {
+	name: String,
+	initial: CpuState,
+	#[serde(rename = "final")]
+	expected: CpuState,
+	/// One entry per M-cycle, `null` (or flags of `---`) if there was no
+	/// memory access
+	cycles: Vec<Option<(u16, u8, String)>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CpuState {
+	pc: u16,
+	sp: u16,
+	a: u8,
+	b: u8,
+	c: u8,
+	d: u8,
+	e: u8,
+	f: u8,
+	h: u8,
+	l: u8,
+	ime: u8,
+	ie: Option<u8>,
+	/// Whether an EI is waiting to take effect, when missing a pending EI
+	/// counts as IME being set
+	ei: Option<u8>,
+	ram: Vec<(u16, u8)>,
+}
+
+fn expected_activity(cycle: &Option<(u16, u8, String)>) -> Option<BusActivity> {
+	let (address, value, flags) = cycle.as_ref()?;
+
+	let access = match flags.as_bytes() {
+		[b'r', ..] => BusAccess::Read,
+		[_, b'w', ..] => BusAccess::Write,
+		_ => return None,
+	};
+
+	Some(BusActivity { address: *address, value: *value, access })
+}
+
+fn setup(test: &SingleStepTest) -> Sm83<TestBus> {
+	let mut cpu = setup_test_bus_emulator();
+	let initial = &test.initial;
+
+	cpu.registers.a = initial.a;
+	cpu.registers.f = initial.f;
+	cpu.registers.b = initial.b;
+	cpu.registers.c = initial.c;
+	cpu.registers.d = initial.d;
+	cpu.registers.e = initial.e;
+	cpu.registers.h = initial.h;
+	cpu.registers.l = initial.l;
+	cpu.registers.sp = initial.sp;
+	cpu.registers.pc = initial.pc;
+
+	cpu.interrupts.ime = initial.ime != 0;
+	cpu.interrupts.ei_queued = initial.ei.unwrap_or_default() != 0;
+	cpu.interrupts.interrupt_enable = initial.ie.unwrap_or_default();
+	cpu.interrupts.interrupt_flag = 0b1110_0000;
+
+	for (address, value) in initial.ram.iter().copied() {
+		cpu.bus.memory[address as usize] = value;
+	}
+
+	// The tests start with the opcode already fetched, as it would be at the
+	// end of the previous instruction
+	cpu.registers.mem_read_hold = Some(cpu.bus.memory[initial.pc as usize]);
+
+	cpu
+}
+
+fn run_test(test: &SingleStepTest) -> Result<(), String> {
+	let mut cpu = setup(test);
+	let mut activity = Vec::new();
+
+	for _ in 0..MAX_M_CYCLES {
+		tick_cpu(&mut cpu);
+
+		assert!(cpu.bus.activity.len() <= 1, "more than one memory access in an M-cycle");
+		activity.push(cpu.bus.activity.pop());
+
+		if cpu.registers.cycle == 0 && cpu.registers.current_opcode.is_none() {
+			break;
+		}
+	}
+
+	let mut errors = Vec::new();
+	let expected = &test.expected;
+	let registers = &cpu.registers;
+
+	macro_rules! check {
+		($name:literal, $actual:expr, $expected:expr) => {
+			if $actual != $expected {
+				errors.push(format!("{}: expected {:#X}, found {:#X}", $name, $expected, $actual));
+			}
+		};
+	}
+
+	check!("A", registers.a, expected.a);
+	check!("F", registers.f, expected.f);
+	check!("B", registers.b, expected.b);
+	check!("C", registers.c, expected.c);
+	check!("D", registers.d, expected.d);
+	check!("E", registers.e, expected.e);
+	check!("H", registers.h, expected.h);
+	check!("L", registers.l, expected.l);
+	check!("SP", registers.sp, expected.sp);
+	// The tests' PC is one past the next opcode, which has already been fetched
+	check!("PC", registers.pc.wrapping_add(1), expected.pc);
+
+	match expected.ei {
+		Some(ei) => {
+			check!("IME", cpu.interrupts.ime as u8, expected.ime);
+			check!("EI", cpu.interrupts.ei_queued as u8, ei);
+		}
+		None => {
+			let ime = cpu.interrupts.ime || cpu.interrupts.ei_queued;
+			check!("IME", ime as u8, expected.ime);
+		}
+	}
+
+	if let Some(ie) = expected.ie {
+		check!("IE", cpu.interrupts.interrupt_enable, ie);
+	}
+
+	for (address, value) in expected.ram.iter().copied() {
+		let actual = cpu.bus.memory[address as usize];
+		if actual != value {
+			errors.push(format!(
+				"[{:#06X}]: expected {:#04X}, found {:#04X}",
+				address, value, actual
+			));
+		}
+	}
+
+	let expected_activity = test.cycles.iter().map(expected_activity).collect::<Vec<_>>();
+	if activity != expected_activity {
+		errors.push(format!(
+			"bus activity: expected {:X?}, found {:X?}",
+			expected_activity, activity
+		));
+	}
+
+	match errors.is_empty() {
+		true => Ok(()),
+		false => Err(format!("{}\n\t{}", test.name, errors.join("\n\t"))),
+	}
+}
+
+/// Runs every test in a file, returning how many ran and a description of
+/// each failure
+fn run_file(path: &Path) -> (usize, Vec<String>) {
+	let source = std::fs::read_to_string(path).unwrap();
+	let tests: Vec<SingleStepTest> = serde_json::from_str(&source)
+		.unwrap_or_else(|why| panic!("Failed to parse {}: {}", path.display(), why));
+
+	let failures = tests
+		.iter()
+		.filter(|test| {
+			let opcode = test.initial.ram.iter().find(|(address, _)| *address == test.initial.pc);
+			!matches!(opcode, Some((_, opcode)) if SKIPPED_OPCODES.contains(opcode))
+		})
+		.filter_map(|test| run_test(test).err())
+		.collect();
+
+	(tests.len(), failures)
+}
+
+fn run_files(paths: impl IntoIterator<Item = PathBuf>) {
+	let mut total = 0;
+	let mut failures = Vec::new();
+
+	for path in paths {
+		let (count, file_failures) = run_file(&path);
+		total += count;
+		failures.extend(file_failures.into_iter().map(|failure| {
+			format!("{}: {}", path.file_name().unwrap().to_string_lossy(), failure)
+		}));
+	}
+
+	if !failures.is_empty() {
+		panic!(
+			"{} of {} tests failed\n{}",
+			failures.len(),
+			total,
+			failures.iter().take(MAX_REPORTED_FAILURES).cloned().collect::<Vec<_>>().join("\n")
+		);
+	}
+}
+
+#[test]
+fn test_sm83_fixtures() {
+	run_files([Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/sm83/fixtures.json")]);
+}
+
+/// The JSON files in `dir`, sorted by name, failing the test with `missing`
+/// if there are none
+fn json_files(dir: &Path, missing: &str) -> Vec<PathBuf> {
+	let mut paths = std::fs::read_dir(dir)
+		.unwrap_or_else(|why| panic!("Failed to read {}: {}, {}", dir.display(), why, missing))
+		.map(|entry| entry.unwrap().path())
+		.filter(|path| path.extension().is_some_and(|extension| extension == "json"))
+		.collect::<Vec<_>>();
+	paths.sort();
+
+	assert!(!paths.is_empty(), "No tests in {}, {}", dir.display(), missing);
+	paths
+}
+
+#[test]
+fn test_sm83_vendored_subset() {
+	let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/sm83/subset");
+
+	run_files(json_files(&dir, "run tests/sm83/vendor-subset.sh to vendor them"));
+}
+
+#[test]
+#[ignore = "needs the full SingleStepTests suite, see the module docs"]
+fn test_sm83_single_step_tests() {
+	let dir = match std::env::var_os("SM83_TESTS_DIR") {
+		Some(dir) => PathBuf::from(dir),
+		None => Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/sm83/v1"),
+	};
+
+	run_files(json_files(&dir, "set SM83_TESTS_DIR to the suite's v1 directory"));
+}
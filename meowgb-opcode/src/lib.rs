@@ -56,7 +56,7 @@ pub fn opcode(item: TokenStream) -> TokenStream {
 	let length = length.base10_parse::<u8>().expect("Failed to parse opcode length as u8");
 
 	let fn_sig = quote::quote! {
-		pub fn #name(state: &mut Gameboy<impl crate::gameboy::serial::SerialWriter>) -> CycleResult
+		pub fn #name(state: &mut Sm83<impl crate::gameboy::cpu::CpuBus>) -> CycleResult
 	};
 
 	let mut cycle = Vec::new();
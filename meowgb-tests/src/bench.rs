@@ -0,0 +1,204 @@
+//! Headless benchmarking of the emulator, with results which can be stored and
+//! compared against later runs to catch performance regressions
+
+use std::{
+	fmt::Display,
+	path::{Path, PathBuf},
+	time::{Duration, Instant},
+};
+
+use meowgb_core::gameboy::profile::TickProfile;
+use serde::{Deserialize, Serialize};
+
+use crate::{new_emulator, run_or_report, DmgTestError};
+
+/// The DMG's clock in T-cycles per second
+const DMG_CLOCK_HZ: f64 = 4_194_304.0;
+
+/// M-cycles in one frame. Frames are counted in emulated time rather than by
+/// redraws, so ROMs which turn the LCD off still finish
+const M_CYCLES_PER_FRAME: u64 = 17_556;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchResult {
+	pub rom: PathBuf,
+	pub frames: u64,
+	pub m_cycles: u64,
+	pub seconds: f64,
+	/// Emulated T-cycles per second of wall time, in MHz
+	pub emulated_mhz: f64,
+	pub fps: f64,
+	/// Only measured with `--profile`
+	pub subsystems: Option<SubsystemTimes>,
+}
+
+/// Wall time spent in each subsystem during the profiled run
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct SubsystemTimes {
+	pub cpu_ms: f64,
+	pub ppu_ms: f64,
+	pub timer_ms: f64,
+	pub dma_ms: f64,
+	pub serial_ms: f64,
+}
+
+impl From<TickProfile> for SubsystemTimes {
+	fn from(profile: TickProfile) -> Self {
+		let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+		Self {
+			cpu_ms: ms(profile.cpu),
+			ppu_ms: ms(profile.ppu),
+			timer_ms: ms(profile.timer),
+			dma_ms: ms(profile.dma),
+			serial_ms: ms(profile.serial),
+		}
+	}
+}
+
+impl Display for BenchResult {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		writeln!(
+			f,
+			"Ran {} frames ({} M-cycles) in {:.0}ms",
+			self.frames,
+			self.m_cycles,
+			self.seconds * 1000.0
+		)?;
+		write!(
+			f,
+			"{:.2} MHz emulated ({:.2}x real time), {:.1} frames/s",
+			self.emulated_mhz,
+			self.emulated_mhz * 1_000_000.0 / DMG_CLOCK_HZ,
+			self.fps
+		)?;
+
+		if let Some(subsystems) = self.subsystems {
+			let total = subsystems.cpu_ms
+				+ subsystems.ppu_ms
+				+ subsystems.timer_ms
+				+ subsystems.dma_ms
+				+ subsystems.serial_ms;
+
+			for (name, ms) in [
+				("CPU", subsystems.cpu_ms),
+				("PPU", subsystems.ppu_ms),
+				("Timer", subsystems.timer_ms),
+				("DMA", subsystems.dma_ms),
+				("Serial", subsystems.serial_ms),
+			] {
+				write!(f, "\n{:<8}{:>10.1}ms {:>5.1}%", name, ms, ms / total * 100.0)?;
+			}
+		}
+
+		Ok(())
+	}
+}
+
+/// A metric which got worse than the baseline by more than the tolerance
+#[derive(Debug, Clone, Copy)]
+pub struct Regression {
+	pub metric: &'static str,
+	pub baseline: f64,
+	pub current: f64,
+}
+
+impl Display for Regression {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(
+			f,
+			"{} dropped from {:.2} to {:.2} ({:+.1}%)",
+			self.metric,
+			self.baseline,
+			self.current,
+			(self.current / self.baseline - 1.0) * 100.0
+		)
+	}
+}
+
+fn run_frames(rom: Vec<u8>, m_cycles: u64, profile: bool) -> (Duration, Option<TickProfile>) {
+	let mut emulator = new_emulator(std::io::sink(), rom);
+	if profile {
+		emulator.gameboy_mut().bus.profile = Some(Box::default());
+	}
+
+	let instant = Instant::now();
+	run_or_report(&mut emulator, m_cycles);
+	let elapsed = instant.elapsed();
+
+	(elapsed, emulator.gameboy_mut().bus.profile.take().map(|profile| *profile))
+}
+
+/// Runs `rom` for `frames` frames of emulated time. With `profile` the ROM is
+/// run a second time with every subsystem timed, as the timing itself slows
+/// the emulator down too much to measure the overall speed
+pub fn run(rom_path: &Path, frames: u64, profile: bool) -> Result<BenchResult, DmgTestError> {
+	if !rom_path.is_file() {
+		return Err(DmgTestError::RomNotFound);
+	}
+	let rom = std::fs::read(rom_path).map_err(DmgTestError::RomRead)?;
+
+	let m_cycles = frames * M_CYCLES_PER_FRAME;
+	let (elapsed, _) = run_frames(rom.clone(), m_cycles, false);
+	let subsystems = match profile {
+		true => run_frames(rom, m_cycles, true).1.map(SubsystemTimes::from),
+		false => None,
+	};
+
+	let seconds = elapsed.as_secs_f64();
+
+	Ok(BenchResult {
+		rom: rom_path.to_path_buf(),
+		frames,
+		m_cycles,
+		seconds,
+		emulated_mhz: (m_cycles * 4) as f64 / seconds / 1_000_000.0,
+		fps: frames as f64 / seconds,
+		subsystems,
+	})
+}
+
+pub fn write(path: &Path, result: &BenchResult) -> Result<(), DmgTestError> {
+	let mut out = serde_json::to_string_pretty(result).unwrap();
+	out.push('\n');
+	std::fs::write(path, out).map_err(DmgTestError::OutputFileWrite)
+}
+
+pub fn read_baseline(path: &Path) -> Result<BenchResult, DmgTestError> {
+	let source = std::fs::read_to_string(path).map_err(DmgTestError::BaselineRead)?;
+	serde_json::from_str(&source).map_err(DmgTestError::BaselineParse)
+}
+
+/// Compares the speed of `current` against `baseline`, allowing it to be up to
+/// `tolerance` percent slower
+pub fn compare(baseline: &BenchResult, current: &BenchResult, tolerance: f64) -> Vec<Regression> {
+	let minimum = 1.0 - tolerance / 100.0;
+
+	[
+		("Emulated MHz", baseline.emulated_mhz, current.emulated_mhz),
+		("Frames/s", baseline.fps, current.fps),
+	]
+	.into_iter()
+	.filter(|(_, baseline, current)| *current < baseline * minimum)
+	.map(|(metric, baseline, current)| Regression { metric, baseline, current })
+	.collect()
+}
+
+#[test]
+fn test_compare_tolerance() {
+	let result = |emulated_mhz: f64| BenchResult {
+		rom: PathBuf::from("rom.gb"),
+		frames: 60,
+		m_cycles: 60 * M_CYCLES_PER_FRAME,
+		seconds: 1.0,
+		emulated_mhz,
+		fps: emulated_mhz * 10.0,
+		subsystems: None,
+	};
+
+	assert!(compare(&result(100.0), &result(96.0), 5.0).is_empty());
+	assert!(compare(&result(100.0), &result(150.0), 5.0).is_empty());
+
+	let regressions = compare(&result(100.0), &result(90.0), 5.0);
+	assert_eq!(regressions.len(), 2);
+	assert_eq!(regressions[0].metric, "Emulated MHz");
+}
@@ -0,0 +1,148 @@
+//! Comparing framebuffers and writing diagnostics when they differ
+
+use std::path::{Path, PathBuf};
+
+use meowgb_core::gameboy::ppu::{Color, FB_HEIGHT, FB_WIDTH, PIXEL_SIZE};
+use serde::Deserialize;
+
+use crate::DmgTestError;
+
+const DIFF_HIGHLIGHT: [u8; PIXEL_SIZE] = [0xFF, 0x00, 0x00, 0xFF];
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Comparison {
+	/// Every pixel must have exactly the same RGBA value
+	#[default]
+	Exact,
+	/// Every pixel must have the same shade, no matter which palette was used
+	/// to produce the expected framebuffer
+	Shade,
+}
+
+impl Comparison {
+	fn pixels_match(self, actual: &[u8], expected: &[u8]) -> bool {
+		match self {
+			Comparison::Exact => actual == expected,
+			Comparison::Shade => shade(actual) == shade(expected),
+		}
+	}
+}
+
+/// How a framebuffer test compares frames, and where it writes its
+/// diagnostics if none match
+#[derive(Debug, Clone)]
+pub struct FramebufferCheck {
+	pub comparison: Comparison,
+	pub diagnostics_dir: PathBuf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FramebufferDiff {
+	pub mismatches: usize,
+	/// (x, y) of the first differing pixel in reading order
+	pub first: (u32, u32),
+}
+
+/// The shade of a pixel from 0 (lightest) to 3 (darkest). Pixels in the
+/// emulator's palette are mapped exactly, anything else is bucketed by
+/// luminance
+fn shade(rgba: &[u8]) -> u8 {
+	let rgba: [u8; PIXEL_SIZE] = rgba.try_into().unwrap();
+
+	match Color::from_rgba(rgba) {
+		Some(Color::White) => 0,
+		Some(Color::LGray) => 1,
+		Some(Color::DGray) => 2,
+		Some(Color::Black) => 3,
+		None => {
+			let luminance =
+				(rgba[0] as u32 * 299 + rgba[1] as u32 * 587 + rgba[2] as u32 * 114) / 1000;
+			3 - (luminance / 64) as u8
+		}
+	}
+}
+
+/// Compares two RGBA framebuffers, returning `None` if they match
+pub fn compare(actual: &[u8], expected: &[u8], comparison: Comparison) -> Option<FramebufferDiff> {
+	let mut diff: Option<FramebufferDiff> = None;
+
+	for (idx, (actual, expected)) in
+		actual.chunks_exact(PIXEL_SIZE).zip(expected.chunks_exact(PIXEL_SIZE)).enumerate()
+	{
+		if comparison.pixels_match(actual, expected) {
+			continue;
+		}
+
+		match diff.as_mut() {
+			Some(diff) => diff.mismatches += 1,
+			None => {
+				diff = Some(FramebufferDiff {
+					mismatches: 1,
+					first: (idx as u32 % FB_WIDTH, idx as u32 / FB_WIDTH),
+				})
+			}
+		}
+	}
+
+	diff
+}
+
+fn write_png(path: &Path, rgba: &[u8]) -> Result<(), DmgTestError> {
+	let file = std::fs::File::create(path).map_err(DmgTestError::DiagnosticsWrite)?;
+	let mut encoder = png::Encoder::new(std::io::BufWriter::new(file), FB_WIDTH, FB_HEIGHT);
+	encoder.set_color(png::ColorType::Rgba);
+	encoder.set_depth(png::BitDepth::Eight);
+
+	let mut writer = encoder.write_header().map_err(DmgTestError::DiagnosticsEncode)?;
+	writer.write_image_data(rgba).map_err(DmgTestError::DiagnosticsEncode)
+}
+
+/// Writes `<name>.actual.png`, `<name>.expected.png` and `<name>.diff.png` to
+/// `dir`. The diff is the actual frame faded out with the differing pixels
+/// highlighted in red
+pub fn write_diagnostics(
+	dir: &Path,
+	name: &str,
+	actual: &[u8],
+	expected: &[u8],
+	comparison: Comparison,
+) -> Result<PathBuf, DmgTestError> {
+	std::fs::create_dir_all(dir).map_err(DmgTestError::DiagnosticsWrite)?;
+
+	let mut diff = Vec::with_capacity(actual.len());
+	for (actual, expected) in actual.chunks_exact(PIXEL_SIZE).zip(expected.chunks_exact(PIXEL_SIZE))
+	{
+		match comparison.pixels_match(actual, expected) {
+			true => {
+				diff.extend(actual[..3].iter().map(|channel| 0xC0 + channel / 4));
+				diff.push(0xFF);
+			}
+			false => diff.extend_from_slice(&DIFF_HIGHLIGHT),
+		}
+	}
+
+	write_png(&dir.join(format!("{}.actual.png", name)), actual)?;
+	write_png(&dir.join(format!("{}.expected.png", name)), expected)?;
+	write_png(&dir.join(format!("{}.diff.png", name)), &diff)?;
+
+	Ok(dir.to_path_buf())
+}
+
+#[test]
+fn test_shade_comparison_ignores_palette() {
+	let emulator = [Color::White, Color::LGray, Color::DGray, Color::Black]
+		.iter()
+		.flat_map(|color| *color.rgba())
+		.collect::<Vec<_>>();
+	let greyscale = [0xFF, 0xAA, 0x55, 0x00]
+		.iter()
+		.flat_map(|value| [*value, *value, *value, 0xFF])
+		.collect::<Vec<_>>();
+
+	assert!(compare(&emulator, &greyscale, Comparison::Shade).is_none());
+
+	let diff = compare(&emulator, &greyscale, Comparison::Exact).unwrap();
+	assert_eq!(diff.mismatches, 4);
+	assert_eq!(diff.first, (0, 0));
+}
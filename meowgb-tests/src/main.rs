@@ -1,17 +1,39 @@
+mod bench;
+mod framebuffer;
+mod movie;
+mod printer;
+mod report;
+mod suite;
+
 use std::{
 	path::{Path, PathBuf},
-	sync::{Arc, RwLock},
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc, RwLock,
+	},
 	time::{Duration, Instant},
 };
 
 use clap::{Parser, Subcommand};
-use meowgb_core::gameboy::{serial::SerialWriter, Gameboy};
+use framebuffer::{Comparison, FramebufferCheck, FramebufferDiff};
+use meowgb_core::{
+	emulator::Emulator,
+	gameboy::{link::LinkedPair, serial::LinkDevice, Gameboy},
+};
+use report::{ReportTarget, TestResult};
 
 #[derive(Debug, Parser)]
 /// DMG Emulator
 pub struct CliArgs {
-	/// game path
-	pub rom: PathBuf,
+	/// game path, not needed for `run-suite`
+	pub rom: Option<PathBuf>,
+	/// write a report of the results, FORMAT is `junit` or `json`. May be
+	/// given more than once
+	#[clap(long, num_args = 2, value_names = ["FORMAT", "PATH"], global = true)]
+	pub report: Vec<String>,
+	/// render whole lines at once where possible rather than a pixel per dot
+	#[clap(long, global = true)]
+	pub scanline_renderer: bool,
 	#[clap(subcommand)]
 	pub operation: Operation,
 }
@@ -25,6 +47,12 @@ pub enum Operation {
 		/// path to expected framebuffer (RGBA)
 		#[clap(short = 's', long)]
 		expected_framebuffer: PathBuf,
+		/// how pixels are compared
+		#[clap(long, value_enum, default_value_t)]
+		compare: Comparison,
+		/// where to write the actual, expected and diff images on a mismatch
+		#[clap(long, default_value = "framebuffer-diagnostics")]
+		diagnostics_dir: PathBuf,
 	},
 	TestSerial {
 		/// maximum M-cycles
@@ -34,6 +62,59 @@ pub enum Operation {
 		#[clap(short = 's', long)]
 		expected_serial: PathBuf,
 	},
+	/// Run the ROM with a Game Boy Printer plugged in, checking everything it
+	/// prints
+	TestPrinter {
+		/// maximum M-cycles
+		#[clap(short = 'm', long)]
+		maximum_m_cycles: u64,
+		/// path to the expected print as a PNG, with all strips printed joined
+		/// top to bottom
+		#[clap(short = 's', long)]
+		expected_print: PathBuf,
+		/// where to write what was printed if it does not match
+		#[clap(long, default_value = "framebuffer-diagnostics")]
+		diagnostics_dir: PathBuf,
+	},
+	/// Run the ROM linked to a second console running `peer`, checking the
+	/// bytes each sends the other over the link cable
+	TestLinked {
+		/// game running on the second console
+		#[clap(long)]
+		peer: PathBuf,
+		/// maximum M-cycles
+		#[clap(short = 'm', long)]
+		maximum_m_cycles: u64,
+		/// path to the expected bytes sent by the first console
+		#[clap(short = 's', long)]
+		expected_serial: PathBuf,
+		/// path to the expected bytes sent by the second console
+		#[clap(long)]
+		peer_expected_serial: PathBuf,
+	},
+	/// Play an input movie back, checking every frame matches the recording
+	TestMovie {
+		/// maximum M-cycles
+		#[clap(short = 'm', long)]
+		maximum_m_cycles: u64,
+		/// path to the movie
+		#[clap(long)]
+		movie: PathBuf,
+	},
+	/// Run a mooneye test ROM, which reports its result in the registers when
+	/// it executes `LD B,B`
+	TestMooneye {
+		/// maximum M-cycles
+		#[clap(short = 'm', long)]
+		maximum_m_cycles: u64,
+	},
+	/// Run a blargg test ROM which reports its result in cartridge RAM at
+	/// 0xA000
+	TestBlarggMemory {
+		/// maximum M-cycles
+		#[clap(short = 'm', long)]
+		maximum_m_cycles: u64,
+	},
 	GenerateOutputSerial {
 		/// M-cycles to run for
 		#[clap(short = 'm', long)]
@@ -50,6 +131,47 @@ pub enum Operation {
 		#[clap(short = 's', long)]
 		expected_framebuffer: PathBuf,
 	},
+	GenerateOutputPrinter {
+		/// M-cycles to run for
+		#[clap(short = 'm', long)]
+		m_cycles: u64,
+		/// path to write everything printed to, as a PNG
+		#[clap(short = 's', long)]
+		expected_print: PathBuf,
+	},
+	/// Run every test ROM listed in a manifest
+	RunSuite {
+		/// path to the manifest, paths inside it are relative to it
+		manifest: PathBuf,
+		/// how many tests to run at once, defaults to the number of CPUs
+		#[clap(short = 'j', long)]
+		jobs: Option<usize>,
+		/// regenerate tests.md at this path, listing the tests which passed
+		#[clap(long)]
+		tests_md: Option<PathBuf>,
+		/// where to write the actual, expected and diff images of framebuffer
+		/// tests which fail
+		#[clap(long, default_value = "framebuffer-diagnostics")]
+		diagnostics_dir: PathBuf,
+	},
+	/// Run a ROM headless and measure how fast the emulator runs it
+	Bench {
+		/// frames of emulated time to run for
+		#[clap(short = 'f', long, default_value_t = 600)]
+		frames: u64,
+		/// also measure the time spent in each subsystem, in a second run
+		#[clap(long)]
+		profile: bool,
+		/// write the results as JSON to this path
+		#[clap(short = 'o', long)]
+		output: Option<PathBuf>,
+		/// compare against results previously written with `--output`
+		#[clap(long)]
+		baseline: Option<PathBuf>,
+		/// how many percent slower than the baseline is still accepted
+		#[clap(long, default_value_t = 5.0)]
+		tolerance: f64,
+	},
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -66,8 +188,87 @@ pub enum DmgTestError {
 	OutputFileWrite(std::io::Error),
 	#[error("Serial mismatch\nExpected: {0}\nFound: {1}")]
 	SerialDifferent(String, String),
-	#[error("Framebuffer mismatch")]
-	FramebufferDifferent,
+	#[error("Serial mismatch on the {0} console\nExpected: {1}\nFound: {2}")]
+	LinkedSerialDifferent(&'static str, String, String),
+	#[error(
+		"Framebuffer mismatch, {} pixels differ with the first at {:?}, images written to {}",
+		.diff.mismatches, .diff.first, .diagnostics.display()
+	)]
+	FramebufferDifferent { diff: FramebufferDiff, diagnostics: PathBuf },
+	#[error("Expected framebuffer is {0} bytes rather than {1}")]
+	ExpectedFramebufferSize(usize, usize),
+	#[error("Timed out before a frame was drawn")]
+	NoFrameDrawn,
+	#[error("Timed out before anything was printed")]
+	NothingPrinted,
+	#[error("Print mismatch, what was printed was written to {}", .0.display())]
+	PrintDifferent(PathBuf),
+	#[error("Error decoding expected print: {0}")]
+	ExpectedPrintDecode(png::DecodingError),
+	#[error("Expected print must be an 8-bit RGBA PNG 160 pixels wide")]
+	ExpectedPrintFormat,
+	#[error("Error writing print: {0}")]
+	PrintWrite(png::EncodingError),
+	#[error("Error writing framebuffer diagnostics: {0}")]
+	DiagnosticsWrite(std::io::Error),
+	#[error("Error encoding framebuffer diagnostics: {0}")]
+	DiagnosticsEncode(png::EncodingError),
+	#[error(transparent)]
+	Movie(#[from] meowgb_core::gameboy::movie::MovieError),
+	#[error("Timed out on frame {0} of the movie's {1}")]
+	MovieTimeout(usize, usize),
+	#[error("Timed out before LD B,B was executed")]
+	SoftwareBreakpointTimeout,
+	#[error(
+		"Mooneye test failed, B={:#04X} C={:#04X} D={:#04X} E={:#04X} H={:#04X} L={:#04X}",
+		.0[0], .0[1], .0[2], .0[3], .0[4], .0[5]
+	)]
+	MooneyeFailed([u8; 6]),
+	#[error("Timed out before the test finished{}", .0.as_ref().map(|text| format!(", output so far:\n{}", text)).unwrap_or_default())]
+	BlarggTimeout(Option<String>),
+	#[error("Test requested a reset, which is not supported")]
+	BlarggResetRequested,
+	#[error("Test failed with status {0:#04X}:\n{1}")]
+	BlarggFailed(u8, String),
+	#[error("Error reading manifest: {0}")]
+	ManifestRead(std::io::Error),
+	#[error("Error parsing manifest: {0}")]
+	ManifestParse(toml::de::Error),
+	#[error("Error writing tests.md: {0}")]
+	TestsMdWrite(std::io::Error),
+	#[error("{0} of {1} tests failed")]
+	SuiteFailed(usize, usize),
+	#[error("Unknown report format {0}, expected junit or json")]
+	ReportFormat(String),
+	#[error("--report takes a FORMAT and a PATH")]
+	ReportArgs,
+	#[error("Error writing report: {0}")]
+	ReportWrite(std::io::Error),
+	#[error("Error reading benchmark baseline: {0}")]
+	BaselineRead(std::io::Error),
+	#[error("Error parsing benchmark baseline: {0}")]
+	BaselineParse(serde_json::Error),
+	#[error("Slower than the baseline:\n{}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("\n"))]
+	BenchRegression(Vec<bench::Regression>),
+	/// A failure found after running for this many M-cycles
+	#[error("{1}")]
+	AfterMCycles(u64, Box<DmgTestError>),
+}
+
+impl DmgTestError {
+	/// Records that the test failed after running for `m_cycles` M-cycles
+	fn after(self, m_cycles: u64) -> Self {
+		Self::AfterMCycles(m_cycles, Box::new(self))
+	}
+
+	/// The M-cycles ran before the test failed, `None` if it failed before
+	/// running at all
+	pub fn m_cycles(&self) -> Option<u64> {
+		match self {
+			Self::AfterMCycles(m_cycles, _) => Some(*m_cycles),
+			_ => None,
+		}
+	}
 }
 
 #[derive(Debug, Clone)]
@@ -87,12 +288,53 @@ impl SyncWriter {
 	}
 }
 
-impl SerialWriter for SyncWriter {
-	fn write_byte(&mut self, byte: u8) {
-		self.0.write().unwrap().write_byte(byte);
+impl std::io::Write for SyncWriter {
+	fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+		self.0.write().unwrap().write(buf)
+	}
+
+	fn flush(&mut self) -> std::io::Result<()> {
+		Ok(())
+	}
+}
+
+/// Set from `--scanline-renderer` for every emulator the harness creates
+static SCANLINE_RENDERER: AtomicBool = AtomicBool::new(false);
+
+fn new_emulator<S: LinkDevice>(link_device: S, rom: Vec<u8>) -> Emulator<S> {
+	let mut emulator = Emulator::new(link_device, Some(rom));
+	emulator.gameboy_mut().bus.ppu.scanline_renderer = SCANLINE_RENDERER.load(Ordering::Relaxed);
+	emulator
+}
+
+/// Runs the emulator for at least one M-cycle and at most `max_m_cycles`, see
+/// [`Emulator::advance`], printing a crash report if it panics
+fn advance_or_report(emulator: &mut Emulator<impl LinkDevice>, max_m_cycles: u64) -> (u64, bool) {
+	match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| emulator.advance(max_m_cycles)))
+	{
+		Ok(advanced) => advanced,
+		Err(why) => {
+			eprintln!("Emulator crashed\n{}", emulator.gameboy().crash_report());
+			std::panic::resume_unwind(why);
+		}
 	}
 }
 
+/// Runs the emulator for `m_cycles` M-cycles, returning whether a frame was
+/// drawn
+fn run_or_report(emulator: &mut Emulator<impl LinkDevice>, m_cycles: u64) -> bool {
+	let mut ran = 0;
+	let mut drawn = false;
+
+	while ran < m_cycles {
+		let (advanced, redraw) = advance_or_report(emulator, m_cycles - ran);
+		ran += advanced;
+		drawn |= redraw;
+	}
+
+	drawn
+}
+
 fn generate_output<const FRAMEBUFFER: bool>(
 	rom: &Path,
 	m_cycles: u64,
@@ -106,25 +348,18 @@ fn generate_output<const FRAMEBUFFER: bool>(
 	};
 
 	let sync_writer = SyncWriter::new();
-	let mut fb = None;
 
-	let mut gameboy = Gameboy::new(sync_writer.clone(), Some(rom));
+	let mut emulator = new_emulator(sync_writer.clone(), rom);
 
 	let instant = std::time::Instant::now();
 
-	for _ in 0..m_cycles {
-		let new_fb = gameboy.tick_4();
-
-		if FRAMEBUFFER && new_fb {
-			fb = Some(gameboy.ppu.write_fb());
-		}
-	}
-
-	drop(gameboy);
+	let drawn = run_or_report(&mut emulator, m_cycles);
 
 	if FRAMEBUFFER {
-		std::fs::write(expected, &fb.unwrap()).map_err(DmgTestError::OutputFileWrite)?;
+		assert!(drawn, "no frame was drawn");
+		std::fs::write(expected, emulator.framebuffer()).map_err(DmgTestError::OutputFileWrite)?;
 	} else {
+		drop(emulator);
 		let serial_content = sync_writer.into_inner();
 		std::fs::write(expected, &serial_content).map_err(DmgTestError::OutputFileWrite)?;
 	}
@@ -132,7 +367,130 @@ fn generate_output<const FRAMEBUFFER: bool>(
 	Ok(instant.elapsed())
 }
 
-fn run_test<const FRAMEBUFFER: bool>(
+/// Runs a ROM for `m_cycles` M-cycles, for tests which only check that the
+/// emulator does not crash
+fn run_for(rom: &Path, m_cycles: u64) -> Result<(u64, Duration), DmgTestError> {
+	let rom = {
+		if !rom.is_file() {
+			return Err(DmgTestError::RomNotFound);
+		}
+		std::fs::read(rom).map_err(DmgTestError::RomRead)?
+	};
+
+	let mut emulator = new_emulator(std::io::sink(), rom);
+
+	let instant = Instant::now();
+
+	run_or_report(&mut emulator, m_cycles);
+
+	Ok((m_cycles, instant.elapsed()))
+}
+
+fn run_suite(
+	manifest_path: &Path,
+	jobs: Option<usize>,
+	tests_md: Option<&Path>,
+	diagnostics_dir: &Path,
+	reports: &[ReportTarget],
+) -> Result<(), DmgTestError> {
+	let manifest = suite::Manifest::from_file(manifest_path)?;
+	let base = manifest_path.parent().unwrap_or(Path::new("."));
+	let jobs =
+		jobs.or_else(|| std::thread::available_parallelism().ok().map(usize::from)).unwrap_or(1);
+
+	let instant = Instant::now();
+	let outcomes = manifest.run(base, jobs, diagnostics_dir);
+	let elapsed = instant.elapsed();
+
+	println!();
+	for (test, outcome) in manifest.tests.iter().zip(&outcomes) {
+		println!("{}: {}", test.name(), outcome);
+	}
+
+	let count = |f: fn(&suite::Outcome) -> bool| outcomes.iter().filter(|x| f(x)).count();
+	let passed = count(|x| matches!(x, suite::Outcome::Passed(..)));
+	let failed = count(|x| matches!(x, suite::Outcome::Failed(..)));
+	let skipped = count(|x| matches!(x, suite::Outcome::Skipped(..)));
+
+	println!(
+		"\nPassed {}/{} ({} failed, {} skipped) in {}ms across {} threads",
+		passed,
+		outcomes.len(),
+		failed,
+		skipped,
+		elapsed.as_millis(),
+		jobs
+	);
+
+	if let Some(tests_md) = tests_md {
+		std::fs::write(tests_md, manifest.tests_md(&outcomes))
+			.map_err(DmgTestError::TestsMdWrite)?;
+	}
+
+	if !reports.is_empty() {
+		let results = manifest
+			.tests
+			.iter()
+			.zip(&outcomes)
+			.map(|(test, outcome)| TestResult {
+				name: test.name(),
+				class: test.section.clone(),
+				rom: &test.rom,
+				outcome,
+			})
+			.collect::<Vec<_>>();
+
+		for report in reports {
+			report::write_report(report, &results, elapsed)?;
+		}
+	}
+
+	match failed {
+		0 => Ok(()),
+		failed => Err(DmgTestError::SuiteFailed(failed, outcomes.len())),
+	}
+}
+
+fn run_bench(
+	rom: &Path,
+	frames: u64,
+	profile: bool,
+	output: Option<&Path>,
+	baseline: Option<&Path>,
+	tolerance: f64,
+) -> Result<(), DmgTestError> {
+	let result = bench::run(rom, frames, profile)?;
+	println!("{}", result);
+
+	if let Some(output) = output {
+		bench::write(output, &result)?;
+	}
+
+	let Some(baseline) = baseline else {
+		return Ok(());
+	};
+
+	let baseline = bench::read_baseline(baseline)?;
+	match bench::compare(&baseline, &result, tolerance).as_slice() {
+		[] => {
+			println!(
+				"Within {}% of the baseline ({:.2} MHz emulated)",
+				tolerance, baseline.emulated_mhz
+			);
+			Ok(())
+		}
+		regressions => Err(DmgTestError::BenchRegression(regressions.to_vec())),
+	}
+}
+
+fn read_expected(expected: &Path) -> Result<Vec<u8>, DmgTestError> {
+	if !expected.is_file() {
+		return Err(DmgTestError::OutputFileNotFound);
+	}
+	std::fs::read(expected).map_err(DmgTestError::OutputFileRead)
+}
+
+fn run_serial_test(
 	rom: &Path,
 	maximum_m_cycles: u64,
 	expected: &Path,
@@ -144,80 +502,388 @@ fn run_test<const FRAMEBUFFER: bool>(
 		std::fs::read(rom).map_err(DmgTestError::RomRead)?
 	};
 
-	let expected = {
-		if !expected.is_file() {
-			return Err(DmgTestError::OutputFileNotFound);
+	let expected = read_expected(expected)?;
+
+	let sync_writer = SyncWriter::new();
+
+	let mut emulator = new_emulator(sync_writer.clone(), rom);
+
+	let instant = Instant::now();
+
+	let mut cycle_counter = 0;
+
+	while cycle_counter < maximum_m_cycles {
+		let (advanced, _) = advance_or_report(&mut emulator, maximum_m_cycles - cycle_counter);
+		cycle_counter += advanced;
+
+		if sync_writer.compare(&expected) {
+			return Ok((cycle_counter, instant.elapsed()));
 		}
-		std::fs::read(expected).map_err(DmgTestError::OutputFileRead)?
+	}
+
+	drop(emulator);
+
+	Err(DmgTestError::SerialDifferent(
+		expected.into_iter().map(char::from).collect(),
+		sync_writer.into_inner().into_iter().map(char::from).collect(),
+	)
+	.after(maximum_m_cycles))
+}
+
+fn run_linked_test(
+	rom: &Path,
+	peer: &Path,
+	maximum_m_cycles: u64,
+	expected: &Path,
+	peer_expected: &Path,
+) -> Result<(u64, Duration), DmgTestError> {
+	let read_rom = |rom: &Path| {
+		if !rom.is_file() {
+			return Err(DmgTestError::RomNotFound);
+		}
+		std::fs::read(rom).map_err(DmgTestError::RomRead)
 	};
 
-	if FRAMEBUFFER {
-		assert_eq!(
-			expected.len(),
-			(meowgb_core::gameboy::ppu::FB_WIDTH as usize
-				* meowgb_core::gameboy::ppu::FB_HEIGHT as usize)
-				* meowgb_core::gameboy::ppu::PIXEL_SIZE as usize
-		);
+	let expected = read_expected(expected)?;
+	let peer_expected = read_expected(peer_expected)?;
+
+	let mut pair = LinkedPair::new(Some(read_rom(rom)?), Some(read_rom(peer)?));
+	let scanline_renderer = SCANLINE_RENDERER.load(Ordering::Relaxed);
+	pair.left.bus.ppu.scanline_renderer = scanline_renderer;
+	pair.right.bus.ppu.scanline_renderer = scanline_renderer;
+
+	let instant = Instant::now();
+
+	for cycle_counter in 1..=maximum_m_cycles {
+		if let Err(why) = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| pair.tick_4())) {
+			eprintln!("Emulator crashed\nFirst console:\n{}", pair.left.crash_report());
+			eprintln!("Second console:\n{}", pair.right.crash_report());
+			std::panic::resume_unwind(why);
+		}
+
+		if pair.has_sent(&expected, &peer_expected) {
+			return Ok((cycle_counter, instant.elapsed()));
+		}
 	}
 
-	let sync_writer = SyncWriter::new();
+	let to_string = |bytes: Vec<u8>| bytes.into_iter().map(char::from).collect();
+	let why = match pair.left_sent() == expected {
+		false => DmgTestError::LinkedSerialDifferent(
+			"first",
+			to_string(expected),
+			to_string(pair.left_sent()),
+		),
+		true => DmgTestError::LinkedSerialDifferent(
+			"second",
+			to_string(peer_expected),
+			to_string(pair.right_sent()),
+		),
+	};
+	Err(why.after(maximum_m_cycles))
+}
 
-	let mut gameboy = Gameboy::new(sync_writer.clone(), Some(rom));
+fn run_framebuffer_test(
+	rom_path: &Path,
+	maximum_m_cycles: u64,
+	expected: &Path,
+	check: &FramebufferCheck,
+) -> Result<(u64, Duration), DmgTestError> {
+	let rom = {
+		if !rom_path.is_file() {
+			return Err(DmgTestError::RomNotFound);
+		}
+		std::fs::read(rom_path).map_err(DmgTestError::RomRead)?
+	};
+
+	let expected = read_expected(expected)?;
+
+	let framebuffer_size = (meowgb_core::gameboy::ppu::FB_WIDTH as usize
+		* meowgb_core::gameboy::ppu::FB_HEIGHT as usize)
+		* meowgb_core::gameboy::ppu::PIXEL_SIZE as usize;
+	if expected.len() != framebuffer_size {
+		return Err(DmgTestError::ExpectedFramebufferSize(expected.len(), framebuffer_size));
+	}
+
+	let mut emulator = new_emulator(std::io::sink(), rom);
 
 	let instant = Instant::now();
 
+	let mut drawn = false;
 	let mut cycle_counter = 0;
 
 	while cycle_counter < maximum_m_cycles {
-		if FRAMEBUFFER {
-			let redraw = gameboy.tick_4();
-
-			cycle_counter += 1;
+		let (advanced, redraw) = advance_or_report(&mut emulator, maximum_m_cycles - cycle_counter);
+		cycle_counter += advanced;
 
-			if redraw && expected == gameboy.ppu.write_fb() {
+		if redraw {
+			if framebuffer::compare(emulator.framebuffer(), &expected, check.comparison).is_none() {
 				return Ok((cycle_counter, instant.elapsed()));
 			}
-		} else {
-			gameboy.tick_4();
 
-			cycle_counter += 1;
+			drawn = true;
+		}
+	}
 
-			if sync_writer.compare(&expected) {
-				return Ok((cycle_counter, instant.elapsed()));
-			}
+	if !drawn {
+		return Err(DmgTestError::NoFrameDrawn.after(maximum_m_cycles));
+	}
+	let frame = emulator.framebuffer();
+
+	let diff = framebuffer::compare(frame, &expected, check.comparison)
+		.expect("frame was compared when it was drawn");
+
+	let name = rom_path.file_stem().unwrap_or_default().to_string_lossy();
+	let diagnostics = framebuffer::write_diagnostics(
+		&check.diagnostics_dir,
+		&name,
+		frame,
+		&expected,
+		check.comparison,
+	)?;
+
+	Err(DmgTestError::FramebufferDifferent { diff, diagnostics }.after(maximum_m_cycles))
+}
+
+/// The registers B, C, D, E, H and L are loaded with these Fibonacci numbers
+/// by a mooneye test ROM which passed
+const MOONEYE_PASS_SIGNATURE: [u8; 6] = [3, 5, 8, 13, 21, 34];
+
+fn run_mooneye(rom: &Path, maximum_m_cycles: u64) -> Result<(u64, Duration), DmgTestError> {
+	let rom = {
+		if !rom.is_file() {
+			return Err(DmgTestError::RomNotFound);
+		}
+		std::fs::read(rom).map_err(DmgTestError::RomRead)?
+	};
+
+	wait_for_mooneye_breakpoint(new_emulator(std::io::sink(), rom), maximum_m_cycles)
+}
+
+/// Runs the emulator until it executes `LD B,B`, checking the registers hold
+/// [`MOONEYE_PASS_SIGNATURE`]
+fn wait_for_mooneye_breakpoint(
+	mut emulator: Emulator<impl LinkDevice>,
+	maximum_m_cycles: u64,
+) -> Result<(u64, Duration), DmgTestError> {
+	let instant = Instant::now();
+
+	let mut cycle_counter = 0;
+
+	while cycle_counter < maximum_m_cycles {
+		let (advanced, _) = advance_or_report(&mut emulator, maximum_m_cycles - cycle_counter);
+		cycle_counter += advanced;
+
+		let gameboy = emulator.gameboy();
+		if gameboy.software_breakpoint {
+			let registers = &gameboy.registers;
+			let signature =
+				[registers.b, registers.c, registers.d, registers.e, registers.h, registers.l];
+
+			return match signature == MOONEYE_PASS_SIGNATURE {
+				true => Ok((cycle_counter, instant.elapsed())),
+				false => Err(DmgTestError::MooneyeFailed(signature).after(cycle_counter)),
+			};
 		}
 	}
 
-	drop(gameboy);
+	Err(DmgTestError::SoftwareBreakpointTimeout.after(maximum_m_cycles))
+}
+
+/// Prints the result of running a single ROM and writes every report of it
+/// requested, exiting with a non-zero status if it failed
+fn finish_test(
+	rom: &Path,
+	reports: &[ReportTarget],
+	instant: Instant,
+	result: Result<(u64, Duration), DmgTestError>,
+) {
+	let outcome = suite::Outcome::from_result(result, instant.elapsed());
+
+	match &outcome {
+		suite::Outcome::Passed(m_cycles, duration, _) => {
+			println!("Success! Ran {} M-Cycles in {}ms", m_cycles, duration.as_millis());
+		}
+		suite::Outcome::Failed(why, ..) | suite::Outcome::Skipped(why) => eprintln!("{}", why),
+	}
+
+	for report in reports {
+		let result = TestResult {
+			name: rom.file_name().unwrap_or_default().to_string_lossy().into_owned(),
+			class: String::from("meowgb-tests"),
+			rom,
+			outcome: &outcome,
+		};
+
+		if let Err(why) = report::write_report(report, &[result], instant.elapsed()) {
+			eprintln!("{}", why);
+			std::process::exit(1);
+		}
+	}
+
+	if !matches!(outcome, suite::Outcome::Passed(..)) {
+		std::process::exit(1);
+	}
+}
+
+/// Written to 0xA001..=0xA003 by blargg test ROMs once the status byte and
+/// text at 0xA000 are valid
+const BLARGG_SIGNATURE: [u8; 3] = [0xDE, 0xB0, 0x61];
+const BLARGG_STATUS_RUNNING: u8 = 0x80;
+const BLARGG_STATUS_RESET: u8 = 0x81;
+const BLARGG_TEXT_START: u16 = 0xA004;
+
+/// Reads the zero terminated text a blargg test ROM writes after the
+/// signature, `None` if the signature has not been written yet
+fn read_blargg_text(gameboy: &Gameboy<impl LinkDevice>) -> Option<String> {
+	let signature = [
+		gameboy.debug_read_u8(0xA001),
+		gameboy.debug_read_u8(0xA002),
+		gameboy.debug_read_u8(0xA003),
+	];
+
+	if signature != BLARGG_SIGNATURE {
+		return None;
+	}
+
+	Some(
+		(BLARGG_TEXT_START..=0xBFFF)
+			.map(|address| gameboy.debug_read_u8(address))
+			.take_while(|byte| *byte != 0)
+			.map(char::from)
+			.collect(),
+	)
+}
+
+/// Runs a blargg test ROM until the status byte at 0xA000 leaves 0x80,
+/// returning the text it wrote alongside the usual M-cycles and time taken
+fn run_blargg_memory(
+	rom: &Path,
+	maximum_m_cycles: u64,
+) -> Result<(u64, Duration, String), DmgTestError> {
+	let rom = {
+		if !rom.is_file() {
+			return Err(DmgTestError::RomNotFound);
+		}
+		std::fs::read(rom).map_err(DmgTestError::RomRead)?
+	};
+
+	wait_for_blargg_status(new_emulator(std::io::sink(), rom), maximum_m_cycles)
+}
+
+/// Runs the emulator until the blargg status byte is set to 0x80 and then
+/// left again, so a status left in cartridge RAM by an earlier run is never
+/// taken as the result
+fn wait_for_blargg_status(
+	mut emulator: Emulator<impl LinkDevice>,
+	maximum_m_cycles: u64,
+) -> Result<(u64, Duration, String), DmgTestError> {
+	let instant = Instant::now();
+
+	let mut cycle_counter = 0;
+	let mut running = false;
+
+	while cycle_counter < maximum_m_cycles {
+		let (advanced, _) = advance_or_report(&mut emulator, maximum_m_cycles - cycle_counter);
+		cycle_counter += advanced;
 
-	match sync_writer.compare(&expected) {
-		true => Ok((cycle_counter, instant.elapsed())),
-		false => Err(DmgTestError::SerialDifferent(
-			expected.into_iter().map(char::from).collect(),
-			sync_writer.into_inner().into_iter().map(char::from).collect(),
-		)),
+		let gameboy = emulator.gameboy();
+		let status = gameboy.debug_read_u8(0xA000);
+		if status == BLARGG_STATUS_RUNNING {
+			running = true;
+			continue;
+		}
+
+		if !running {
+			continue;
+		}
+
+		let Some(text) = read_blargg_text(gameboy) else {
+			continue;
+		};
+
+		return match status {
+			0 => Ok((cycle_counter, instant.elapsed(), text)),
+			BLARGG_STATUS_RESET => Err(DmgTestError::BlarggResetRequested.after(cycle_counter)),
+			status => Err(DmgTestError::BlarggFailed(status, text).after(cycle_counter)),
+		};
 	}
+
+	Err(DmgTestError::BlarggTimeout(read_blargg_text(emulator.gameboy())).after(maximum_m_cycles))
 }
 
 fn main() {
 	let args = CliArgs::parse();
+	SCANLINE_RENDERER.store(args.scanline_renderer, Ordering::Relaxed);
+
+	let reports = match ReportTarget::from_args(&args.report) {
+		Ok(reports) => reports,
+		Err(why) => {
+			eprintln!("{}", why);
+			std::process::exit(1);
+		}
+	};
+
+	if let Operation::RunSuite { manifest, jobs, tests_md, diagnostics_dir } = &args.operation {
+		if let Err(why) = run_suite(manifest, *jobs, tests_md.as_deref(), diagnostics_dir, &reports)
+		{
+			eprintln!("{}", why);
+			std::process::exit(1);
+		}
+
+		return;
+	}
+
+	let Some(rom) = args.rom else {
+		eprintln!("{}", DmgTestError::RomNotFound);
+		std::process::exit(1);
+	};
+
+	let instant = Instant::now();
 
 	match args.operation {
 		Operation::TestSerial { maximum_m_cycles, expected_serial } => {
-			match run_test::<false>(args.rom.as_path(), maximum_m_cycles, expected_serial.as_path())
-			{
-				Ok((m_cycles, duration)) => {
-					println!("Success! Ran {} M-Cycles in {}ms", m_cycles, duration.as_millis());
-				}
-				Err(why) => {
-					eprintln!("{}", why);
-					std::process::exit(1);
-				}
-			}
+			let result =
+				run_serial_test(rom.as_path(), maximum_m_cycles, expected_serial.as_path());
+			finish_test(&rom, &reports, instant, result);
+		}
+		Operation::TestPrinter { maximum_m_cycles, expected_print, diagnostics_dir } => {
+			let result = printer::run_test(
+				rom.as_path(),
+				maximum_m_cycles,
+				expected_print.as_path(),
+				diagnostics_dir.as_path(),
+			);
+			finish_test(&rom, &reports, instant, result);
+		}
+		Operation::TestLinked { peer, maximum_m_cycles, expected_serial, peer_expected_serial } => {
+			let result = run_linked_test(
+				rom.as_path(),
+				peer.as_path(),
+				maximum_m_cycles,
+				expected_serial.as_path(),
+				peer_expected_serial.as_path(),
+			);
+			finish_test(&rom, &reports, instant, result);
+		}
+		Operation::TestMovie { maximum_m_cycles, movie } => {
+			let result = movie::run_test(rom.as_path(), movie.as_path(), maximum_m_cycles);
+			finish_test(&rom, &reports, instant, result);
+		}
+		Operation::TestMooneye { maximum_m_cycles } => {
+			let result = run_mooneye(rom.as_path(), maximum_m_cycles);
+			finish_test(&rom, &reports, instant, result);
+		}
+		Operation::TestBlarggMemory { maximum_m_cycles } => {
+			let result = run_blargg_memory(rom.as_path(), maximum_m_cycles).map(
+				|(m_cycles, duration, text)| {
+					println!("{}", text.trim_end());
+					(m_cycles, duration)
+				},
+			);
+			finish_test(&rom, &reports, instant, result);
 		}
 		Operation::GenerateOutputSerial { m_cycles, expected_serial } => {
-			match generate_output::<false>(args.rom.as_path(), m_cycles, expected_serial.as_path())
-			{
+			match generate_output::<false>(rom.as_path(), m_cycles, expected_serial.as_path()) {
 				Ok(duration) => {
 					println!("Successfully written serial output to {} in {} M-Cycles ({}ms), please verify it is correct", expected_serial.display(), m_cycles, duration.as_millis());
 				}
@@ -227,14 +893,25 @@ fn main() {
 				}
 			}
 		}
-		Operation::TestFramebuffer { maximum_m_cycles, expected_framebuffer } => {
-			match run_test::<true>(
-				args.rom.as_path(),
+		Operation::TestFramebuffer {
+			maximum_m_cycles,
+			expected_framebuffer,
+			compare,
+			diagnostics_dir,
+		} => {
+			let check = FramebufferCheck { comparison: compare, diagnostics_dir };
+			let result = run_framebuffer_test(
+				rom.as_path(),
 				maximum_m_cycles,
 				expected_framebuffer.as_path(),
-			) {
-				Ok((m_cycles, duration)) => {
-					println!("Success! Ran {} M-Cycles in {}ms", m_cycles, duration.as_millis());
+				&check,
+			);
+			finish_test(&rom, &reports, instant, result);
+		}
+		Operation::GenerateOutputFramebuffer { m_cycles, expected_framebuffer } => {
+			match generate_output::<true>(rom.as_path(), m_cycles, expected_framebuffer.as_path()) {
+				Ok(duration) => {
+					println!("Successfully written framebuffer output to {} in {} M-Cycles ({}ms), please verify it is correct", expected_framebuffer.display(), m_cycles, duration.as_millis());
 				}
 				Err(why) => {
 					eprintln!("{}", why);
@@ -242,14 +919,10 @@ fn main() {
 				}
 			}
 		}
-		Operation::GenerateOutputFramebuffer { m_cycles, expected_framebuffer } => {
-			match generate_output::<true>(
-				args.rom.as_path(),
-				m_cycles,
-				expected_framebuffer.as_path(),
-			) {
+		Operation::GenerateOutputPrinter { m_cycles, expected_print } => {
+			match printer::generate_output(rom.as_path(), m_cycles, expected_print.as_path()) {
 				Ok(duration) => {
-					println!("Successfully written framebuffer output to {} in {} M-Cycles ({}ms), please verify it is correct", expected_framebuffer.display(), m_cycles, duration.as_millis());
+					println!("Successfully written printer output to {} in {} M-Cycles ({}ms), please verify it is correct", expected_print.display(), m_cycles, duration.as_millis());
 				}
 				Err(why) => {
 					eprintln!("{}", why);
@@ -257,5 +930,119 @@ fn main() {
 				}
 			}
 		}
+		Operation::Bench { frames, profile, output, baseline, tolerance } => {
+			if let Err(why) =
+				run_bench(&rom, frames, profile, output.as_deref(), baseline.as_deref(), tolerance)
+			{
+				eprintln!("{}", why);
+				std::process::exit(1);
+			}
+		}
+		Operation::RunSuite { .. } => unreachable!(),
+	}
+}
+
+/// A cartridge with RAM which writes the blargg signature and `text`, then
+/// sets the status byte to each of `statuses` in turn
+#[cfg(test)]
+fn blargg_memory_program(statuses: &[u8], text: &str) -> Vec<u8> {
+	let mut rom = vec![0; 0x8000];
+	rom[0x147] = 3;
+	rom[0x149] = 2;
+
+	// LD A,n then LD (nn),A
+	let store = |address: u16, value: u8| {
+		let [low, high] = address.to_le_bytes();
+		[0x3E, value, 0xEA, low, high]
+	};
+
+	let mut program = store(0x0000, 0x0A).to_vec();
+	let mut ram = BLARGG_SIGNATURE.to_vec();
+	ram.extend(text.bytes());
+	ram.push(0);
+	for (address, value) in (0xA001..).zip(ram) {
+		program.extend(store(address, value));
+	}
+	for status in statuses {
+		program.extend(store(0xA000, *status));
+	}
+	program.extend([0x18, 0xFE]); // JR -2
+
+	// JP 0x150, past the header
+	rom[0x100..0x103].copy_from_slice(&[0xC3, 0x50, 0x01]);
+	rom[0x150..0x150 + program.len()].copy_from_slice(&program);
+	rom
+}
+
+#[cfg(test)]
+fn run_blargg_program(statuses: &[u8], text: &str) -> Result<String, DmgTestError> {
+	let emulator = Emulator::new(std::io::sink(), Some(blargg_memory_program(statuses, text)));
+	match wait_for_blargg_status(emulator, 10_000) {
+		Ok((_, _, text)) => Ok(text),
+		Err(DmgTestError::AfterMCycles(_, why)) => Err(*why),
+		Err(why) => Err(why),
 	}
 }
+
+#[test]
+fn test_blargg_memory() {
+	assert_eq!(run_blargg_program(&[0x80, 0], "Passed").unwrap(), "Passed");
+
+	assert!(matches!(
+		run_blargg_program(&[0x80, 1], "Failed #1"),
+		Err(DmgTestError::BlarggFailed(1, text)) if text == "Failed #1"
+	));
+
+	assert!(matches!(
+		run_blargg_program(&[0x80], "Running"),
+		Err(DmgTestError::BlarggTimeout(Some(text))) if text == "Running"
+	));
+
+	// A result which was never preceded by the test running is not one
+	assert!(matches!(
+		run_blargg_program(&[0], "Passed"),
+		Err(DmgTestError::BlarggTimeout(Some(_)))
+	));
+}
+
+/// Loads `registers` into B, C, D, E, H and L then executes `LD B,B`, unless
+/// `breakpoint` is false
+#[cfg(test)]
+fn run_mooneye_program(registers: [u8; 6], breakpoint: bool) -> Result<u64, DmgTestError> {
+	let [b, c, d, e, h, l] = registers;
+	// LD B,B, or a NOP in its place
+	let last = match breakpoint {
+		true => 0x40,
+		false => 0x00,
+	};
+	#[rustfmt::skip]
+	let program = [
+		// LD B,b; LD C,c; LD D,d; LD E,e; LD H,h; LD L,l
+		0x06, b, 0x0E, c, 0x16, d, 0x1E, e, 0x26, h, 0x2E, l,
+		// LD B,B or NOP; JR -2
+		last, 0x18, 0xFE,
+	];
+	let emulator = Emulator::from_gameboy(meowgb_core::setup_test_emulator(program));
+
+	match wait_for_mooneye_breakpoint(emulator, 1000) {
+		Ok((m_cycles, _)) => Ok(m_cycles),
+		Err(DmgTestError::AfterMCycles(_, why)) => Err(*why),
+		Err(why) => Err(why),
+	}
+}
+
+#[test]
+fn test_mooneye() {
+	assert!(run_mooneye_program(MOONEYE_PASS_SIGNATURE, true).unwrap() < 1000);
+
+	let failed = [0x42, 0x42, 0x42, 0x42, 0x42, 0x42];
+	assert!(matches!(
+		run_mooneye_program(failed, true),
+		Err(DmgTestError::MooneyeFailed(registers)) if registers == failed
+	));
+
+	assert!(matches!(
+		run_mooneye_program(MOONEYE_PASS_SIGNATURE, false),
+		Err(DmgTestError::SoftwareBreakpointTimeout)
+	));
+}
@@ -0,0 +1,60 @@
+//! Playing input movies back headless, failing on the first frame which does
+//! not match the recording
+
+use std::{
+	path::Path,
+	time::{Duration, Instant},
+};
+
+use meowgb_core::gameboy::movie::Movie;
+
+use crate::{new_emulator, DmgTestError};
+
+/// Plays the movie at `movie_path` on `rom_path` until every frame it recorded
+/// has been played
+pub fn run_test(
+	rom_path: &Path,
+	movie_path: &Path,
+	maximum_m_cycles: u64,
+) -> Result<(u64, Duration), DmgTestError> {
+	if !rom_path.is_file() {
+		return Err(DmgTestError::RomNotFound);
+	}
+	let rom = std::fs::read(rom_path).map_err(DmgTestError::RomRead)?;
+
+	if !movie_path.is_file() {
+		return Err(DmgTestError::OutputFileNotFound);
+	}
+	let movie = Movie::read(movie_path)?;
+
+	let mut emulator = new_emulator(std::io::sink(), rom);
+	let mut player = emulator.play_movie(movie)?;
+
+	let instant = Instant::now();
+
+	for cycle_counter in 1..=maximum_m_cycles {
+		let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+			emulator.tick_playing(&mut player)
+		}));
+
+		match result {
+			Ok(result) => {
+				result.map_err(|why| DmgTestError::from(why).after(cycle_counter))?;
+			}
+			Err(why) => {
+				eprintln!(
+					"Emulator crashed on frame {} of the movie\n{}",
+					player.frame(),
+					emulator.gameboy().crash_report()
+				);
+				std::panic::resume_unwind(why);
+			}
+		}
+
+		if player.finished() {
+			return Ok((cycle_counter, instant.elapsed()));
+		}
+	}
+
+	Err(DmgTestError::MovieTimeout(player.frame(), player.frames()).after(maximum_m_cycles))
+}
@@ -0,0 +1,117 @@
+//! Running ROMs with a Game Boy Printer plugged in and checking what they print
+
+use std::{
+	path::Path,
+	time::{Duration, Instant},
+};
+
+use meowgb_core::{
+	emulator::Emulator,
+	gameboy::printer::{PrintedStrip, Printer, PRINT_WIDTH},
+};
+
+use crate::{advance_or_report, new_emulator, run_or_report, DmgTestError};
+
+/// Everything printed, with the strips joined top to bottom
+fn paper(strips: &[PrintedStrip]) -> Option<PrintedStrip> {
+	let (first, last) = (strips.first()?, strips.last()?);
+
+	Some(PrintedStrip {
+		height: strips.iter().map(|strip| strip.height).sum(),
+		rgba: strips.iter().flat_map(|strip| strip.rgba.iter().copied()).collect(),
+		margin_before: first.margin_before,
+		margin_after: last.margin_after,
+	})
+}
+
+fn read_png(path: &Path) -> Result<(u32, u32, Vec<u8>), DmgTestError> {
+	if !path.is_file() {
+		return Err(DmgTestError::OutputFileNotFound);
+	}
+
+	let file = std::fs::File::open(path).map_err(DmgTestError::OutputFileRead)?;
+	let mut decoder = png::Decoder::new(std::io::BufReader::new(file));
+	decoder.set_transformations(png::Transformations::EXPAND);
+	let mut reader = decoder.read_info().map_err(DmgTestError::ExpectedPrintDecode)?;
+
+	let mut rgba = vec![0; reader.output_buffer_size()];
+	let info = reader.next_frame(&mut rgba).map_err(DmgTestError::ExpectedPrintDecode)?;
+	if info.color_type != png::ColorType::Rgba || info.bit_depth != png::BitDepth::Eight {
+		return Err(DmgTestError::ExpectedPrintFormat);
+	}
+	rgba.truncate(info.buffer_size());
+
+	Ok((info.width, info.height, rgba))
+}
+
+fn new_printer_emulator(rom: &Path) -> Result<Emulator<Printer>, DmgTestError> {
+	if !rom.is_file() {
+		return Err(DmgTestError::RomNotFound);
+	}
+	let rom = std::fs::read(rom).map_err(DmgTestError::RomRead)?;
+
+	Ok(new_emulator(Printer::new(), rom))
+}
+
+/// Runs `rom` until everything it has printed matches the PNG at `expected`.
+/// If it never does, what was printed is written to `diagnostics_dir`
+pub fn run_test(
+	rom_path: &Path,
+	maximum_m_cycles: u64,
+	expected: &Path,
+	diagnostics_dir: &Path,
+) -> Result<(u64, Duration), DmgTestError> {
+	let (width, height, expected) = read_png(expected)?;
+	if width != PRINT_WIDTH {
+		return Err(DmgTestError::ExpectedPrintFormat);
+	}
+
+	let mut emulator = new_printer_emulator(rom_path)?;
+	let mut strips = 0;
+
+	let instant = Instant::now();
+
+	let mut cycle_counter = 0;
+
+	while cycle_counter < maximum_m_cycles {
+		let (advanced, _) = advance_or_report(&mut emulator, maximum_m_cycles - cycle_counter);
+		cycle_counter += advanced;
+
+		let printed = &emulator.gameboy().bus.serial.device.printed;
+		if printed.len() != strips {
+			strips = printed.len();
+
+			let paper = paper(printed).expect("something was printed");
+			if paper.height == height && paper.rgba == expected {
+				return Ok((cycle_counter, instant.elapsed()));
+			}
+		}
+	}
+
+	let Some(paper) = paper(&emulator.gameboy().bus.serial.device.printed) else {
+		return Err(DmgTestError::NothingPrinted.after(maximum_m_cycles));
+	};
+
+	std::fs::create_dir_all(diagnostics_dir).map_err(DmgTestError::DiagnosticsWrite)?;
+	let name = rom_path.file_stem().unwrap_or_default().to_string_lossy();
+	let diagnostics = diagnostics_dir.join(format!("{}.print.png", name));
+	paper.write_png(&diagnostics).map_err(DmgTestError::DiagnosticsEncode)?;
+
+	Err(DmgTestError::PrintDifferent(diagnostics).after(maximum_m_cycles))
+}
+
+/// Runs `rom` for `m_cycles` M-cycles, writing everything it printed to
+/// `output` as a PNG
+pub fn generate_output(rom: &Path, m_cycles: u64, output: &Path) -> Result<Duration, DmgTestError> {
+	let mut emulator = new_printer_emulator(rom)?;
+
+	let instant = Instant::now();
+
+	run_or_report(&mut emulator, m_cycles);
+
+	let paper =
+		paper(&emulator.gameboy().bus.serial.device.printed).ok_or(DmgTestError::NothingPrinted)?;
+	paper.write_png(output).map_err(DmgTestError::PrintWrite)?;
+
+	Ok(instant.elapsed())
+}
@@ -0,0 +1,305 @@
+//! Machine readable reports of test results, for CI systems
+
+use std::{
+	fmt::Write,
+	path::{Path, PathBuf},
+	time::Duration,
+};
+
+use serde::Serialize;
+
+use crate::{suite::Outcome, DmgTestError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ReportFormat {
+	/// JUnit XML, understood by most CI systems
+	Junit,
+	Json,
+}
+
+/// A `--report FORMAT PATH` argument
+#[derive(Debug, Clone)]
+pub struct ReportTarget {
+	pub format: ReportFormat,
+	pub path: PathBuf,
+}
+
+impl ReportTarget {
+	/// Parses the values given to every `--report`, two each
+	pub fn from_args(args: &[String]) -> Result<Vec<Self>, DmgTestError> {
+		args.chunks(2)
+			.map(|pair| {
+				let [format, path] = pair else {
+					return Err(DmgTestError::ReportArgs);
+				};
+
+				let format = <ReportFormat as clap::ValueEnum>::from_str(format, true)
+					.map_err(|_| DmgTestError::ReportFormat(format.clone()))?;
+
+				Ok(Self { format, path: PathBuf::from(path) })
+			})
+			.collect()
+	}
+}
+
+/// The result of running one ROM
+pub struct TestResult<'a> {
+	pub name: String,
+	/// What the test is grouped under, e.g. its section in the manifest
+	pub class: String,
+	pub rom: &'a Path,
+	pub outcome: &'a Outcome,
+}
+
+#[derive(Serialize)]
+struct JsonReport<'a> {
+	tests: usize,
+	passed: usize,
+	failed: usize,
+	skipped: usize,
+	time_ms: u128,
+	results: Vec<JsonResult<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonResult<'a> {
+	name: &'a str,
+	class: &'a str,
+	rom: &'a Path,
+	status: &'static str,
+	/// Not known for tests which were skipped or failed before running
+	m_cycles: Option<u64>,
+	time_ms: Option<u128>,
+	message: Option<&'a str>,
+	/// What the test reported, for tests which report anything when they pass
+	output: Option<&'a str>,
+}
+
+impl Outcome {
+	fn m_cycles(&self) -> Option<u64> {
+		match self {
+			Outcome::Passed(m_cycles, ..) => Some(*m_cycles),
+			Outcome::Failed(_, m_cycles, _) => *m_cycles,
+			Outcome::Skipped(_) => None,
+		}
+	}
+
+	fn duration(&self) -> Option<Duration> {
+		match self {
+			Outcome::Passed(_, duration, _) | Outcome::Failed(_, _, duration) => Some(*duration),
+			Outcome::Skipped(_) => None,
+		}
+	}
+
+	fn output(&self) -> Option<&str> {
+		match self {
+			Outcome::Passed(_, _, output) => output.as_deref(),
+			_ => None,
+		}
+	}
+
+	fn message(&self) -> Option<&str> {
+		match self {
+			Outcome::Passed(..) => None,
+			Outcome::Failed(why, ..) | Outcome::Skipped(why) => Some(why),
+		}
+	}
+}
+
+fn count(results: &[TestResult], f: fn(&Outcome) -> bool) -> usize {
+	results.iter().filter(|result| f(result.outcome)).count()
+}
+
+fn escape_xml(value: &str) -> String {
+	let mut out = String::with_capacity(value.len());
+
+	for c in value.chars() {
+		match c {
+			'&' => out.push_str("&amp;"),
+			'<' => out.push_str("&lt;"),
+			'>' => out.push_str("&gt;"),
+			'"' => out.push_str("&quot;"),
+			'\'' => out.push_str("&apos;"),
+			// Serial output can contain anything, XML 1.0 can't contain most
+			// control characters even when escaped
+			c if c.is_control() && !matches!(c, '\n' | '\r' | '\t') => {
+				let _ = write!(out, "\\x{:02X}", c as u32);
+			}
+			c => out.push(c),
+		}
+	}
+
+	out
+}
+
+fn junit(results: &[TestResult], elapsed: Duration) -> String {
+	let tests = results.len();
+	let failures = count(results, |x| matches!(x, Outcome::Failed(..)));
+	let skipped = count(results, |x| matches!(x, Outcome::Skipped(..)));
+
+	let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+	let _ = writeln!(
+		out,
+		"<testsuites name=\"meowgb-tests\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{:.3}\">",
+		tests,
+		failures,
+		skipped,
+		elapsed.as_secs_f64()
+	);
+	let _ = writeln!(
+		out,
+		"\t<testsuite name=\"meowgb-tests\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{:.3}\">",
+		tests,
+		failures,
+		skipped,
+		elapsed.as_secs_f64()
+	);
+
+	for result in results {
+		let _ = writeln!(
+			out,
+			"\t\t<testcase name=\"{}\" classname=\"{}\" file=\"{}\" time=\"{:.3}\">",
+			escape_xml(&result.name),
+			escape_xml(&result.class),
+			escape_xml(&result.rom.to_string_lossy()),
+			result.outcome.duration().unwrap_or_default().as_secs_f64()
+		);
+
+		match result.outcome {
+			Outcome::Passed(m_cycles, _, output) => {
+				let _ = writeln!(
+					out,
+					"\t\t\t<system-out>Ran {} M-Cycles{}</system-out>",
+					m_cycles,
+					output
+						.as_deref()
+						.map(|output| format!("\n{}", escape_xml(output)))
+						.unwrap_or_default()
+				);
+			}
+			Outcome::Failed(why, m_cycles, _) => {
+				let first_line = why.lines().next().unwrap_or_default();
+				let _ = writeln!(
+					out,
+					"\t\t\t<failure message=\"{}\">{}</failure>",
+					escape_xml(first_line),
+					escape_xml(why)
+				);
+				if let Some(m_cycles) = m_cycles {
+					let _ =
+						writeln!(out, "\t\t\t<system-out>Ran {} M-Cycles</system-out>", m_cycles);
+				}
+			}
+			Outcome::Skipped(why) => {
+				let _ = writeln!(out, "\t\t\t<skipped message=\"{}\"/>", escape_xml(why));
+			}
+		}
+
+		out.push_str("\t\t</testcase>\n");
+	}
+
+	out.push_str("\t</testsuite>\n</testsuites>\n");
+	out
+}
+
+fn json(results: &[TestResult], elapsed: Duration) -> String {
+	let report = JsonReport {
+		tests: results.len(),
+		passed: count(results, |x| matches!(x, Outcome::Passed(..))),
+		failed: count(results, |x| matches!(x, Outcome::Failed(..))),
+		skipped: count(results, |x| matches!(x, Outcome::Skipped(..))),
+		time_ms: elapsed.as_millis(),
+		results: results
+			.iter()
+			.map(|result| JsonResult {
+				name: &result.name,
+				class: &result.class,
+				rom: result.rom,
+				status: result.outcome.status(),
+				m_cycles: result.outcome.m_cycles(),
+				time_ms: result.outcome.duration().map(|duration| duration.as_millis()),
+				message: result.outcome.message(),
+				output: result.outcome.output(),
+			})
+			.collect(),
+	};
+
+	let mut out = serde_json::to_string_pretty(&report).unwrap();
+	out.push('\n');
+	out
+}
+
+/// Writes a report of `results`, which took `elapsed` to run in total
+pub fn write_report(
+	target: &ReportTarget,
+	results: &[TestResult],
+	elapsed: Duration,
+) -> Result<(), DmgTestError> {
+	let report = match target.format {
+		ReportFormat::Junit => junit(results, elapsed),
+		ReportFormat::Json => json(results, elapsed),
+	};
+
+	std::fs::write(&target.path, report).map_err(DmgTestError::ReportWrite)
+}
+
+#[test]
+fn test_failed_m_cycles() {
+	let outcome =
+		Outcome::from_result(Err(DmgTestError::NoFrameDrawn.after(1000)), Duration::from_millis(5));
+	let rom = PathBuf::from("rom.gb");
+	let results = [TestResult {
+		name: String::from("rom.gb"),
+		class: String::from("suite"),
+		rom: &rom,
+		outcome: &outcome,
+	}];
+
+	let report: serde_json::Value = serde_json::from_str(&json(&results, Duration::ZERO)).unwrap();
+	assert_eq!(report["results"][0]["m_cycles"], 1000);
+	assert_eq!(report["results"][0]["message"], DmgTestError::NoFrameDrawn.to_string());
+	assert!(junit(&results, Duration::ZERO).contains("Ran 1000 M-Cycles"));
+}
+
+#[test]
+fn test_passed_output() {
+	let outcome = Outcome::from_result(Ok((1000, Duration::from_millis(5))), Duration::ZERO)
+		.with_output(Some(String::from("cpu_instrs\n\nPassed")));
+	let rom = PathBuf::from("rom.gb");
+	let results = [TestResult {
+		name: String::from("rom.gb"),
+		class: String::from("suite"),
+		rom: &rom,
+		outcome: &outcome,
+	}];
+
+	let report: serde_json::Value = serde_json::from_str(&json(&results, Duration::ZERO)).unwrap();
+	assert_eq!(report["results"][0]["output"], "cpu_instrs\n\nPassed");
+	assert!(junit(&results, Duration::ZERO)
+		.contains("<system-out>Ran 1000 M-Cycles\ncpu_instrs\n\nPassed</system-out>"));
+}
+
+#[test]
+fn test_several_report_targets() {
+	let args = ["junit", "a.xml", "JSON", "b.json"].map(String::from);
+	let reports = ReportTarget::from_args(&args).unwrap();
+	assert_eq!(reports.len(), 2);
+	assert_eq!(
+		(reports[0].format, reports[0].path.as_path()),
+		(ReportFormat::Junit, Path::new("a.xml"))
+	);
+	assert_eq!(
+		(reports[1].format, reports[1].path.as_path()),
+		(ReportFormat::Json, Path::new("b.json"))
+	);
+
+	assert!(ReportTarget::from_args(&[]).unwrap().is_empty());
+	assert!(matches!(
+		ReportTarget::from_args(&["junit", "a.xml", "xml", "b.xml"].map(String::from)),
+		Err(DmgTestError::ReportFormat(format)) if format == "xml"
+	));
+	assert!(matches!(
+		ReportTarget::from_args(&["junit"].map(String::from)),
+		Err(DmgTestError::ReportArgs)
+	));
+}
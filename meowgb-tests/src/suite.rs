@@ -0,0 +1,280 @@
+//! Running a whole manifest of test ROMs at once
+
+use std::{
+	fmt::Write,
+	path::{Path, PathBuf},
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		Mutex,
+	},
+	time::{Duration, Instant},
+};
+
+use serde::Deserialize;
+
+use crate::{
+	framebuffer::{Comparison, FramebufferCheck},
+	movie, printer, run_blargg_memory, run_for, run_framebuffer_test, run_mooneye, run_serial_test,
+	DmgTestError,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct Manifest {
+	#[serde(rename = "test")]
+	pub tests: Vec<TestEntry>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct TestEntry {
+	/// The heading the test is listed under in tests.md
+	pub section: String,
+	/// The subheading the test is listed under in tests.md, this is also used
+	/// as a prefix for the test's name
+	pub subsection: Option<String>,
+	pub rom: PathBuf,
+	pub mode: TestMode,
+	pub expected: Option<PathBuf>,
+	pub max_m_cycles: u64,
+	pub model: HardwareModel,
+	/// How pixels are compared for `framebuffer` tests
+	#[serde(default)]
+	pub compare: Comparison,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TestMode {
+	/// The serial output must match the expected output
+	Serial,
+	/// A frame must match the expected framebuffer
+	Framebuffer,
+	/// Everything printed on a Game Boy Printer must match the expected PNG
+	Printer,
+	/// The input movie given as the expected output must play back without
+	/// desyncing
+	Movie,
+	/// The ROM must execute `LD B,B` with the mooneye pass signature in the
+	/// registers, no expected output is needed
+	Mooneye,
+	/// The ROM must report a pass through the blargg status byte, signature and
+	/// text at 0xA000, no expected output is needed
+	#[serde(rename = "blargg-memory")]
+	BlarggMemory,
+	/// The ROM must run for the whole M-cycle limit without crashing
+	Other,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HardwareModel {
+	Dmg0,
+	Dmg,
+	Mgb,
+	Sgb,
+	Sgb2,
+	Cgb,
+	Agb,
+}
+
+impl HardwareModel {
+	/// Only the DMG (revisions A/B/C) is emulated
+	pub fn is_supported(self) -> bool {
+		self == HardwareModel::Dmg
+	}
+}
+
+impl TestEntry {
+	pub fn name(&self) -> String {
+		let file_name = self.rom.file_name().unwrap_or_default().to_string_lossy();
+
+		match self.subsection.as_ref() {
+			Some(subsection) => format!("{}/{}", subsection, file_name),
+			None => file_name.into_owned(),
+		}
+	}
+
+	fn run(&self, base: &Path, diagnostics_dir: &Path) -> Outcome {
+		if !self.model.is_supported() {
+			return Outcome::Skipped(format!("{:?} is not emulated", self.model));
+		}
+
+		let rom = base.join(&self.rom);
+		let expected = self.expected.as_ref().map(|expected| base.join(expected));
+		let check = FramebufferCheck {
+			comparison: self.compare,
+			diagnostics_dir: diagnostics_dir.join(self.subsection.as_deref().unwrap_or_default()),
+		};
+		let instant = Instant::now();
+		// The text blargg tests write to memory, which is kept even when they pass
+		let mut output = None;
+
+		let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+			match (self.mode, expected.as_deref()) {
+				(TestMode::Serial, Some(expected)) => {
+					run_serial_test(&rom, self.max_m_cycles, expected)
+				}
+				(TestMode::Framebuffer, Some(expected)) => {
+					run_framebuffer_test(&rom, self.max_m_cycles, expected, &check)
+				}
+				(TestMode::Printer, Some(expected)) => {
+					printer::run_test(&rom, self.max_m_cycles, expected, &check.diagnostics_dir)
+				}
+				(TestMode::Movie, Some(expected)) => {
+					movie::run_test(&rom, expected, self.max_m_cycles)
+				}
+				(
+					TestMode::Serial | TestMode::Framebuffer | TestMode::Printer | TestMode::Movie,
+					None,
+				) => Err(DmgTestError::OutputFileNotFound),
+				(TestMode::Mooneye, _) => run_mooneye(&rom, self.max_m_cycles),
+				(TestMode::BlarggMemory, _) => {
+					run_blargg_memory(&rom, self.max_m_cycles).map(|(m_cycles, duration, text)| {
+						output = Some(text);
+						(m_cycles, duration)
+					})
+				}
+				(TestMode::Other, _) => run_for(&rom, self.max_m_cycles),
+			}
+		}));
+
+		match result {
+			Ok(result) => Outcome::from_result(result, instant.elapsed()).with_output(output),
+			Err(_) => Outcome::Failed(String::from("Emulator crashed"), None, instant.elapsed()),
+		}
+	}
+}
+
+#[derive(Debug)]
+pub enum Outcome {
+	/// (M-cycles ran, time taken, text the test reported)
+	Passed(u64, Duration, Option<String>),
+	/// (Reason, M-cycles ran if it got as far as running, time taken)
+	Failed(String, Option<u64>, Duration),
+	Skipped(String),
+}
+
+impl Manifest {
+	pub fn from_file(path: &Path) -> Result<Self, DmgTestError> {
+		let source = std::fs::read_to_string(path).map_err(DmgTestError::ManifestRead)?;
+		toml::from_str(source.as_str()).map_err(DmgTestError::ManifestParse)
+	}
+
+	/// Runs every test in the manifest across `jobs` threads, resolving paths
+	/// relative to `base`. The outcomes are in the same order as the tests.
+	pub fn run(&self, base: &Path, jobs: usize, diagnostics_dir: &Path) -> Vec<Outcome> {
+		let next = AtomicUsize::new(0);
+		let outcomes = Mutex::new((0..self.tests.len()).map(|_| None).collect::<Vec<_>>());
+
+		std::thread::scope(|scope| {
+			for _ in 0..jobs.max(1) {
+				scope.spawn(|| loop {
+					let idx = next.fetch_add(1, Ordering::Relaxed);
+					let Some(test) = self.tests.get(idx) else {
+						break;
+					};
+
+					let outcome = test.run(base, diagnostics_dir);
+					println!("{} {}", outcome.status(), test.name());
+					outcomes.lock().unwrap()[idx] = Some(outcome);
+				});
+			}
+		});
+
+		outcomes.into_inner().unwrap().into_iter().map(Option::unwrap).collect()
+	}
+
+	/// Generates tests.md, listing every test which passed
+	pub fn tests_md(&self, outcomes: &[Outcome]) -> String {
+		let mut out = String::from("# Passing Tests\n");
+		let mut section = None;
+		let mut subsection = None;
+
+		for (test, _) in self
+			.tests
+			.iter()
+			.zip(outcomes)
+			.filter(|(_, outcome)| matches!(outcome, Outcome::Passed(..)))
+		{
+			if section != Some(&test.section) {
+				section = Some(&test.section);
+				subsection = None;
+				let _ = write!(out, "\n## {}\n\n", test.section);
+			}
+
+			if subsection != test.subsection.as_ref() {
+				subsection = test.subsection.as_ref();
+				if let Some(subsection) = subsection {
+					let _ = write!(out, "\n### {}\n\n", subsection);
+				}
+			}
+
+			let _ = write!(out, "* {} - [ROM](./{})", test.name(), test.rom.display());
+
+			let label = match test.mode {
+				TestMode::Serial => Some("Expected Serial Output"),
+				TestMode::Framebuffer => Some("Expected Framebuffer (RGBA32)"),
+				TestMode::Printer => Some("Expected Print (PNG)"),
+				TestMode::Movie => Some("Input Movie"),
+				TestMode::Mooneye | TestMode::BlarggMemory | TestMode::Other => None,
+			};
+
+			if let (Some(label), Some(expected)) = (label, test.expected.as_ref()) {
+				let _ = write!(out, " - [{}](./{})", label, expected.display());
+			}
+
+			out.push('\n');
+		}
+
+		out
+	}
+}
+
+impl Outcome {
+	/// Converts the result of running a test, `elapsed` is used as the time
+	/// taken if it failed
+	pub fn from_result(result: Result<(u64, Duration), DmgTestError>, elapsed: Duration) -> Self {
+		match result {
+			Ok((m_cycles, duration)) => Outcome::Passed(m_cycles, duration, None),
+			Err(why) => Outcome::Failed(why.to_string(), why.m_cycles(), elapsed),
+		}
+	}
+
+	/// Adds the text a passing test reported to the outcome
+	pub fn with_output(self, output: Option<String>) -> Self {
+		match self {
+			Outcome::Passed(m_cycles, duration, _) => Outcome::Passed(m_cycles, duration, output),
+			outcome => outcome,
+		}
+	}
+
+	pub fn status(&self) -> &'static str {
+		match self {
+			Outcome::Passed(..) => "PASS",
+			Outcome::Failed(..) => "FAIL",
+			Outcome::Skipped(..) => "SKIP",
+		}
+	}
+}
+
+impl std::fmt::Display for Outcome {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Outcome::Passed(m_cycles, duration, _) => f.write_fmt(format_args!(
+				"PASS ({} M-Cycles in {}ms)",
+				m_cycles,
+				duration.as_millis()
+			)),
+			Outcome::Failed(why, Some(m_cycles), duration) => f.write_fmt(format_args!(
+				"FAIL after {} M-Cycles in {}ms: {}",
+				m_cycles,
+				duration.as_millis(),
+				why
+			)),
+			Outcome::Failed(why, None, duration) => {
+				f.write_fmt(format_args!("FAIL after {}ms: {}", duration.as_millis(), why))
+			}
+			Outcome::Skipped(why) => f.write_fmt(format_args!("SKIP: {}", why)),
+		}
+	}
+}
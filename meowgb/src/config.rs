@@ -1,8 +1,11 @@
 use std::path::Path;
 
+use meowgb_core::gameboy::joypad::Joypad;
 use toml::Value;
 use winit::event::VirtualKeyCode;
 
+use crate::gamepad::GamepadConfig;
+
 #[derive(Debug, thiserror::Error)]
 pub enum ConfigError {
 	#[error("Config source not found")]
@@ -76,9 +79,13 @@ impl ConfigParser {
 	}
 }
 
-#[derive(Debug, serde::Deserialize, Clone, Copy)]
+#[derive(Debug, serde::Deserialize, Clone)]
 pub struct MeowGBConfig {
 	pub bindings: Bindings,
+	pub gamepad: GamepadConfig,
+	pub turbo: Turbo,
+	#[serde(default)]
+	pub macros: Vec<InputMacro>,
 }
 
 impl MeowGBConfig {
@@ -95,8 +102,35 @@ impl MeowGBConfig {
 			right = "Right"
 			pause = "P"
 			exit = "Escape"
+			save_state = "F5"
+			load_state = "F8"
+			reset = "F12"
+			fast_forward = "Tab"
+			turbo_a = "Z"
+			turbo_b = "X"
 			log_ops = "L"
 			dump_memory = "Comma"
+
+			[turbo]
+			rate = 4
+
+			[gamepad]
+			deadzone = 0.5
+
+			[gamepad.bindings]
+			a = "East"
+			b = "South"
+			select = "Select"
+			start = "Start"
+			up = "DPadUp"
+			down = "DPadDown"
+			left = "DPadLeft"
+			right = "DPadRight"
+			fast_forward = "RightTrigger2"
+
+			[gamepad.axes]
+			horizontal = "LeftStickX"
+			vertical = "LeftStickY"
 		}));
 
 		if let Some(path) = home::home_dir().and_then(|mut path| {
@@ -123,6 +157,68 @@ pub struct Bindings {
 
 	pub pause: VirtualKeyCode,
 	pub exit: VirtualKeyCode,
+	pub save_state: VirtualKeyCode,
+	pub load_state: VirtualKeyCode,
+	pub reset: VirtualKeyCode,
+	/// Held rather than pressed
+	pub fast_forward: VirtualKeyCode,
+	pub turbo_a: VirtualKeyCode,
+	pub turbo_b: VirtualKeyCode,
 
 	pub debug_menu: Option<VirtualKeyCode>,
 }
+
+#[derive(Debug, serde::Deserialize, Clone, Copy)]
+pub struct Turbo {
+	/// How many frames a turbo button is pressed for, and then released for
+	pub rate: u32,
+}
+
+#[derive(Debug, serde::Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Button {
+	A,
+	B,
+	Select,
+	Start,
+	Up,
+	Down,
+	Left,
+	Right,
+}
+
+impl Button {
+	pub fn mask(self) -> u8 {
+		match self {
+			Self::A => Joypad::A,
+			Self::B => Joypad::B,
+			Self::Select => Joypad::SELECT,
+			Self::Start => Joypad::START,
+			Self::Up => Joypad::UP,
+			Self::Down => Joypad::DOWN,
+			Self::Left => Joypad::LEFT,
+			Self::Right => Joypad::RIGHT,
+		}
+	}
+}
+
+/// A sequence of buttons played back frame by frame when `key` is pressed
+#[derive(Debug, serde::Deserialize, Clone)]
+pub struct InputMacro {
+	pub name: String,
+	pub key: VirtualKeyCode,
+	pub steps: Vec<MacroStep>,
+}
+
+#[derive(Debug, serde::Deserialize, Clone)]
+pub struct MacroStep {
+	/// Held for the whole step, along with anything held on the keyboard
+	#[serde(default)]
+	pub buttons: Vec<Button>,
+	pub frames: u32,
+}
+
+impl MacroStep {
+	pub fn mask(&self) -> u8 {
+		self.buttons.iter().fold(0, |mask, button| mask | button.mask())
+	}
+}
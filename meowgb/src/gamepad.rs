@@ -0,0 +1,388 @@
+//! Game controller input. Controller events are turned into joypad buttons and
+//! hotkeys by [`GamepadMapper`], which knows nothing about where the events
+//! come from. With the `gamepad` feature they are read from gilrs, which also
+//! reports controllers being plugged in and unplugged
+
+use std::collections::BTreeMap;
+
+use meowgb_core::gameboy::joypad::Joypad;
+
+/// A controller button, named after gilrs' `Button`
+#[derive(Debug, serde::Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ControllerButton {
+	South,
+	East,
+	North,
+	West,
+	C,
+	Z,
+	LeftTrigger,
+	LeftTrigger2,
+	RightTrigger,
+	RightTrigger2,
+	Select,
+	Start,
+	Mode,
+	LeftThumb,
+	RightThumb,
+	DPadUp,
+	DPadDown,
+	DPadLeft,
+	DPadRight,
+}
+
+/// A controller axis, named after gilrs' `Axis`. Positive values are right
+/// and up
+#[derive(Debug, serde::Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ControllerAxis {
+	LeftStickX,
+	LeftStickY,
+	LeftZ,
+	RightStickX,
+	RightStickY,
+	RightZ,
+	DPadX,
+	DPadY,
+}
+
+#[derive(Debug, serde::Deserialize, Clone, Copy)]
+pub struct GamepadConfig {
+	/// How far an axis has to be pushed before it counts as a d-pad press,
+	/// from 0 to 1
+	pub deadzone: f32,
+	pub bindings: GamepadBindings,
+	pub axes: GamepadAxes,
+}
+
+#[derive(Debug, serde::Deserialize, Clone, Copy)]
+pub struct GamepadBindings {
+	pub a: Option<ControllerButton>,
+	pub b: Option<ControllerButton>,
+	pub select: Option<ControllerButton>,
+	pub start: Option<ControllerButton>,
+	pub up: Option<ControllerButton>,
+	pub down: Option<ControllerButton>,
+	pub left: Option<ControllerButton>,
+	pub right: Option<ControllerButton>,
+
+	pub pause: Option<ControllerButton>,
+	pub save_state: Option<ControllerButton>,
+	pub load_state: Option<ControllerButton>,
+	pub reset: Option<ControllerButton>,
+	/// Held rather than pressed
+	pub fast_forward: Option<ControllerButton>,
+	pub turbo_a: Option<ControllerButton>,
+	pub turbo_b: Option<ControllerButton>,
+}
+
+/// Axes emulating the d-pad
+#[derive(Debug, serde::Deserialize, Clone, Copy)]
+pub struct GamepadAxes {
+	pub horizontal: Option<ControllerAxis>,
+	pub vertical: Option<ControllerAxis>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ControllerEvent {
+	Connected(usize),
+	Disconnected(usize),
+	Button(usize, ControllerButton, bool),
+	Axis(usize, ControllerAxis, f32),
+}
+
+/// Hotkeys triggered by pressing a controller button
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hotkey {
+	Pause,
+	SaveState,
+	LoadState,
+	Reset,
+}
+
+#[derive(Debug, Default)]
+struct ControllerState {
+	buttons: u8,
+	turbo: u8,
+	horizontal: f32,
+	vertical: f32,
+	fast_forward: bool,
+}
+
+/// Tracks every connected controller, holding a button while any of them
+/// does
+pub struct GamepadMapper {
+	config: GamepadConfig,
+	controllers: BTreeMap<usize, ControllerState>,
+}
+
+impl GamepadMapper {
+	pub fn new(config: GamepadConfig) -> Self {
+		Self { config, controllers: BTreeMap::new() }
+	}
+
+	/// Applies an event, returning the hotkey it pressed if there is one.
+	/// Unplugging a controller releases everything it held
+	pub fn handle(&mut self, event: ControllerEvent) -> Option<Hotkey> {
+		let bindings = &self.config.bindings;
+
+		match event {
+			ControllerEvent::Connected(id) => {
+				self.controllers.insert(id, ControllerState::default());
+			}
+			ControllerEvent::Disconnected(id) => {
+				self.controllers.remove(&id);
+			}
+			ControllerEvent::Button(id, button, pressed) => {
+				let state = self.controllers.entry(id).or_default();
+
+				for (binding, mask, turbo) in [
+					(bindings.a, Joypad::A, false),
+					(bindings.b, Joypad::B, false),
+					(bindings.select, Joypad::SELECT, false),
+					(bindings.start, Joypad::START, false),
+					(bindings.right, Joypad::RIGHT, false),
+					(bindings.left, Joypad::LEFT, false),
+					(bindings.up, Joypad::UP, false),
+					(bindings.down, Joypad::DOWN, false),
+					(bindings.turbo_a, Joypad::A, true),
+					(bindings.turbo_b, Joypad::B, true),
+				] {
+					if binding == Some(button) {
+						let buttons = match turbo {
+							true => &mut state.turbo,
+							false => &mut state.buttons,
+						};
+						match pressed {
+							true => *buttons |= mask,
+							false => *buttons &= !mask,
+						}
+					}
+				}
+
+				if bindings.fast_forward == Some(button) {
+					state.fast_forward = pressed;
+				}
+
+				if pressed {
+					return [
+						(bindings.pause, Hotkey::Pause),
+						(bindings.save_state, Hotkey::SaveState),
+						(bindings.load_state, Hotkey::LoadState),
+						(bindings.reset, Hotkey::Reset),
+					]
+					.into_iter()
+					.find(|(binding, _)| *binding == Some(button))
+					.map(|(_, hotkey)| hotkey);
+				}
+			}
+			ControllerEvent::Axis(id, axis, value) => {
+				let state = self.controllers.entry(id).or_default();
+
+				if self.config.axes.horizontal == Some(axis) {
+					state.horizontal = value;
+				}
+				if self.config.axes.vertical == Some(axis) {
+					state.vertical = value;
+				}
+			}
+		}
+
+		None
+	}
+
+	/// The buttons held on any controller, as a mask of [`Joypad::A`] and
+	/// friends
+	pub fn buttons(&self) -> u8 {
+		let deadzone = self.config.deadzone;
+
+		self.controllers.values().fold(0, |mask, state| {
+			let axes = [
+				(state.horizontal > deadzone, Joypad::RIGHT),
+				(state.horizontal < -deadzone, Joypad::LEFT),
+				(state.vertical > deadzone, Joypad::UP),
+				(state.vertical < -deadzone, Joypad::DOWN),
+			]
+			.into_iter()
+			.filter(|(pushed, _)| *pushed)
+			.fold(0, |mask, (_, button)| mask | button);
+
+			mask | state.buttons | axes
+		})
+	}
+
+	/// The turbo buttons held on any controller
+	pub fn turbo(&self) -> u8 {
+		self.controllers.values().fold(0, |mask, state| mask | state.turbo)
+	}
+
+	/// Whether the fast forward button is held on any controller
+	pub fn fast_forward(&self) -> bool {
+		self.controllers.values().any(|state| state.fast_forward)
+	}
+}
+
+/// Reads controller events from gilrs
+#[cfg(feature = "gamepad")]
+pub struct Gamepads(gilrs::Gilrs);
+
+#[cfg(feature = "gamepad")]
+impl Gamepads {
+	/// Connects to the system's controllers, `None` if that is not supported
+	pub fn new() -> Option<Self> {
+		match gilrs::Gilrs::new() {
+			Ok(gilrs) => Some(Self(gilrs)),
+			Err(why) => {
+				log::error!("Failed to open game controllers: {}", why);
+				None
+			}
+		}
+	}
+
+	/// The next event from a controller, ignoring ones with nothing bindable
+	pub fn next_event(&mut self) -> Option<ControllerEvent> {
+		while let Some(gilrs::Event { id, event, .. }) = self.0.next_event() {
+			let id = usize::from(id);
+			let event = match event {
+				gilrs::EventType::Connected => ControllerEvent::Connected(id),
+				gilrs::EventType::Disconnected => ControllerEvent::Disconnected(id),
+				gilrs::EventType::ButtonPressed(button, _) => match Self::button(button) {
+					Some(button) => ControllerEvent::Button(id, button, true),
+					None => continue,
+				},
+				gilrs::EventType::ButtonReleased(button, _) => match Self::button(button) {
+					Some(button) => ControllerEvent::Button(id, button, false),
+					None => continue,
+				},
+				gilrs::EventType::AxisChanged(axis, value, _) => match Self::axis(axis) {
+					Some(axis) => ControllerEvent::Axis(id, axis, value),
+					None => continue,
+				},
+				_ => continue,
+			};
+
+			return Some(event);
+		}
+
+		None
+	}
+
+	fn button(button: gilrs::Button) -> Option<ControllerButton> {
+		Some(match button {
+			gilrs::Button::South => ControllerButton::South,
+			gilrs::Button::East => ControllerButton::East,
+			gilrs::Button::North => ControllerButton::North,
+			gilrs::Button::West => ControllerButton::West,
+			gilrs::Button::C => ControllerButton::C,
+			gilrs::Button::Z => ControllerButton::Z,
+			gilrs::Button::LeftTrigger => ControllerButton::LeftTrigger,
+			gilrs::Button::LeftTrigger2 => ControllerButton::LeftTrigger2,
+			gilrs::Button::RightTrigger => ControllerButton::RightTrigger,
+			gilrs::Button::RightTrigger2 => ControllerButton::RightTrigger2,
+			gilrs::Button::Select => ControllerButton::Select,
+			gilrs::Button::Start => ControllerButton::Start,
+			gilrs::Button::Mode => ControllerButton::Mode,
+			gilrs::Button::LeftThumb => ControllerButton::LeftThumb,
+			gilrs::Button::RightThumb => ControllerButton::RightThumb,
+			gilrs::Button::DPadUp => ControllerButton::DPadUp,
+			gilrs::Button::DPadDown => ControllerButton::DPadDown,
+			gilrs::Button::DPadLeft => ControllerButton::DPadLeft,
+			gilrs::Button::DPadRight => ControllerButton::DPadRight,
+			gilrs::Button::Unknown => return None,
+		})
+	}
+
+	fn axis(axis: gilrs::Axis) -> Option<ControllerAxis> {
+		Some(match axis {
+			gilrs::Axis::LeftStickX => ControllerAxis::LeftStickX,
+			gilrs::Axis::LeftStickY => ControllerAxis::LeftStickY,
+			gilrs::Axis::LeftZ => ControllerAxis::LeftZ,
+			gilrs::Axis::RightStickX => ControllerAxis::RightStickX,
+			gilrs::Axis::RightStickY => ControllerAxis::RightStickY,
+			gilrs::Axis::RightZ => ControllerAxis::RightZ,
+			gilrs::Axis::DPadX => ControllerAxis::DPadX,
+			gilrs::Axis::DPadY => ControllerAxis::DPadY,
+			gilrs::Axis::Unknown => return None,
+		})
+	}
+}
+
+#[cfg(test)]
+fn test_mapper() -> GamepadMapper {
+	GamepadMapper::new(
+		toml::from_str(
+			r#"
+			deadzone = 0.5
+
+			[bindings]
+			a = "East"
+			b = "South"
+			up = "DPadUp"
+			pause = "Mode"
+			fast_forward = "RightTrigger2"
+			turbo_a = "North"
+
+			[axes]
+			horizontal = "LeftStickX"
+			vertical = "LeftStickY"
+			"#,
+		)
+		.unwrap(),
+	)
+}
+
+#[test]
+fn test_buttons_from_any_controller() {
+	let mut mapper = test_mapper();
+
+	assert_eq!(mapper.handle(ControllerEvent::Button(0, ControllerButton::East, true)), None);
+	mapper.handle(ControllerEvent::Button(1, ControllerButton::South, true));
+	mapper.handle(ControllerEvent::Button(1, ControllerButton::DPadUp, true));
+	assert_eq!(mapper.buttons(), Joypad::A | Joypad::B | Joypad::UP);
+
+	mapper.handle(ControllerEvent::Button(1, ControllerButton::DPadUp, false));
+	assert_eq!(mapper.buttons(), Joypad::A | Joypad::B);
+
+	// Unplugging a controller releases what it held
+	mapper.handle(ControllerEvent::Disconnected(1));
+	assert_eq!(mapper.buttons(), Joypad::A);
+	mapper.handle(ControllerEvent::Connected(1));
+	assert_eq!(mapper.buttons(), Joypad::A);
+}
+
+#[test]
+fn test_axis_deadzone() {
+	let mut mapper = test_mapper();
+
+	mapper.handle(ControllerEvent::Axis(0, ControllerAxis::LeftStickX, 0.4));
+	mapper.handle(ControllerEvent::Axis(0, ControllerAxis::LeftStickY, -0.4));
+	assert_eq!(mapper.buttons(), 0);
+
+	mapper.handle(ControllerEvent::Axis(0, ControllerAxis::LeftStickX, -0.9));
+	mapper.handle(ControllerEvent::Axis(0, ControllerAxis::LeftStickY, 0.6));
+	assert_eq!(mapper.buttons(), Joypad::LEFT | Joypad::UP);
+
+	// Unbound axes are ignored
+	mapper.handle(ControllerEvent::Axis(0, ControllerAxis::RightStickY, -1.0));
+	assert_eq!(mapper.buttons(), Joypad::LEFT | Joypad::UP);
+}
+
+#[test]
+fn test_hotkeys() {
+	let mut mapper = test_mapper();
+
+	assert_eq!(
+		mapper.handle(ControllerEvent::Button(0, ControllerButton::Mode, true)),
+		Some(Hotkey::Pause)
+	);
+	assert_eq!(mapper.handle(ControllerEvent::Button(0, ControllerButton::Mode, false)), None);
+
+	mapper.handle(ControllerEvent::Button(0, ControllerButton::RightTrigger2, true));
+	assert!(mapper.fast_forward());
+	assert_eq!(mapper.buttons(), 0);
+	mapper.handle(ControllerEvent::Button(0, ControllerButton::RightTrigger2, false));
+	assert!(!mapper.fast_forward());
+
+	mapper.handle(ControllerEvent::Button(0, ControllerButton::North, true));
+	assert_eq!(mapper.turbo(), Joypad::A);
+	assert_eq!(mapper.buttons(), 0);
+}
@@ -0,0 +1,203 @@
+//! The buttons held on every frame, built from what the window reports as
+//! held along with turbo buttons and input macros. Turbo and macros only ever
+//! change the buttons at the start of a frame, so they play out identically
+//! every time
+
+use meowgb_core::gameboy::movie::FrameClock;
+
+use crate::config::{InputMacro, Turbo};
+
+#[derive(Debug)]
+struct RunningMacro {
+	index: usize,
+	step: usize,
+	/// Frames left of the current step
+	frames: u32,
+}
+
+pub struct FrameInput {
+	turbo_rate: u32,
+	macros: Vec<InputMacro>,
+	clock: FrameClock,
+	held: u8,
+	turbo: u8,
+	/// Frames since a turbo button started being held
+	turbo_frames: u32,
+	running: Option<RunningMacro>,
+	/// A macro waiting for the current frame to end before it starts
+	queued: Option<usize>,
+}
+
+impl FrameInput {
+	pub fn new(turbo: Turbo, macros: Vec<InputMacro>) -> Self {
+		Self {
+			turbo_rate: turbo.rate.max(1),
+			macros,
+			clock: FrameClock::default(),
+			held: 0,
+			turbo: 0,
+			turbo_frames: 0,
+			running: None,
+			queued: None,
+		}
+	}
+
+	/// Sets the buttons held, and the buttons to press and release every
+	/// [`Turbo::rate`] frames while held
+	pub fn set_held(&mut self, held: u8, turbo: u8) {
+		self.held = held;
+		self.turbo = turbo;
+	}
+
+	/// Starts playing the macro at `index` in the config from the start of
+	/// the next frame, or this one if none of it has run yet, stopping
+	/// whichever one was playing
+	pub fn start_macro(&mut self, index: usize) {
+		let Some(input_macro) = self.macros.get(index) else {
+			return;
+		};
+
+		log::info!("Playing macro {}", input_macro.name);
+		self.queued = Some(index);
+		if self.clock.at_frame_start() {
+			self.start_queued();
+		}
+	}
+
+	fn start_queued(&mut self) {
+		if let Some(index) = self.queued.take() {
+			self.running = Some(RunningMacro { index, step: 0, frames: 0 });
+			self.skip_finished_steps();
+		}
+	}
+
+	/// The buttons to hold for the current frame
+	pub fn buttons(&self) -> u8 {
+		let turbo = match (self.turbo_frames / self.turbo_rate) % 2 {
+			0 => self.turbo,
+			_ => 0,
+		};
+		let input_macro = self
+			.running
+			.as_ref()
+			.map(|running| self.macros[running.index].steps[running.step].mask())
+			.unwrap_or_default();
+
+		self.held | turbo | input_macro
+	}
+
+	/// Counts an M-cycle, moving turbo and the playing macro on when it ends
+	/// the frame
+	pub fn tick(&mut self, redraw: bool) {
+		if !self.clock.tick(redraw) {
+			return;
+		}
+
+		self.turbo_frames = match self.turbo {
+			0 => 0,
+			_ => self.turbo_frames.wrapping_add(1),
+		};
+
+		if let Some(running) = self.running.as_mut() {
+			running.frames += 1;
+			self.skip_finished_steps();
+		}
+		self.start_queued();
+	}
+
+	/// Moves the playing macro past every step it has held for long enough,
+	/// stopping it after the last one
+	fn skip_finished_steps(&mut self) {
+		let Some(running) = self.running.as_mut() else {
+			return;
+		};
+		let steps = &self.macros[running.index].steps;
+
+		while running.step < steps.len() && running.frames >= steps[running.step].frames {
+			running.frames = 0;
+			running.step += 1;
+		}
+
+		if running.step >= steps.len() {
+			self.running = None;
+		}
+	}
+}
+
+#[cfg(test)]
+fn run_frames(input: &mut FrameInput, frames: usize) -> Vec<u8> {
+	(0..frames)
+		.map(|_| {
+			let buttons = input.buttons();
+			input.tick(true);
+			buttons
+		})
+		.collect()
+}
+
+#[test]
+fn test_turbo() {
+	use meowgb_core::gameboy::joypad::Joypad;
+
+	let mut input = FrameInput::new(Turbo { rate: 2 }, Vec::new());
+	input.set_held(Joypad::UP, Joypad::A);
+	let (on, off) = (Joypad::UP | Joypad::A, Joypad::UP);
+	assert_eq!(run_frames(&mut input, 6), [on, on, off, off, on, on]);
+
+	// Letting go restarts the turbo pressed
+	input.set_held(0, 0);
+	run_frames(&mut input, 1);
+	input.set_held(0, Joypad::B);
+	assert_eq!(run_frames(&mut input, 3), [Joypad::B, Joypad::B, 0]);
+}
+
+#[test]
+fn test_macro() {
+	use meowgb_core::gameboy::joypad::Joypad;
+
+	use crate::config::{Button, MacroStep};
+
+	let input_macro = InputMacro {
+		name: String::from("jump right"),
+		key: winit::event::VirtualKeyCode::M,
+		steps: vec![
+			MacroStep { buttons: vec![Button::Right], frames: 2 },
+			MacroStep { buttons: vec![], frames: 0 },
+			MacroStep { buttons: vec![Button::Right, Button::A], frames: 1 },
+		],
+	};
+	let mut input = FrameInput::new(Turbo { rate: 1 }, vec![input_macro]);
+	input.set_held(Joypad::B, 0);
+	input.start_macro(0);
+
+	let right = Joypad::B | Joypad::RIGHT;
+	assert_eq!(run_frames(&mut input, 4), [right, right, right | Joypad::A, Joypad::B]);
+
+	// Unknown macros are ignored
+	input.start_macro(1);
+	assert_eq!(input.buttons(), Joypad::B);
+}
+
+#[test]
+fn test_macro_mid_frame() {
+	use meowgb_core::gameboy::joypad::Joypad;
+
+	use crate::config::{Button, MacroStep};
+
+	let input_macro = InputMacro {
+		name: String::from("press a"),
+		key: winit::event::VirtualKeyCode::M,
+		steps: vec![MacroStep { buttons: vec![Button::A], frames: 2 }],
+	};
+	let mut input = FrameInput::new(Turbo { rate: 1 }, vec![input_macro]);
+
+	// The buttons only change once the frame being run ends
+	input.tick(false);
+	input.start_macro(0);
+	assert_eq!(input.buttons(), 0);
+	input.tick(false);
+	assert_eq!(input.buttons(), 0);
+	input.tick(true);
+
+	assert_eq!(run_frames(&mut input, 3), [Joypad::A, Joypad::A, 0]);
+}
@@ -0,0 +1,482 @@
+//! A link cable between two instances over TCP. Whichever side's game clocks a
+//! transfer sends its byte and waits for the other side to answer with its
+//! own. A thread on each side answers as soon as its game is waiting for a
+//! transfer, or clocks one itself, so the clocking side's game is held until
+//! the other has caught up to the transfer
+//!
+//! The clocking side decides whether a transfer happened, confirming it if
+//! the answer arrived in time and cancelling it otherwise. The other side's
+//! game only shifts in the byte once the transfer is confirmed, so both games
+//! always agree
+
+use std::{
+	io::{Read, Write},
+	net::{SocketAddr, TcpListener, TcpStream},
+	path::PathBuf,
+	sync::{Arc, Condvar, Mutex},
+	time::{Duration, Instant},
+};
+
+use meowgb_core::gameboy::{
+	printer::Printer,
+	serial::{LinkDevice, DISCONNECTED},
+};
+
+/// Sent by both sides on connecting, followed by [`PROTOCOL_VERSION`]
+const MAGIC: &[u8; 8] = b"MEOWLINK";
+const PROTOCOL_VERSION: u8 = 2;
+
+/// How long a side clocking a transfer waits for the other to answer before
+/// cancelling it and shifting in [`DISCONNECTED`], as a real cable would if
+/// the other game never got ready for it
+const TRANSFER_TIMEOUT: Duration = Duration::from_millis(250);
+
+#[derive(Debug, thiserror::Error)]
+pub enum LinkError {
+	#[error("Link cable IO error: {0}")]
+	Io(#[from] std::io::Error),
+	#[error("Link cable peer is not a compatible emulator")]
+	Handshake,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Message {
+	/// The sender clocked transfer `seq`, shifting out `byte`
+	Clock { seq: u16, byte: u8 },
+	/// The sender answered transfer `seq`, shifting out `byte`
+	Reply { seq: u16, byte: u8 },
+	/// The answer to transfer `seq` arrived in time, so it happened
+	Confirm { seq: u16 },
+	/// The sender gave up waiting for transfer `seq` to be answered, so it
+	/// never happened
+	Cancel { seq: u16 },
+}
+
+impl Message {
+	fn encode(self) -> [u8; 4] {
+		let (kind, seq, byte) = match self {
+			Self::Clock { seq, byte } => (0, seq, byte),
+			Self::Reply { seq, byte } => (1, seq, byte),
+			Self::Cancel { seq } => (2, seq, 0),
+			Self::Confirm { seq } => (3, seq, 0),
+		};
+		let [seq_low, seq_high] = seq.to_le_bytes();
+		[kind, seq_low, seq_high, byte]
+	}
+
+	fn decode([kind, seq_low, seq_high, byte]: [u8; 4]) -> Option<Self> {
+		let seq = u16::from_le_bytes([seq_low, seq_high]);
+		match kind {
+			0 => Some(Self::Clock { seq, byte }),
+			1 => Some(Self::Reply { seq, byte }),
+			2 => Some(Self::Cancel { seq }),
+			3 => Some(Self::Confirm { seq }),
+			_ => None,
+		}
+	}
+}
+
+/// Everything about the link both the emulator and the thread reading the
+/// peer's messages touch. Messages are only sent with it locked, so what each
+/// side sends always matches the state it was in
+struct LinkState {
+	stream: TcpStream,
+	connected: bool,
+	/// The transfer the game is clocking and the byte it shifts out, until
+	/// the peer answers or it times out
+	clocking: Option<(u16, u8)>,
+	/// The peer's answer to the transfer the game clocked
+	answer: Option<u8>,
+	/// The byte the game shifts out when the peer clocks a transfer, while it
+	/// waits for one
+	waiting: Option<u8>,
+	/// A transfer the peer clocked before the game was ready for one, answered
+	/// once it is unless the peer cancels it first
+	pending: Option<(u16, u8)>,
+	/// A transfer the peer clocked which was answered for the game, waiting
+	/// for the peer to confirm it
+	accepted: Option<(u16, u8)>,
+	/// The byte a confirmed transfer shifted in, until the game takes it
+	received: Option<u8>,
+}
+
+impl LinkState {
+	fn send(&mut self, message: Message) {
+		if self.connected && self.stream.write_all(&message.encode()).is_err() {
+			self.disconnect();
+		}
+	}
+
+	fn disconnect(&mut self) {
+		if self.connected {
+			log::warn!("Link cable disconnected");
+			self.connected = false;
+			self.clocking = None;
+			self.pending = None;
+			self.accepted = None;
+		}
+	}
+
+	/// Answers transfer `seq` with the byte the waiting game shifts out
+	fn accept(&mut self, seq: u16, byte: u8, waiting: u8) {
+		self.accepted = Some((seq, byte));
+		self.send(Message::Reply { seq, byte: waiting });
+	}
+
+	fn handle(&mut self, message: Message) {
+		match message {
+			Message::Clock { seq, byte } => match (self.clocking, self.waiting) {
+				// Both sides clocked at once, so each shifts in the other's
+				// byte from the answer to its own transfer
+				(Some((_, clocking)), _) => self.send(Message::Reply { seq, byte: clocking }),
+				(None, Some(waiting)) if self.received.is_none() => self.accept(seq, byte, waiting),
+				_ => self.pending = Some((seq, byte)),
+			},
+			Message::Reply { seq, byte } => {
+				// Replies to transfers which have already been cancelled are
+				// dropped
+				if self.clocking.map(|(clocked, _)| clocked) == Some(seq) {
+					self.clocking = None;
+					self.answer = Some(byte);
+					self.send(Message::Confirm { seq });
+				}
+			}
+			Message::Confirm { seq } => {
+				if let Some((_, byte)) = self.accepted.filter(|&(accepted, _)| accepted == seq) {
+					self.accepted = None;
+					// Dropped if the game stopped waiting while it was confirmed
+					if self.waiting.take().is_some() {
+						self.received = Some(byte);
+					}
+				}
+			}
+			Message::Cancel { seq } => {
+				if self.accepted.map(|(accepted, _)| accepted) == Some(seq) {
+					self.accepted = None;
+				}
+				if self.pending.map(|(pending, _)| pending) == Some(seq) {
+					self.pending = None;
+				}
+			}
+		}
+	}
+}
+
+struct Shared {
+	state: Mutex<LinkState>,
+	/// Notified whenever a message from the peer has been handled
+	handled: Condvar,
+}
+
+pub struct TcpLink {
+	shared: Arc<Shared>,
+	next_seq: u16,
+}
+
+impl TcpLink {
+	/// Waits for another instance to connect on `address`
+	pub fn listen(address: SocketAddr) -> Result<Self, LinkError> {
+		log::info!("Waiting for a link cable connection on {}", address);
+		let (stream, peer) = TcpListener::bind(address)?.accept()?;
+		log::info!("Link cable connected to {}", peer);
+		Self::new(stream)
+	}
+
+	pub fn connect(address: SocketAddr) -> Result<Self, LinkError> {
+		let stream = TcpStream::connect(address)?;
+		log::info!("Link cable connected to {}", address);
+		Self::new(stream)
+	}
+
+	fn new(mut stream: TcpStream) -> Result<Self, LinkError> {
+		stream.set_nodelay(true)?;
+
+		stream.write_all(MAGIC)?;
+		stream.write_all(&[PROTOCOL_VERSION])?;
+		let mut hello = [0u8; 9];
+		stream.read_exact(&mut hello)?;
+		if &hello[..8] != MAGIC || hello[8] != PROTOCOL_VERSION {
+			return Err(LinkError::Handshake);
+		}
+
+		let mut reader = stream.try_clone()?;
+		let shared = Arc::new(Shared {
+			state: Mutex::new(LinkState {
+				stream,
+				connected: true,
+				clocking: None,
+				answer: None,
+				waiting: None,
+				pending: None,
+				accepted: None,
+				received: None,
+			}),
+			handled: Condvar::new(),
+		});
+
+		let reader_shared = Arc::clone(&shared);
+		std::thread::Builder::new().name(String::from("link-cable")).spawn(move || {
+			let mut buffer = [0u8; 4];
+			while reader.read_exact(&mut buffer).is_ok() {
+				let Some(message) = Message::decode(buffer) else {
+					break;
+				};
+				reader_shared.state.lock().unwrap().handle(message);
+				reader_shared.handled.notify_all();
+			}
+
+			reader_shared.state.lock().unwrap().disconnect();
+			reader_shared.handled.notify_all();
+		})?;
+
+		Ok(Self { shared, next_seq: 0 })
+	}
+}
+
+impl Drop for TcpLink {
+	fn drop(&mut self) {
+		if let Ok(mut state) = self.shared.state.lock() {
+			state.connected = false;
+			// Also stops the reader thread, which holds a clone of the stream
+			let _ = state.stream.shutdown(std::net::Shutdown::Both);
+		}
+	}
+}
+
+impl LinkDevice for TcpLink {
+	fn exchange(&mut self, byte: u8) -> u8 {
+		let mut state = self.shared.state.lock().unwrap();
+		// The game clocking a transfer itself means it stopped waiting for
+		// the peer to
+		state.waiting = None;
+		state.accepted = None;
+		state.received = None;
+
+		if !state.connected {
+			return DISCONNECTED;
+		}
+
+		// The peer clocked first, so the game answers its transfer rather
+		// than clocking another, shifting in the byte once it is confirmed
+		if let Some((seq, clocked)) = state.pending.take() {
+			state.waiting = Some(byte);
+			state.accept(seq, clocked, byte);
+
+			let deadline = Instant::now() + TRANSFER_TIMEOUT;
+			while state.accepted.is_some() {
+				let timeout = deadline.saturating_duration_since(Instant::now());
+				if timeout.is_zero() {
+					state.accepted = None;
+					break;
+				}
+				state = self.shared.handled.wait_timeout(state, timeout).unwrap().0;
+			}
+
+			state.waiting = None;
+			// Nothing if the peer cancelled or disconnected
+			return state.received.take().unwrap_or(DISCONNECTED);
+		}
+
+		let seq = self.next_seq;
+		self.next_seq = self.next_seq.wrapping_add(1);
+		state.clocking = Some((seq, byte));
+		state.answer = None;
+		state.send(Message::Clock { seq, byte });
+
+		let deadline = Instant::now() + TRANSFER_TIMEOUT;
+		while state.clocking.is_some() {
+			let timeout = deadline.saturating_duration_since(Instant::now());
+			if timeout.is_zero() {
+				state.clocking = None;
+				state.send(Message::Cancel { seq });
+				return DISCONNECTED;
+			}
+			state = self.shared.handled.wait_timeout(state, timeout).unwrap().0;
+		}
+
+		// No answer if the peer disconnected
+		state.answer.take().unwrap_or(DISCONNECTED)
+	}
+
+	fn poll_external(&mut self, byte: u8) -> Option<u8> {
+		let mut state = self.shared.state.lock().unwrap();
+		let received = state.received.take();
+		if received.is_none() {
+			state.waiting = Some(byte);
+			if let Some((seq, clocked)) = state.pending.take() {
+				state.accept(seq, clocked, byte);
+			}
+		}
+		received
+	}
+
+	fn stop_polling(&mut self) {
+		let mut state = self.shared.state.lock().unwrap();
+		state.waiting = None;
+		state.received = None;
+	}
+}
+
+/// What the frontend plugs into the serial port
+pub enum SerialPort {
+	/// Prints outgoing bytes, with nothing connected
+	Stdout(std::io::Stdout),
+	Link(TcpLink),
+	/// A printer saving everything it prints to `dir`
+	Printer {
+		printer: Printer,
+		dir: PathBuf,
+		pages: usize,
+	},
+}
+
+impl SerialPort {
+	pub fn printer(dir: PathBuf) -> Result<Self, LinkError> {
+		std::fs::create_dir_all(&dir)?;
+		Ok(Self::Printer { printer: Printer::new(), dir, pages: 0 })
+	}
+}
+
+impl LinkDevice for SerialPort {
+	fn exchange(&mut self, byte: u8) -> u8 {
+		match self {
+			Self::Stdout(stdout) => stdout.exchange(byte),
+			Self::Link(link) => link.exchange(byte),
+			Self::Printer { printer, dir, pages } => {
+				let reply = printer.exchange(byte);
+
+				for strip in printer.take_printed() {
+					*pages += 1;
+					let path = dir.join(format!("print-{:04}.png", pages));
+					match strip.write_png(&path) {
+						Ok(()) => log::info!("Printed {}", path.display()),
+						Err(why) => log::error!("Failed to save {}: {}", path.display(), why),
+					}
+				}
+
+				reply
+			}
+		}
+	}
+
+	fn poll_external(&mut self, byte: u8) -> Option<u8> {
+		match self {
+			Self::Stdout(stdout) => stdout.poll_external(byte),
+			Self::Link(link) => link.poll_external(byte),
+			Self::Printer { printer, .. } => printer.poll_external(byte),
+		}
+	}
+
+	fn stop_polling(&mut self) {
+		match self {
+			Self::Stdout(stdout) => stdout.stop_polling(),
+			Self::Link(link) => link.stop_polling(),
+			Self::Printer { printer, .. } => printer.stop_polling(),
+		}
+	}
+}
+
+/// Two links connected to each other over localhost
+#[cfg(test)]
+fn loopback() -> (TcpLink, TcpLink) {
+	let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+	let address = listener.local_addr().unwrap();
+	let right = std::thread::spawn(move || TcpLink::new(listener.accept().unwrap().0).unwrap());
+	let left = TcpLink::connect(address).unwrap();
+	(left, right.join().unwrap())
+}
+
+/// Polls `link` like a game waiting for the peer to clock a transfer, until
+/// it shifts a byte in or a second passes
+#[cfg(test)]
+fn poll_for(link: &mut TcpLink, byte: u8) -> Option<u8> {
+	let deadline = Instant::now() + Duration::from_secs(1);
+	while Instant::now() < deadline {
+		if let Some(received) = link.poll_external(byte) {
+			return Some(received);
+		}
+		std::thread::sleep(Duration::from_millis(1));
+	}
+	None
+}
+
+#[test]
+fn test_tcp_link_transfer() {
+	let (mut left, mut right) = loopback();
+
+	assert_eq!(right.poll_external(0x22), None);
+	assert_eq!(left.exchange(0x11), 0x22);
+	assert_eq!(poll_for(&mut right, 0x22), Some(0x11));
+}
+
+#[test]
+fn test_tcp_link_late_arming() {
+	let (mut left, mut right) = loopback();
+
+	// The clocking side waits for the peer's game to start waiting
+	let handle = std::thread::spawn(move || left.exchange(0x11));
+	std::thread::sleep(Duration::from_millis(50));
+	assert_eq!(poll_for(&mut right, 0x22), Some(0x11));
+	assert_eq!(handle.join().unwrap(), 0x22);
+}
+
+#[test]
+fn test_tcp_link_stop_polling() {
+	let (mut left, mut right) = loopback();
+
+	// A game which stopped waiting never answers, so the transfer times out
+	assert_eq!(right.poll_external(0x22), None);
+	right.stop_polling();
+	let instant = Instant::now();
+	assert_eq!(left.exchange(0x11), DISCONNECTED);
+	assert!(instant.elapsed() >= TRANSFER_TIMEOUT);
+
+	// Nor is the cancelled transfer answered once it waits again
+	std::thread::sleep(Duration::from_millis(50));
+	assert_eq!(right.poll_external(0x22), None);
+	assert_eq!(left.exchange(0x33), 0x22);
+	assert_eq!(poll_for(&mut right, 0x22), Some(0x33));
+}
+
+#[test]
+fn test_tcp_link_simultaneous() {
+	let (mut left, mut right) = loopback();
+	let barrier = Arc::new(std::sync::Barrier::new(2));
+
+	// However the two transfers cross, each side shifts in the other's byte
+	for _ in 0..20 {
+		let right_barrier = Arc::clone(&barrier);
+		let handle = std::thread::spawn(move || {
+			right_barrier.wait();
+			let received = right.exchange(0x22);
+			(right, received)
+		});
+		barrier.wait();
+		let left_received = left.exchange(0x11);
+		let right_received;
+		(right, right_received) = handle.join().unwrap();
+
+		assert_eq!((left_received, right_received), (0x22, 0x11));
+	}
+}
+
+#[test]
+fn test_tcp_link_timeout() {
+	let (mut left, mut right) = loopback();
+	assert_eq!(right.poll_external(0x22), None);
+
+	// Holding the lock keeps the peer from answering in time
+	let guard = right.shared.state.lock().unwrap();
+	let instant = Instant::now();
+	assert_eq!(left.exchange(0x11), DISCONNECTED);
+	assert!(instant.elapsed() >= TRANSFER_TIMEOUT);
+	drop(guard);
+
+	// The answer the peer then sends is not delivered, and neither is the
+	// cancelled transfer
+	std::thread::sleep(Duration::from_millis(50));
+	assert_eq!(right.poll_external(0x22), None);
+
+	assert_eq!(left.exchange(0x33), 0x22);
+	assert_eq!(poll_for(&mut right, 0x22), Some(0x33));
+}
@@ -1,8 +1,13 @@
 mod config;
+#[cfg_attr(not(feature = "gamepad"), allow(dead_code))]
+mod gamepad;
+mod input;
+mod link;
 mod window;
 
 use std::{
 	borrow::Cow,
+	net::SocketAddr,
 	path::PathBuf,
 	sync::{
 		mpsc::{channel, Receiver, Sender},
@@ -12,7 +17,17 @@ use std::{
 
 use clap::Parser;
 use config::MeowGBConfig;
-use meowgb_core::gameboy::{serial::SerialWriter, Gameboy};
+use input::FrameInput;
+use link::{SerialPort, TcpLink};
+use meowgb_core::{
+	emulator::Emulator,
+	gameboy::{
+		movie::{Movie, MovieError, MoviePlayer, MovieRecorder},
+		serial::LinkDevice,
+		state::StateError,
+	},
+	symbols::SymbolTable,
+};
 use window::events::{EmulatorDebugEvent, EmulatorWindowEvent, GameboyEvent};
 
 #[cfg(feature = "debugger")]
@@ -25,6 +40,28 @@ pub struct CliArgs {
 	/// start the emulator in debug mode
 	#[clap(short, long)]
 	pub debug: bool,
+	/// RGBDS symbol file, defaults to the `.sym` file next to the game
+	#[clap(long)]
+	pub symbols: Option<PathBuf>,
+	/// wait for another instance to connect a link cable on this address
+	#[clap(long, conflicts_with = "link_connect")]
+	pub link_listen: Option<SocketAddr>,
+	/// connect a link cable to another instance listening on this address
+	#[clap(long)]
+	pub link_connect: Option<SocketAddr>,
+	/// plug a Game Boy Printer into the link port, saving what it prints to
+	/// this directory
+	#[clap(long, conflicts_with_all = ["link_listen", "link_connect"])]
+	pub printer: Option<PathBuf>,
+	/// load this save state before starting
+	#[clap(long, conflicts_with = "play_movie")]
+	pub load_state: Option<PathBuf>,
+	/// record the input to this movie, which is written on exit
+	#[clap(long, conflicts_with = "play_movie")]
+	pub record_movie: Option<PathBuf>,
+	/// play this movie back, then carry on with the keyboard
+	#[clap(long)]
+	pub play_movie: Option<PathBuf>,
 }
 
 #[cfg(not(feature = "debugger"))]
@@ -34,6 +71,25 @@ pub struct CliArgs {
 	/// game path
 	#[clap(long)]
 	pub rom: Option<PathBuf>,
+	/// wait for another instance to connect a link cable on this address
+	#[clap(long, conflicts_with = "link_connect")]
+	pub link_listen: Option<SocketAddr>,
+	/// connect a link cable to another instance listening on this address
+	#[clap(long)]
+	pub link_connect: Option<SocketAddr>,
+	/// plug a Game Boy Printer into the link port, saving what it prints to
+	/// this directory
+	#[clap(long, conflicts_with_all = ["link_listen", "link_connect"])]
+	pub printer: Option<PathBuf>,
+	/// load this save state before starting
+	#[clap(long, conflicts_with = "play_movie")]
+	pub load_state: Option<PathBuf>,
+	/// record the input to this movie, which is written on exit
+	#[clap(long, conflicts_with = "play_movie")]
+	pub record_movie: Option<PathBuf>,
+	/// play this movie back, then carry on with the keyboard
+	#[clap(long)]
+	pub play_movie: Option<PathBuf>,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -44,6 +100,14 @@ pub enum MeowGBError {
 	IO(#[from] std::io::Error),
 	#[error(transparent)]
 	Config(#[from] config::ConfigError),
+	#[error(transparent)]
+	Symbols(#[from] meowgb_core::symbols::SymbolError),
+	#[error(transparent)]
+	Link(#[from] link::LinkError),
+	#[error("Error loading save state: {0}")]
+	State(#[from] StateError),
+	#[error(transparent)]
+	Movie(#[from] MovieError),
 }
 
 fn real_main() -> Result<(), MeowGBError> {
@@ -70,18 +134,54 @@ fn real_main() -> Result<(), MeowGBError> {
 		None => None,
 	};
 
-	let mut gameboy = WrappedGameboy::new(Gameboy::new(std::io::stdout(), rom));
+	let serial = match (args.link_listen, args.link_connect, args.printer) {
+		(Some(address), ..) => SerialPort::Link(TcpLink::listen(address)?),
+		(_, Some(address), _) => SerialPort::Link(TcpLink::connect(address)?),
+		(.., Some(dir)) => SerialPort::printer(dir)?,
+		(None, None, None) => SerialPort::Stdout(std::io::stdout()),
+	};
+
+	let mut gameboy = WrappedGameboy::new(Emulator::new(serial, rom));
+	gameboy.state_path = args.rom.as_ref().map(|rom| rom.with_extension("state"));
+	if let Some(path) = args.load_state.as_deref() {
+		gameboy.emulator.load_state(&std::fs::read(path)?)?;
+	}
+	gameboy.movie = match (args.record_movie, args.play_movie.as_deref()) {
+		(Some(path), _) => {
+			let recorder = MovieRecorder::new(gameboy.emulator.gameboy());
+			Some(MovieMode::Recording { recorder, path })
+		}
+		(_, Some(path)) => {
+			let player = gameboy.emulator.play_movie(Movie::read(path)?)?;
+			log::info!("Playing {} frames from {}", player.frames(), path.display());
+			Some(MovieMode::Playing(player))
+		}
+		(None, None) => None,
+	};
 	#[cfg(feature = "debugger")]
 	let dbg = args.debug;
 	#[cfg(not(feature = "debugger"))]
 	let dbg = false;
 	gameboy.debugging = dbg;
+	#[cfg(feature = "debugger")]
+	{
+		let sidecar = args.rom.as_ref().map(|rom| rom.with_extension("sym"));
+		match args.symbols.as_deref() {
+			Some(path) => gameboy.symbols = SymbolTable::from_file(path)?,
+			None => {
+				if let Some(path) = sidecar.filter(|path| path.is_file()) {
+					gameboy.symbols = SymbolTable::from_file(&path)?;
+				}
+			}
+		}
+	}
 	let gameboy = Arc::new(RwLock::new(gameboy));
 	let gameboy_2 = gameboy.clone();
+	let input = FrameInput::new(config.turbo, config.macros.clone());
 
 	let jh = std::thread::Builder::new()
 		.name(String::from("mewmulator"))
-		.spawn(move || run_gameboy(gameboy_2, gb_side_rx, gb_side_tx).unwrap())
+		.spawn(move || run_gameboy(gameboy_2, input, gb_side_rx, gb_side_tx).unwrap())
 		.unwrap();
 
 	window::run_window(
@@ -104,26 +204,158 @@ fn main() {
 	}
 }
 
-pub struct WrappedGameboy<W: SerialWriter> {
+pub enum MovieMode {
+	/// Written to `path` on exit
+	Recording {
+		recorder: MovieRecorder,
+		path: PathBuf,
+	},
+	Playing(MoviePlayer),
+}
+
+pub struct WrappedGameboy<W: LinkDevice> {
 	pub breakpoints: [[bool; 3]; 0x10000],
+	/// The bank each breakpoint is for, `None` breaks on whichever is mapped in
+	pub breakpoint_banks: [Option<u16>; 0x10000],
 	pub debugging: bool,
-	pub gameboy: Gameboy<W>,
+	pub symbols: SymbolTable,
+	pub emulator: Emulator<W>,
+	/// Where the save state hotkeys save to and load from, next to the game
+	pub state_path: Option<PathBuf>,
+	pub movie: Option<MovieMode>,
 }
 
-impl<W: SerialWriter> WrappedGameboy<W> {
-	pub fn new(gameboy: Gameboy<W>) -> Self {
-		Self { breakpoints: [[false; 3]; 0x10000], debugging: false, gameboy }
+impl<W: LinkDevice> WrappedGameboy<W> {
+	pub fn new(emulator: Emulator<W>) -> Self {
+		Self {
+			breakpoints: [[false; 3]; 0x10000],
+			breakpoint_banks: [None; 0x10000],
+			debugging: false,
+			symbols: SymbolTable::new(),
+			emulator,
+			state_path: None,
+			movie: None,
+		}
+	}
+
+	/// Whether the read (0), write (1) or execute (2) breakpoint at `address`
+	/// is enabled, and is for the bank currently mapped in there
+	fn breaks_at(&self, address: u16, kind: usize) -> bool {
+		self.breakpoints[address as usize][kind]
+			&& match (self.breakpoint_banks[address as usize], address) {
+				(Some(bank), 0x0000..=0x7FFF | 0xA000..=0xBFFF) => {
+					self.emulator.gameboy().bank_at(address) == bank
+				}
+				_ => true,
+			}
+	}
+
+	/// Whether keyboard input can be applied to the joypad right now. A movie
+	/// being recorded only takes input at the start of a frame, and one being
+	/// played back takes none at all
+	fn takes_input(&self) -> bool {
+		match &self.movie {
+			Some(MovieMode::Recording { recorder, .. }) => recorder.at_frame_start(),
+			Some(MovieMode::Playing(_)) => false,
+			None => true,
+		}
+	}
+
+	/// Ticks the emulator for an M-cycle, through the movie being recorded or
+	/// played back if there is one
+	fn tick(&mut self) -> bool {
+		let player = match &mut self.movie {
+			Some(MovieMode::Recording { recorder, .. }) => {
+				return self.emulator.tick_recording(recorder)
+			}
+			Some(MovieMode::Playing(player)) => player,
+			None => return self.emulator.tick(),
+		};
+
+		match self.emulator.tick_playing(player) {
+			Ok(redraw) => {
+				if player.finished() {
+					log::info!("Movie finished after {} frames", player.frames());
+					self.movie = None;
+				}
+				redraw
+			}
+			Err(why) => {
+				log::error!("{}, stopping playback", why);
+				self.movie = None;
+				// Desyncs are only found at the end of a frame
+				true
+			}
+		}
+	}
+
+	fn save_state(&self) {
+		let Some(path) = self.state_path.as_deref() else {
+			log::warn!("No game to save the state of");
+			return;
+		};
+
+		match std::fs::write(path, self.emulator.save_state()) {
+			Ok(()) => log::info!("Saved state to {}", path.display()),
+			Err(why) => log::error!("Failed to save state to {}: {}", path.display(), why),
+		}
+	}
+
+	fn load_state(&mut self) {
+		let Some(path) = self.state_path.as_deref() else {
+			log::warn!("No game to load the state of");
+			return;
+		};
+
+		if self.movie.is_some() {
+			log::warn!("States cannot be loaded while a movie is recording or playing");
+			return;
+		}
+
+		let result = std::fs::read(path)
+			.map_err(MeowGBError::from)
+			.and_then(|state| Ok(self.emulator.load_state(&state)?));
+		match result {
+			Ok(()) => log::info!("Loaded state from {}", path.display()),
+			Err(why) => log::error!("Failed to load state from {}: {}", path.display(), why),
+		}
+	}
+
+	fn reset(&mut self) {
+		if self.movie.is_some() {
+			log::warn!("The console cannot be reset while a movie is recording or playing");
+			return;
+		}
+
+		self.emulator.reset();
+		log::info!("Reset the console");
+	}
+
+	/// Writes the movie being recorded, if there is one
+	fn finish_movie(&mut self) {
+		let Some(MovieMode::Recording { recorder, path }) = self.movie.take() else {
+			return;
+		};
+
+		let movie = recorder.finish();
+		match movie.write(&path) {
+			Ok(()) => log::info!("Recorded {} frames to {}", movie.frames.len(), path.display()),
+			Err(why) => log::error!("Failed to write movie to {}: {}", path.display(), why),
+		}
 	}
 }
 
 pub fn run_gameboy(
-	gameboy_arc: Arc<RwLock<WrappedGameboy<impl SerialWriter>>>,
+	gameboy_arc: Arc<RwLock<WrappedGameboy<impl LinkDevice>>>,
+	mut input: FrameInput,
 	rx: Receiver<EmulatorWindowEvent>,
 	tx: Sender<GameboyEvent>,
 ) -> Result<(), MeowGBError> {
 	let mut goal = time::OffsetDateTime::now_utc() + time::Duration::milliseconds(1000 / 60);
 	let mut frame_counter = 0;
 	let mut debugging_tbf = None;
+	let mut paused = false;
+	let mut fast_forward = false;
 
 	'outer: loop {
 		let mut step = false;
@@ -131,17 +363,31 @@ pub fn run_gameboy(
 		let mut gameboy = gameboy_arc.write().unwrap();
 		while let Ok(event) = rx.try_recv() {
 			match event {
-				EmulatorWindowEvent::AToggle => gameboy.gameboy.joypad.invert_a(),
-				EmulatorWindowEvent::BToggle => gameboy.gameboy.joypad.invert_b(),
-				EmulatorWindowEvent::SelectToggle => gameboy.gameboy.joypad.invert_select(),
-				EmulatorWindowEvent::StartToggle => gameboy.gameboy.joypad.invert_start(),
-				EmulatorWindowEvent::UpToggle => gameboy.gameboy.joypad.invert_up(),
-				EmulatorWindowEvent::DownToggle => gameboy.gameboy.joypad.invert_down(),
-				EmulatorWindowEvent::LeftToggle => gameboy.gameboy.joypad.invert_left(),
-				EmulatorWindowEvent::RightToggle => gameboy.gameboy.joypad.invert_right(),
-				EmulatorWindowEvent::Exit => break 'outer,
-				EmulatorWindowEvent::Debug(EmulatorDebugEvent::ToggleBreakpoint(addr, breaks)) => {
+				EmulatorWindowEvent::Buttons { held, turbo } => input.set_held(held, turbo),
+				EmulatorWindowEvent::Macro(index) => input.start_macro(index),
+				EmulatorWindowEvent::PauseToggle => {
+					paused = !paused;
+					goal =
+						time::OffsetDateTime::now_utc() + time::Duration::milliseconds(1000 / 60);
+				}
+				EmulatorWindowEvent::FastForward(held) => fast_forward = held,
+				EmulatorWindowEvent::SaveState => gameboy.save_state(),
+				EmulatorWindowEvent::LoadState => gameboy.load_state(),
+				EmulatorWindowEvent::Reset => gameboy.reset(),
+				EmulatorWindowEvent::Exit => {
+					gameboy.finish_movie();
+					break 'outer;
+				}
+				EmulatorWindowEvent::Debug(EmulatorDebugEvent::ToggleBreakpoint(
+					addr,
+					bank,
+					breaks,
+				)) => {
 					gameboy.breakpoints[addr as usize] = breaks;
+					gameboy.breakpoint_banks[addr as usize] = bank;
+				}
+				EmulatorWindowEvent::Debug(EmulatorDebugEvent::WriteMemory(addr, value)) => {
+					gameboy.emulator.gameboy_mut().debug_write_u8(addr, value);
 				}
 				EmulatorWindowEvent::Debug(EmulatorDebugEvent::Continue) => {
 					gameboy.debugging = false;
@@ -156,19 +402,36 @@ pub fn run_gameboy(
 			}
 		}
 
+		if paused && !gameboy.debugging {
+			drop(gameboy);
+			std::thread::sleep(std::time::Duration::from_millis(1000 / 60));
+			continue;
+		}
+
+		if gameboy.takes_input() {
+			gameboy.emulator.set_buttons(input.buttons());
+		}
+
 		if !gameboy.debugging || step {
-			let needs_redraw = gameboy.gameboy.tick_4();
-			let bp_triggered = gameboy
-				.gameboy
+			let needs_redraw = match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+				gameboy.tick()
+			})) {
+				Ok(needs_redraw) => needs_redraw,
+				Err(why) => {
+					eprintln!("Emulator crashed\n{}", gameboy.emulator.gameboy().crash_report());
+					// Keeps the input leading up to the crash, to reproduce it
+					gameboy.finish_movie();
+					std::panic::resume_unwind(why);
+				}
+			};
+			input.tick(needs_redraw);
+			let cpu = gameboy.emulator.gameboy();
+			let bp_triggered = cpu
 				.last_read
-				.map(|(addr, _)| gameboy.breakpoints[addr as usize][0])
+				.map(|(addr, _)| gameboy.breaks_at(addr, 0))
 				.unwrap_or_default()
-				|| gameboy
-					.gameboy
-					.last_write
-					.map(|(addr, _)| gameboy.breakpoints[addr as usize][1])
-					.unwrap_or_default()
-				|| gameboy.breakpoints[gameboy.gameboy.registers.pc as usize][2];
+				|| cpu.last_write.map(|(addr, _)| gameboy.breaks_at(addr, 1)).unwrap_or_default()
+				|| gameboy.breaks_at(cpu.registers.pc, 2);
 			gameboy.debugging |= bp_triggered;
 
 			if bp_triggered || step {
@@ -186,9 +449,12 @@ pub fn run_gameboy(
 
 			if needs_redraw {
 				let now = time::OffsetDateTime::now_utc();
+				if fast_forward {
+					goal = now;
+				}
 				frame_counter += 1;
 				tx.send(GameboyEvent::Framebuffer(
-					gameboy_arc.read().unwrap().gameboy.ppu.write_fb(),
+					gameboy_arc.read().unwrap().emulator.framebuffer().to_vec(),
 				))
 				.unwrap();
 				let delta = goal - now;
@@ -7,8 +7,8 @@ use std::sync::{
 	Arc, RwLock,
 };
 
-use events::{EmulatorWindowEvent, GameboyEvent, Keymap};
-use meowgb_core::gameboy::serial::SerialWriter;
+use events::{EmulatorWindowEvent, GameboyEvent};
+use meowgb_core::gameboy::{joypad::Joypad, serial::LinkDevice};
 #[cfg(feature = "debugger")]
 use overlay::Framework;
 use pixels::{Pixels, SurfaceTexture};
@@ -19,30 +19,37 @@ use winit::{
 };
 use winit_input_helper::WinitInputHelper;
 
-use crate::{config::MeowGBConfig, WrappedGameboy};
-
-macro_rules! define_keypress {
-	($input:ident, $config:ident, $keymap:ident, $tx:ident, $key:ident, $event:ident) => {
-		if $input.key_pressed($config.bindings.$key)
-			&& !*$keymap.idx(&$config, $config.bindings.$key)
-		{
-			$tx.send(EmulatorWindowEvent::$event).unwrap();
-			*$keymap.idx(&$config, $config.bindings.$key) = true;
-		}
+#[cfg(feature = "gamepad")]
+use crate::gamepad::Gamepads;
+use crate::{
+	config::MeowGBConfig,
+	gamepad::{GamepadMapper, Hotkey},
+	WrappedGameboy,
+};
 
-		if $input.key_released($config.bindings.$key)
-			&& *$keymap.idx(&$config, $config.bindings.$key)
-		{
-			$tx.send(EmulatorWindowEvent::$event).unwrap();
-			*$keymap.idx(&$config, $config.bindings.$key) = false;
-		}
-	};
+/// The buttons whose keys are held, the input helper forgetting every held
+/// key when the window loses focus
+fn held_buttons(input: &WinitInputHelper, config: &MeowGBConfig) -> u8 {
+	let bindings = &config.bindings;
+	[
+		(bindings.a, Joypad::A),
+		(bindings.b, Joypad::B),
+		(bindings.select, Joypad::SELECT),
+		(bindings.start, Joypad::START),
+		(bindings.right, Joypad::RIGHT),
+		(bindings.left, Joypad::LEFT),
+		(bindings.up, Joypad::UP),
+		(bindings.down, Joypad::DOWN),
+	]
+	.into_iter()
+	.filter(|(key, _)| input.key_held(*key))
+	.fold(0, |mask, (_, button)| mask | button)
 }
 
 pub fn run_window(
 	rom_name: &str,
 	config: MeowGBConfig,
-	gameboy: Arc<RwLock<WrappedGameboy<impl SerialWriter + 'static>>>,
+	gameboy: Arc<RwLock<WrappedGameboy<impl LinkDevice + 'static>>>,
 	rx: Receiver<GameboyEvent>,
 	tx: Sender<EmulatorWindowEvent>,
 ) {
@@ -81,7 +88,11 @@ pub fn run_window(
 	let mut redraw_happened = true;
 	let mut fb: Option<Vec<u8>> = None;
 
-	let mut keymap = Keymap::default();
+	#[cfg(feature = "gamepad")]
+	let mut gamepads = Gamepads::new();
+	#[cfg_attr(not(feature = "gamepad"), allow(unused_mut))]
+	let mut gamepad = GamepadMapper::new(config.gamepad);
+	let mut fast_forward = false;
 
 	event_loop.run(move |event, _, control_flow| {
 		if input.update(&event) {
@@ -91,9 +102,6 @@ pub fn run_window(
 				return;
 			}
 
-			// if input.key_pressed(config.bindings.pause) {
-			// 	tx.send(EmulatorWindowEvent::PauseToggle).unwrap();
-			// }
 			#[cfg(feature = "debugger")]
 			if let Some(debug_menu) = config.bindings.debug_menu {
 				if input.key_pressed(debug_menu) {
@@ -109,7 +117,42 @@ pub fn run_window(
 					redraw_happened = true;
 				}
 			}
-			if input.key_pressed(config.bindings.pause) {}
+
+			let mut hotkeys = Vec::new();
+			#[cfg(feature = "gamepad")]
+			if let Some(gamepads) = gamepads.as_mut() {
+				while let Some(event) = gamepads.next_event() {
+					hotkeys.extend(gamepad.handle(event));
+				}
+			}
+
+			for (key, hotkey) in [
+				(config.bindings.pause, Hotkey::Pause),
+				(config.bindings.save_state, Hotkey::SaveState),
+				(config.bindings.load_state, Hotkey::LoadState),
+				(config.bindings.reset, Hotkey::Reset),
+			] {
+				if input.key_pressed(key) {
+					hotkeys.push(hotkey);
+				}
+			}
+
+			for hotkey in hotkeys {
+				tx.send(match hotkey {
+					Hotkey::Pause => EmulatorWindowEvent::PauseToggle,
+					Hotkey::SaveState => EmulatorWindowEvent::SaveState,
+					Hotkey::LoadState => EmulatorWindowEvent::LoadState,
+					Hotkey::Reset => EmulatorWindowEvent::Reset,
+				})
+				.unwrap();
+			}
+
+			let fast_forward_held =
+				input.key_held(config.bindings.fast_forward) || gamepad.fast_forward();
+			if fast_forward_held != fast_forward {
+				fast_forward = fast_forward_held;
+				tx.send(EmulatorWindowEvent::FastForward(fast_forward)).unwrap();
+			}
 
 			#[cfg(feature = "debugger")]
 			if let Some(scale_factor) = input.scale_factor() {
@@ -121,14 +164,22 @@ pub fn run_window(
 				redraw_happened |= input.scale_factor().is_some();
 			}
 
-			define_keypress!(input, config, keymap, tx, a, AToggle);
-			define_keypress!(input, config, keymap, tx, b, BToggle);
-			define_keypress!(input, config, keymap, tx, start, StartToggle);
-			define_keypress!(input, config, keymap, tx, select, SelectToggle);
-			define_keypress!(input, config, keymap, tx, up, UpToggle);
-			define_keypress!(input, config, keymap, tx, down, DownToggle);
-			define_keypress!(input, config, keymap, tx, left, LeftToggle);
-			define_keypress!(input, config, keymap, tx, right, RightToggle);
+			for (index, input_macro) in config.macros.iter().enumerate() {
+				if input.key_pressed(input_macro.key) {
+					tx.send(EmulatorWindowEvent::Macro(index)).unwrap();
+				}
+			}
+
+			let turbo =
+				[(config.bindings.turbo_a, Joypad::A), (config.bindings.turbo_b, Joypad::B)]
+					.into_iter()
+					.filter(|(key, _)| input.key_held(*key))
+					.fold(gamepad.turbo(), |mask, (_, button)| mask | button);
+			tx.send(EmulatorWindowEvent::Buttons {
+				held: held_buttons(&input, &config) | gamepad.buttons(),
+				turbo,
+			})
+			.unwrap();
 		}
 
 		match event {
@@ -1,17 +1,22 @@
-use winit::event::VirtualKeyCode;
-
-use crate::config::MeowGBConfig;
-
 #[derive(Debug, Clone, Copy)]
 pub enum EmulatorWindowEvent {
-	AToggle,
-	BToggle,
-	SelectToggle,
-	StartToggle,
-	UpToggle,
-	DownToggle,
-	LeftToggle,
-	RightToggle,
+	/// Every button held, and every button whose turbo is held, as masks of
+	/// `Joypad::A` and friends. Sent whenever the window processes input
+	/// rather than only on changes
+	Buttons {
+		held: u8,
+		turbo: u8,
+	},
+	/// Plays the macro at this index in the config
+	Macro(usize),
+	PauseToggle,
+	/// Whether emulation should run as fast as it can rather than at 60 frames
+	/// a second
+	FastForward(bool),
+	SaveState,
+	LoadState,
+	/// Turns the console off and on again, keeping the game and its save
+	Reset,
 	Debug(EmulatorDebugEvent),
 	Exit,
 }
@@ -20,49 +25,13 @@ pub enum EmulatorWindowEvent {
 pub enum EmulatorDebugEvent {
 	Step,
 	Continue,
-	ToggleBreakpoint(u16, [bool; 3]),
+	/// Read, write and execute breakpoints at an address, only breaking while
+	/// the given bank is mapped in there if there is one
+	ToggleBreakpoint(u16, Option<u16>, [bool; 3]),
+	WriteMemory(u16, u8),
 }
 
 #[derive(Debug)]
 pub enum GameboyEvent {
 	Framebuffer(Vec<u8>),
 }
-
-#[derive(Debug, Default)]
-pub(super) struct Keymap {
-	pub down: bool,
-	pub up: bool,
-	pub left: bool,
-	pub right: bool,
-	pub start: bool,
-	pub select: bool,
-	pub b: bool,
-	pub a: bool,
-	pub pause: bool,
-}
-
-impl Keymap {
-	pub fn idx(&mut self, config: &MeowGBConfig, kc: VirtualKeyCode) -> &mut bool {
-		if kc == config.bindings.a {
-			&mut self.a
-		} else if kc == config.bindings.b {
-			&mut self.b
-		} else if kc == config.bindings.start {
-			&mut self.start
-		} else if kc == config.bindings.select {
-			&mut self.select
-		} else if kc == config.bindings.up {
-			&mut self.up
-		} else if kc == config.bindings.down {
-			&mut self.down
-		} else if kc == config.bindings.left {
-			&mut self.left
-		} else if kc == config.bindings.right {
-			&mut self.right
-		} else if kc == config.bindings.pause {
-			&mut self.pause
-		} else {
-			unreachable!();
-		}
-	}
-}
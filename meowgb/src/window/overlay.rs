@@ -1,8 +1,21 @@
 /// Provides an [egui] based overlay for debugigng the emulator whilst it is
 /// running
-use egui::{ClippedPrimitive, Color32, Context, Grid, RichText, TexturesDelta};
+use egui::{
+	ClippedPrimitive, Color32, ColorImage, Context, Grid, Rect, RichText, Stroke, TextureHandle,
+	TextureOptions, TexturesDelta, Vec2,
+};
 use egui_wgpu::renderer::{Renderer, ScreenDescriptor};
-use meowgb_core::gameboy::serial::SerialWriter;
+use meowgb_core::{
+	disassembler,
+	gameboy::{
+		call_stack::{CallFrame, InterruptRecord},
+		io,
+		ppu::{OAMEntry, Palette, TileMap, TILE_COUNT, TILE_MAP_SIZE, TILE_SHEET_WIDTH},
+		serial::LinkDevice,
+		Gameboy,
+	},
+	symbols::SymbolTable,
+};
 use pixels::{wgpu, PixelsContext};
 use winit::{event_loop::EventLoopWindowTarget, window::Window};
 
@@ -25,10 +38,13 @@ pub struct GuiWindowState {
 	pub register_window_open: bool,
 	pub ppu_register_window_open: bool,
 	pub debugger_window_open: bool,
-	pub wram_window_open: bool,
-	pub oam_window_open: bool,
-	pub hram_window_open: bool,
+	pub memory_window_open: bool,
 	pub dma_window_open: bool,
+	pub tiles_window_open: bool,
+	pub tile_maps_window_open: bool,
+	pub oam_window_open: bool,
+	pub call_stack_window_open: bool,
+	pub io_window_open: bool,
 }
 
 impl GuiWindowState {
@@ -37,10 +53,13 @@ impl GuiWindowState {
 		self.register_window_open = false;
 		self.window_open = false;
 		self.debugger_window_open = false;
-		self.wram_window_open = false;
-		self.oam_window_open = false;
-		self.hram_window_open = false;
+		self.memory_window_open = false;
 		self.dma_window_open = false;
+		self.tiles_window_open = false;
+		self.tile_maps_window_open = false;
+		self.oam_window_open = false;
+		self.call_stack_window_open = false;
+		self.io_window_open = false;
 	}
 
 	pub fn any_open(&self) -> bool {
@@ -48,10 +67,13 @@ impl GuiWindowState {
 			|| self.register_window_open
 			|| self.ppu_register_window_open
 			|| self.debugger_window_open
-			|| self.wram_window_open
-			|| self.oam_window_open
-			|| self.hram_window_open
+			|| self.memory_window_open
 			|| self.dma_window_open
+			|| self.tiles_window_open
+			|| self.tile_maps_window_open
+			|| self.oam_window_open
+			|| self.call_stack_window_open
+			|| self.io_window_open
 	}
 }
 
@@ -60,20 +82,107 @@ pub struct Gui {
 	pub state_restore: Option<GuiWindowState>,
 	pub registers: meowgb_core::gameboy::cpu::Registers,
 	pub ppu_registers: meowgb_core::gameboy::ppu::PpuRegisters,
-	pub wram: [u8; 0x2000],
-	pub hram: [u8; 0xAF],
-	// pub vram: [u8; 0x2000],
-	pub oam: [u8; 0xA0],
+	pub memory: Box<[u8; 0x10000]>,
+	pub memory_goto_string: String,
+	pub memory_edit_string: String,
+	pub memory_selected: Option<u16>,
+	pub memory_scroll_to: Option<u16>,
 	pub bp_string: String,
 	pub bp_read_checkbox: bool,
 	pub bp_write_checkbox: bool,
 	pub bp_execute_checkbox: bool,
 	pub is_debugging: bool,
 	pub breakpoints: [[bool; 3]; 0x10000],
+	pub breakpoint_banks: [Option<u16>; 0x10000],
 	pub sender: std::sync::mpsc::Sender<EmulatorWindowEvent>,
 	pub dma: meowgb_core::gameboy::dma::DmaState,
+	pub symbols: SymbolTable,
+	pub disassembly: Vec<DisassemblyLine>,
+	pub banks: MappedBanks,
+	pub tile_palette: TilePalette,
+	pub tiles: Vec<u8>,
+	pub tiles_texture: Option<TextureHandle>,
+	pub tile_maps: [Vec<u8>; 2],
+	pub tile_map_textures: [Option<TextureHandle>; 2],
+	pub bg_tile_map: TileMap,
+	pub window_tile_map: TileMap,
+	pub sprite_height: usize,
+	pub sprites: Vec<(OAMEntry, Vec<u8>)>,
+	pub sprite_textures: Vec<TextureHandle>,
+	pub call_stack: Vec<CallFrame>,
+	pub interrupt_history: Vec<InterruptRecord>,
+	pub io_registers: Vec<(u16, u8)>,
+	pub timer_internal_div: u16,
+	pub timer_overflow_state: u8,
+}
+
+/// The palette used to colour the tiles in the tile viewer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TilePalette {
+	Bgp,
+	Obp0,
+	Obp1,
+	Greyscale,
+}
+
+/// How much the tile and tile map viewers are scaled up by
+const TILE_VIEWER_SCALE: f32 = 2.0;
+/// How much the sprite previews in the OAM viewer are scaled up by
+const SPRITE_PREVIEW_SCALE: f32 = 4.0;
+
+/// The banks mapped in to each banked region of memory
+#[derive(Debug, Clone, Copy)]
+pub struct MappedBanks {
+	pub rom0: u16,
+	pub romx: u16,
+	pub eram: u16,
+}
+
+impl MappedBanks {
+	fn new(gameboy: &Gameboy<impl LinkDevice>) -> Self {
+		Self {
+			rom0: gameboy.bank_at(0x0000),
+			romx: gameboy.bank_at(0x4000),
+			eram: gameboy.bank_at(0xA000),
+		}
+	}
+
+	fn bank_at(&self, address: u16) -> u16 {
+		match address {
+			0x0000..=0x3FFF => self.rom0,
+			0x4000..=0x7FFF => self.romx,
+			0xA000..=0xBFFF => self.eram,
+			_ => 0,
+		}
+	}
+
+	/// Names the region of memory `address` is in
+	fn region_name(&self, address: u16) -> String {
+		match address {
+			0x0000..=0x7FFF => format!("ROM bank {:02X}", self.bank_at(address)),
+			0x8000..=0x9FFF => String::from("VRAM"),
+			0xA000..=0xBFFF => format!("ERAM bank {:02X}", self.eram),
+			0xC000..=0xDFFF => String::from("WRAM"),
+			0xE000..=0xFDFF => String::from("Echo RAM"),
+			0xFE00..=0xFE9F => String::from("OAM"),
+			0xFEA0..=0xFEFF => String::from("Unusable"),
+			0xFF00..=0xFF7F => String::from("IO"),
+			0xFF80..=0xFFFE => String::from("HRAM"),
+			0xFFFF => String::from("IE"),
+		}
+	}
+}
+
+/// A single disassembled instruction shown in the debugger
+pub struct DisassemblyLine {
+	pub address: u16,
+	pub label: Option<String>,
+	pub text: String,
 }
 
+/// How many instructions following PC are disassembled in the debugger
+const DISASSEMBLY_LENGTH: usize = 16;
+
 impl Framework {
 	pub(crate) fn new<T>(
 		event_loop: &EventLoopWindowTarget<T>,
@@ -81,7 +190,7 @@ impl Framework {
 		height: u32,
 		scale_factor: f32,
 		pixels: &pixels::Pixels,
-		gameboy: &WrappedGameboy<impl SerialWriter>,
+		gameboy: &WrappedGameboy<impl LinkDevice>,
 		sender: std::sync::mpsc::Sender<EmulatorWindowEvent>,
 	) -> Self {
 		let max_texture_size = pixels.device().limits().max_texture_dimension_2d as usize;
@@ -121,14 +230,66 @@ impl Framework {
 		self.screen_descriptor.pixels_per_point = scale_factor as f32;
 	}
 
-	pub(crate) fn prepare(&mut self, window: &Window, gameboy: &WrappedGameboy<impl SerialWriter>) {
-		self.gui.registers = gameboy.gameboy.registers;
-		self.gui.ppu_registers = gameboy.gameboy.ppu.registers;
+	pub(crate) fn prepare(&mut self, window: &Window, gameboy: &WrappedGameboy<impl LinkDevice>) {
+		self.gui.registers = gameboy.emulator.gameboy().registers;
+		self.gui.ppu_registers = gameboy.emulator.gameboy().bus.ppu.registers;
 		self.gui.is_debugging = gameboy.debugging;
-		self.gui.oam = gameboy.gameboy.ppu.oam;
-		self.gui.hram = gameboy.gameboy.memory.hram;
-		self.gui.wram = gameboy.gameboy.memory.wram;
-		self.gui.dma = gameboy.gameboy.dma;
+		self.gui.dma = gameboy.emulator.gameboy().bus.dma;
+		self.gui.bg_tile_map = gameboy.emulator.gameboy().bus.ppu.bg_tile_map();
+		self.gui.window_tile_map = gameboy.emulator.gameboy().bus.ppu.window_tile_map();
+		if self.gui.state.tiles_window_open {
+			let ppu = &gameboy.emulator.gameboy().bus.ppu;
+			let palette = match self.gui.tile_palette {
+				TilePalette::Bgp => ppu.bgp,
+				TilePalette::Obp0 => ppu.obp[0],
+				TilePalette::Obp1 => ppu.obp[1],
+				// The default OBP maps each colour id to the shade of the same value
+				TilePalette::Greyscale => Palette::new_obp(),
+			};
+			self.gui.tiles = ppu.render_tiles(&palette);
+		}
+		if self.gui.state.tile_maps_window_open {
+			self.gui.tile_maps = [
+				gameboy.emulator.gameboy().bus.ppu.render_tile_map(TileMap::Low),
+				gameboy.emulator.gameboy().bus.ppu.render_tile_map(TileMap::High),
+			];
+		}
+		if self.gui.state.io_window_open {
+			self.gui.io_registers = (0xFF00..=0xFF7F)
+				.chain([0xFFFF])
+				.filter(|address| io::register_name(*address).is_some())
+				.map(|address| (address, gameboy.emulator.gameboy().debug_read_u8(address)))
+				.collect();
+			self.gui.timer_internal_div = gameboy.emulator.gameboy().bus.timer_now().internal_div();
+			self.gui.timer_overflow_state =
+				gameboy.emulator.gameboy().bus.timer_now().overflow_state();
+		}
+		if self.gui.state.call_stack_window_open {
+			self.gui.call_stack =
+				gameboy.emulator.gameboy().call_stack.frames().rev().copied().collect();
+			self.gui.interrupt_history = gameboy.emulator.gameboy().interrupt_history.to_vec();
+		}
+		if self.gui.state.oam_window_open {
+			self.gui.sprite_height = gameboy.emulator.gameboy().bus.ppu.sprite_height() as usize;
+			self.gui.sprites = gameboy
+				.emulator
+				.gameboy()
+				.bus
+				.ppu
+				.oam_entries()
+				.into_iter()
+				.map(|entry| (entry, gameboy.emulator.gameboy().bus.ppu.render_sprite(&entry)))
+				.collect();
+		}
+		self.gui.banks = MappedBanks::new(gameboy.emulator.gameboy());
+		if self.gui.state.memory_window_open {
+			for (address, value) in self.gui.memory.iter_mut().enumerate() {
+				*value = gameboy.emulator.gameboy().debug_read_u8(address as u16);
+			}
+		}
+		if self.gui.state.debugger_window_open {
+			self.gui.disassembly = Gui::disassemble(gameboy);
+		}
 
 		// Run the egui frame and create all paint jobs to prepare for rendering.
 		let raw_input = self.egui_state.take_egui_input(window);
@@ -182,7 +343,7 @@ impl Framework {
 
 impl Gui {
 	fn new(
-		gameboy: &WrappedGameboy<impl SerialWriter>,
+		gameboy: &WrappedGameboy<impl LinkDevice>,
 		sender: std::sync::mpsc::Sender<EmulatorWindowEvent>,
 	) -> Self {
 		Self {
@@ -191,26 +352,73 @@ impl Gui {
 				register_window_open: false,
 				ppu_register_window_open: false,
 				debugger_window_open: gameboy.debugging,
-				wram_window_open: false,
-				oam_window_open: false,
-				hram_window_open: false,
+				memory_window_open: false,
 				dma_window_open: false,
+				tiles_window_open: false,
+				tile_maps_window_open: false,
+				oam_window_open: false,
+				call_stack_window_open: false,
+				io_window_open: false,
 			},
 			state_restore: None,
-			registers: gameboy.gameboy.registers,
-			ppu_registers: gameboy.gameboy.ppu.registers,
+			registers: gameboy.emulator.gameboy().registers,
+			ppu_registers: gameboy.emulator.gameboy().bus.ppu.registers,
 			bp_string: String::with_capacity(16),
 			breakpoints: [[false, false, false]; 0x10000],
+			breakpoint_banks: [None; 0x10000],
 			bp_read_checkbox: false,
 			bp_write_checkbox: false,
 			bp_execute_checkbox: false,
 			sender,
 			is_debugging: gameboy.debugging,
-			wram: gameboy.gameboy.memory.wram,
-			hram: gameboy.gameboy.memory.hram,
-			oam: gameboy.gameboy.ppu.oam,
-			dma: gameboy.gameboy.dma,
+			memory: Box::new([0; 0x10000]),
+			memory_goto_string: String::new(),
+			memory_edit_string: String::new(),
+			memory_selected: None,
+			memory_scroll_to: None,
+			dma: gameboy.emulator.gameboy().bus.dma,
+			symbols: gameboy.symbols.clone(),
+			disassembly: Vec::with_capacity(DISASSEMBLY_LENGTH),
+			banks: MappedBanks::new(gameboy.emulator.gameboy()),
+			tile_palette: TilePalette::Bgp,
+			tiles: Vec::new(),
+			tiles_texture: None,
+			tile_maps: [Vec::new(), Vec::new()],
+			tile_map_textures: [None, None],
+			bg_tile_map: gameboy.emulator.gameboy().bus.ppu.bg_tile_map(),
+			window_tile_map: gameboy.emulator.gameboy().bus.ppu.window_tile_map(),
+			sprite_height: gameboy.emulator.gameboy().bus.ppu.sprite_height() as usize,
+			sprites: Vec::new(),
+			sprite_textures: Vec::new(),
+			call_stack: Vec::new(),
+			interrupt_history: Vec::new(),
+			io_registers: Vec::new(),
+			timer_internal_div: gameboy.emulator.gameboy().bus.timer_now().internal_div(),
+			timer_overflow_state: gameboy.emulator.gameboy().bus.timer_now().overflow_state(),
+		}
+	}
+
+	fn disassemble(gameboy: &WrappedGameboy<impl LinkDevice>) -> Vec<DisassemblyLine> {
+		let symbols = &gameboy.symbols;
+		let gameboy = gameboy.emulator.gameboy();
+		let mut address = gameboy.registers.pc;
+		let mut out = Vec::with_capacity(DISASSEMBLY_LENGTH);
+
+		for _ in 0..DISASSEMBLY_LENGTH {
+			let instruction =
+				disassembler::disassemble(address, |addr| gameboy.debug_read_u8(addr));
+			let text = instruction.format_with(|target| {
+				symbols.label_at(gameboy.bank_at(target), target).map(|symbol| symbol.name.clone())
+			});
+			let label = symbols
+				.label_at(gameboy.bank_at(address), address)
+				.map(|symbol| symbol.name.clone());
+
+			out.push(DisassemblyLine { address, label, text });
+			address = address.wrapping_add(instruction.length as u16);
 		}
+
+		out
 	}
 
 	fn ui(&mut self, ctx: &Context) {
@@ -219,30 +427,38 @@ impl Gui {
 				self.state.debugger_window_open = !self.state.debugger_window_open;
 			}
 
+			if ui.button("Toggle Call Stack Window").clicked() {
+				self.state.call_stack_window_open = !self.state.call_stack_window_open;
+			}
+
 			if ui.button("Toggle Register Window").clicked() {
 				self.state.register_window_open = !self.state.register_window_open;
 			}
 
-			if ui.button("Toggle PPU Window").clicked() {
-				self.state.ppu_register_window_open = !self.state.ppu_register_window_open;
+			if ui.button("Toggle IO Registers Window").clicked() {
+				self.state.io_window_open = !self.state.io_window_open;
 			}
 
-			if ui.button("Toggle BG Tiles Window").clicked() {
+			if ui.button("Toggle PPU Window").clicked() {
 				self.state.ppu_register_window_open = !self.state.ppu_register_window_open;
 			}
 
-			if ui.button("Toggle WRAM Window").clicked() {
-				self.state.wram_window_open = !self.state.wram_window_open;
+			if ui.button("Toggle Tiles Window").clicked() {
+				self.state.tiles_window_open = !self.state.tiles_window_open;
 			}
 
-			if ui.button("Toggle HRAM Window").clicked() {
-				self.state.hram_window_open = !self.state.hram_window_open;
+			if ui.button("Toggle Tile Maps Window").clicked() {
+				self.state.tile_maps_window_open = !self.state.tile_maps_window_open;
 			}
 
 			if ui.button("Toggle OAM Window").clicked() {
 				self.state.oam_window_open = !self.state.oam_window_open;
 			}
 
+			if ui.button("Toggle Memory Window").clicked() {
+				self.state.memory_window_open = !self.state.memory_window_open;
+			}
+
 			if ui.button("Toggle DMA Window").clicked() {
 				self.state.dma_window_open = !self.state.dma_window_open;
 			}
@@ -267,23 +483,48 @@ impl Gui {
 			if ui.button("Continue").clicked() {
 				let _ = self.sender.send(EmulatorWindowEvent::Debug(EmulatorDebugEvent::Continue));
 			}
+
+			ui.heading("Disassembly");
+			Grid::new("debugger_disassembly_grid").show(ui, |ui| {
+				for line in &self.disassembly {
+					if let Some(label) = line.label.as_ref() {
+						ui.monospace(format!("{}:", label));
+						ui.end_row();
+					}
+
+					let text = format!("{:04X}:  {}", line.address, line.text);
+					match line.address == self.registers.pc {
+						true => ui.monospace(RichText::new(text).color(Color32::LIGHT_GREEN)),
+						false => ui.monospace(text),
+					};
+					ui.end_row();
+				}
+			});
+
 			ui.label("Toggle Breakpoint");
 			ui.text_edit_singleline(&mut self.bp_string);
-			self.bp_string.retain(|x| x.is_ascii_hexdigit());
-			if let Some((fourth_index, _)) = self.bp_string.char_indices().nth(4) {
-				self.bp_string.truncate(fourth_index);
-			}
 			Grid::new("debugger_bp_select_grid").show(ui, |ui| {
-				let address = u16::from_str_radix(self.bp_string.as_str(), 16).unwrap_or_default();
-				ui.label(format!("({:#X}) ", address));
+				let (bank, address) = match parse_address(&self.symbols, self.bp_string.as_str()) {
+					Ok(location) => location,
+					Err(why) => {
+						ui.label(RichText::new(why).color(Color32::LIGHT_RED));
+						return;
+					}
+				};
+				ui.label(format!(
+					"({}) ",
+					describe_breakpoint(&self.symbols, self.banks, bank, address)
+				));
 				let [read, write, execute] = &mut self.breakpoints[address as usize];
 				let mut changed = ui.checkbox(read, "Read").clicked();
 				changed |= ui.checkbox(write, "Write").clicked();
 				changed |= ui.checkbox(execute, "Execute").clicked();
 				if changed {
+					self.breakpoint_banks[address as usize] = bank;
 					let _ = self.sender.send(EmulatorWindowEvent::Debug(
 						EmulatorDebugEvent::ToggleBreakpoint(
 							address,
+							bank,
 							self.breakpoints[address as usize],
 						),
 					));
@@ -295,7 +536,11 @@ impl Gui {
 				ui.end_row();
 				for (idx, [read, write, execute]) in self.breakpoints.iter_mut().enumerate() {
 					if *read || *write || *execute {
-						ui.label(format!("{:#X}: ", idx));
+						let bank = self.breakpoint_banks[idx];
+						ui.label(format!(
+							"{}: ",
+							describe_breakpoint(&self.symbols, self.banks, bank, idx as u16)
+						));
 						let mut changed = ui.checkbox(read, "Read").clicked();
 						changed |= ui.checkbox(write, "Write").clicked();
 						changed |= ui.checkbox(execute, "Execute").clicked();
@@ -303,6 +548,7 @@ impl Gui {
 							let _ = self.sender.send(EmulatorWindowEvent::Debug(
 								EmulatorDebugEvent::ToggleBreakpoint(
 									idx as u16,
+									bank,
 									[*read, *write, *execute],
 								),
 							));
@@ -336,16 +582,261 @@ impl Gui {
 			},
 		);
 
-		egui::Window::new("WRAM").vscroll(true).open(&mut self.state.wram_window_open).show(ctx, |ui| {
-			egui::Grid::new("memory_ov_wram").show(ui, |ui| {
-				ui.monospace("      00 01 02 03 04 05 06 07 08 09 0A 0B 0C 0D 0E 0F");
-				for mem_row_idx in 0xC00..(0xE00) {
-					let row_base = (mem_row_idx * 0x10) - 0xC000;
+		egui::Window::new("Tiles").vscroll(true).open(&mut self.state.tiles_window_open).show(
+			ctx,
+			|ui| {
+				egui::ComboBox::from_label("Palette")
+					.selected_text(format!("{:?}", self.tile_palette))
+					.show_ui(ui, |ui| {
+						ui.selectable_value(&mut self.tile_palette, TilePalette::Bgp, "BGP");
+						ui.selectable_value(&mut self.tile_palette, TilePalette::Obp0, "OBP0");
+						ui.selectable_value(&mut self.tile_palette, TilePalette::Obp1, "OBP1");
+						ui.selectable_value(
+							&mut self.tile_palette,
+							TilePalette::Greyscale,
+							"Greyscale",
+						);
+					});
+
+				if self.tiles.is_empty() {
+					return;
+				}
+
+				let size = [TILE_SHEET_WIDTH * 8, (TILE_COUNT / TILE_SHEET_WIDTH) * 8];
+				let texture =
+					update_texture(ctx, &mut self.tiles_texture, "tiles", size, &self.tiles);
+				let response = ui.image(texture.id(), texture.size_vec2() * TILE_VIEWER_SCALE);
+
+				if let Some(position) = response.hover_pos() {
+					let offset = (position - response.rect.min) / (8.0 * TILE_VIEWER_SCALE);
+					let tile = (offset.y as usize * TILE_SHEET_WIDTH) + offset.x as usize;
+					if tile < TILE_COUNT {
+						response.on_hover_text(format!(
+							"Tile {:#05X} at {:#06X}",
+							tile,
+							0x8000 + (tile * 16)
+						));
+					}
+				}
+			},
+		);
+
+		egui::Window::new("Tile Maps")
+			.vscroll(true)
+			.open(&mut self.state.tile_maps_window_open)
+			.show(ctx, |ui| {
+				if self.tile_maps.iter().any(Vec::is_empty) {
+					return;
+				}
+
+				for (idx, map) in [TileMap::Low, TileMap::High].into_iter().enumerate() {
+					let mut users = Vec::new();
+					if self.bg_tile_map == map {
+						users.push("BG");
+					}
+					if self.window_tile_map == map {
+						users.push("Window");
+					}
+					ui.heading(format!("{:#06X} {}", map.base_address(), users.join(", ")));
+
+					let texture = update_texture(
+						ctx,
+						&mut self.tile_map_textures[idx],
+						["tile_map_low", "tile_map_high"][idx],
+						[TILE_MAP_SIZE, TILE_MAP_SIZE],
+						&self.tile_maps[idx],
+					);
+					let response = ui.image(texture.id(), texture.size_vec2() * TILE_VIEWER_SCALE);
+					let painter = ui.painter_at(response.rect);
+
+					if self.bg_tile_map == map {
+						// The viewport wraps around the edges of the tile map so it is drawn
+						// once for every edge it could wrap over
+						let origin =
+							Vec2::new(self.ppu_registers.scx as f32, self.ppu_registers.scy as f32);
+						for wrap in [
+							Vec2::ZERO,
+							Vec2::new(-256.0, 0.0),
+							Vec2::new(0.0, -256.0),
+							Vec2::splat(-256.0),
+						] {
+							painter.rect_stroke(
+								map_rect(response.rect, origin + wrap, Vec2::new(160.0, 144.0)),
+								0.0,
+								Stroke::new(1.0_f32, Color32::LIGHT_RED),
+							);
+						}
+					}
+
+					if self.window_tile_map == map && (self.ppu_registers.lcdc >> 5) & 0b1 == 1 {
+						let size = Vec2::new(
+							167.0 - self.ppu_registers.wx as f32,
+							144.0 - self.ppu_registers.wy as f32,
+						);
+						if size.x > 0.0 && size.y > 0.0 {
+							painter.rect_stroke(
+								map_rect(response.rect, Vec2::ZERO, size),
+								0.0,
+								Stroke::new(1.0_f32, Color32::LIGHT_BLUE),
+							);
+						}
+					}
+				}
+			});
+
+		egui::Window::new("OAM").vscroll(true).open(&mut self.state.oam_window_open).show(
+			ctx,
+			|ui| {
+				if self.sprite_textures.len() != self.sprites.len() {
+					self.sprite_textures.clear();
+				}
+
+				Grid::new("oam_entry_grid").striped(true).show(ui, |ui| {
+					for heading in ["#", "Sprite", "X", "Y", "Tile", "Flip", "Priority", "Palette"]
+					{
+						ui.label(heading);
+					}
 					ui.end_row();
-					ui.monospace(format!("{:X}: {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X}", mem_row_idx * 0x10, self.wram[row_base + 0], self.wram[row_base + 1], self.wram[row_base + 2], self.wram[row_base + 3], self.wram[row_base + 4], self.wram[row_base + 5], self.wram[row_base + 6], self.wram[row_base + 7], self.wram[row_base + 8], self.wram[row_base + 9], self.wram[row_base + 10], self.wram[row_base + 11], self.wram[row_base + 12], self.wram[row_base + 13], self.wram[row_base + 14], self.wram[row_base + 15]));
+
+					for (idx, (entry, rgba)) in self.sprites.iter().enumerate() {
+						let image =
+							ColorImage::from_rgba_unmultiplied([8, self.sprite_height], rgba);
+						match self.sprite_textures.get_mut(idx) {
+							Some(texture) => texture.set(image, TextureOptions::NEAREST),
+							None => self.sprite_textures.push(ctx.load_texture(
+								format!("sprite_{}", idx),
+								image,
+								TextureOptions::NEAREST,
+							)),
+						}
+						let texture = &self.sprite_textures[idx];
+
+						ui.label(format!("{}", idx));
+						ui.image(texture.id(), texture.size_vec2() * SPRITE_PREVIEW_SCALE);
+						ui.monospace(format!("{:3}", entry.x as i16 - 8));
+						ui.monospace(format!("{:3}", entry.y as i16 - 16));
+						ui.monospace(format!("{:02X}", entry.tile_idx));
+						ui.label(match (entry.x_flip(), entry.y_flip()) {
+							(false, false) => "-",
+							(true, false) => "X",
+							(false, true) => "Y",
+							(true, true) => "XY",
+						});
+						ui.label(match entry.covered_by_bg_window() {
+							true => "Behind BG",
+							false => "Above BG",
+						});
+						ui.label(format!("OBP{}", entry.palette_number()));
+						ui.end_row();
+					}
+				});
+			},
+		);
+
+		egui::Window::new("Call Stack")
+			.vscroll(true)
+			.open(&mut self.state.call_stack_window_open)
+			.show(ctx, |ui| {
+				ui.heading("Call Stack");
+				if self.call_stack.is_empty() {
+					ui.label("Empty");
 				}
+				Grid::new("call_stack_grid").striped(true).show(ui, |ui| {
+					for frame in &self.call_stack {
+						ui.monospace(describe_address(&self.symbols, self.banks, frame.target));
+						ui.monospace(format!("{}", frame));
+						ui.end_row();
+					}
+				});
+
+				ui.heading("Interrupt History");
+				Grid::new("interrupt_history_grid").striped(true).show(ui, |ui| {
+					for record in self.interrupt_history.iter().rev() {
+						ui.monospace(format!("{}", record));
+						ui.end_row();
+					}
+				});
 			});
-		});
+
+		egui::Window::new("IO Registers").vscroll(true).open(&mut self.state.io_window_open).show(
+			ctx,
+			|ui| {
+				ui.heading("Timer");
+				ui.monospace(format!("Internal DIV: {:#06X}", self.timer_internal_div));
+				ui.monospace(match self.timer_overflow_state {
+					0 => String::from("TIMA Overflow: None"),
+					4 => String::from("TIMA Overflow: Reloaded from TMA"),
+					state => format!("TIMA Overflow: Reload pending ({}/3)", state),
+				});
+
+				ui.heading("DMA");
+				match self.dma.in_progress() {
+					Some(bus) => ui.monospace(format!(
+						"Active ({:?} Bus), {:#04X} bytes remaining from {:#06X}",
+						bus,
+						self.dma.remaining_cycles,
+						(self.dma.base as u16) << 8
+					)),
+					None => ui.monospace("Inactive"),
+				};
+
+				ui.heading("Registers");
+				if !self.is_debugging {
+					ui.label("Pause to edit");
+				}
+
+				for &(address, value) in &self.io_registers {
+					let name = io::register_name(address).unwrap_or_default();
+					let fields = io::register_fields(address);
+					let mut new_value = value;
+
+					egui::CollapsingHeader::new(
+						RichText::new(format!("{:04X} {:<5} {:02X}", address, name, value))
+							.monospace(),
+					)
+					.id_source(("io_register", address))
+					.show(ui, |ui| {
+						Grid::new(("io_register_fields", address)).show(ui, |ui| {
+							if fields.is_empty() {
+								ui.label("Value");
+								ui.monospace(format!("{:#04X}", value));
+								if self.is_debugging {
+									ui.add(
+										egui::DragValue::new(&mut new_value)
+											.hexadecimal(2, false, true),
+									);
+								}
+								ui.end_row();
+							}
+
+							for field in fields {
+								ui.label(field.name);
+								ui.monospace(field.describe(value));
+								if self.is_debugging {
+									let mut field_value = field.get(value);
+									if ui
+										.add(
+											egui::DragValue::new(&mut field_value)
+												.clamp_range(0..=field.max()),
+										)
+										.changed()
+									{
+										new_value = field.set(new_value, field_value);
+									}
+								}
+								ui.end_row();
+							}
+						});
+					});
+
+					if new_value != value {
+						let _ = self.sender.send(EmulatorWindowEvent::Debug(
+							EmulatorDebugEvent::WriteMemory(address, new_value),
+						));
+					}
+				}
+			},
+		);
 
 		egui::Window::new("DMA").vscroll(true).open(&mut self.state.dma_window_open).show(
 			ctx,
@@ -367,34 +858,175 @@ impl Gui {
 			},
 		);
 
-		egui::Window::new("HRAM").vscroll(true).open(&mut self.state.hram_window_open).show(ctx, |ui| {
-			egui::Grid::new("memory_ov_hram").show(ui, |ui| {
-				ui.label("ROW: 00 01 02 03 04 05 06 07 08 09 0A 0B 0C 0D 0E 0F");
-				for mem_row_idx in 0x0..0xA {
-					let row_base = mem_row_idx * 0x10;
-					ui.end_row();
-					ui.monospace(format!("{:X}: {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X}", 0xFF80 + row_base, self.hram[row_base + 0], self.hram[row_base + 1], self.hram[row_base + 2], self.hram[row_base + 3], self.hram[row_base + 4], self.hram[row_base + 5], self.hram[row_base + 6], self.hram[row_base + 7], self.hram[row_base + 8], self.hram[row_base + 9], self.hram[row_base + 10], self.hram[row_base + 11], self.hram[row_base + 12], self.hram[row_base + 13], self.hram[row_base + 14], self.hram[row_base + 15]));
+		egui::Window::new("Memory").open(&mut self.state.memory_window_open).show(ctx, |ui| {
+			ui.horizontal(|ui| {
+				ui.label("Go to");
+				let response = ui.text_edit_singleline(&mut self.memory_goto_string);
+				if response.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter)) {
+					if let Ok((_, address)) = parse_address(&self.symbols, &self.memory_goto_string)
+					{
+						self.memory_scroll_to = Some(address);
+						self.memory_selected = Some(address);
+						self.memory_edit_string = format!("{:02X}", self.memory[address as usize]);
+					}
 				}
-
-				let row_base = 0xA0;
-				ui.end_row();
-				ui.monospace(format!("FFF0: {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} ??", self.hram[row_base + 0], self.hram[row_base + 1], self.hram[row_base + 2], self.hram[row_base + 3], self.hram[row_base + 4], self.hram[row_base + 5], self.hram[row_base + 6], self.hram[row_base + 7], self.hram[row_base + 8], self.hram[row_base + 9], self.hram[row_base + 10], self.hram[row_base + 11], self.hram[row_base + 12], self.hram[row_base + 13], self.hram[row_base + 14]));
 			});
-		});
 
-		egui::Window::new("OAM").vscroll(true).open(&mut self.state.oam_window_open).show(ctx, |ui| {
-			egui::Grid::new("memory_ov_oam").show(ui, |ui| {
-				ui.label("ROW: 00 01 02 03 04 05 06 07 08 09 0A 0B 0C 0D 0E 0F");
-				for mem_row_idx in 0xC00..(0xC0A) {
-					let row_base = (mem_row_idx * 0x10) - 0xC000;
-					ui.end_row();
-					ui.label(format!("{:X}: {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X}", mem_row_idx * 0x10, self.hram[row_base + 0], self.hram[row_base + 1], self.hram[row_base + 2], self.hram[row_base + 3], self.hram[row_base + 4], self.hram[row_base + 5], self.hram[row_base + 6], self.hram[row_base + 7], self.hram[row_base + 8], self.hram[row_base + 9], self.hram[row_base + 10], self.hram[row_base + 11], self.hram[row_base + 12], self.hram[row_base + 13], self.hram[row_base + 14], self.hram[row_base + 15]));
-				}
+			if let Some(address) = self.memory_selected {
+				ui.horizontal(|ui| {
+					ui.monospace(format!(
+						"{} [{}{}]",
+						describe_address(&self.symbols, self.banks, address),
+						self.banks.region_name(address),
+						io::register_name(address)
+							.map(|name| format!(" {}", name))
+							.unwrap_or_default()
+					));
 
-				let row_base = 0xA0;
-				ui.end_row();
-				ui.label(format!("CF00: {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} {:02X} ??", self.hram[row_base + 0], self.hram[row_base + 1], self.hram[row_base + 2], self.hram[row_base + 3], self.hram[row_base + 4], self.hram[row_base + 5], self.hram[row_base + 6], self.hram[row_base + 7], self.hram[row_base + 8], self.hram[row_base + 9], self.hram[row_base + 10], self.hram[row_base + 11], self.hram[row_base + 12], self.hram[row_base + 13], self.hram[row_base + 14]));
+					if !self.is_debugging {
+						ui.label("Pause to edit");
+						return;
+					}
+
+					let response = ui.add(
+						egui::TextEdit::singleline(&mut self.memory_edit_string)
+							.char_limit(2)
+							.desired_width(24.0),
+					);
+					self.memory_edit_string.retain(|x| x.is_ascii_hexdigit());
+					if response.lost_focus()
+						&& ui.input(|input| input.key_pressed(egui::Key::Enter))
+					{
+						if let Ok(value) = u8::from_str_radix(&self.memory_edit_string, 16) {
+							let _ = self.sender.send(EmulatorWindowEvent::Debug(
+								EmulatorDebugEvent::WriteMemory(address, value),
+							));
+						}
+					}
+				});
+			}
+
+			ui.separator();
+
+			let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+			let mut scroll_area = egui::ScrollArea::vertical().auto_shrink([false, false]);
+			if let Some(address) = self.memory_scroll_to.take() {
+				scroll_area = scroll_area.vertical_scroll_offset(
+					(address >> 4) as f32 * (row_height + ui.spacing().item_spacing.y),
+				);
+			}
+
+			scroll_area.show_rows(ui, row_height, 0x1000, |ui, rows| {
+				for row in rows {
+					let row_base = (row * 0x10) as u16;
+					let bytes = &self.memory[row_base as usize..row_base as usize + 0x10];
+
+					ui.horizontal(|ui| {
+						ui.monospace(format!("{:04X}:", row_base));
+
+						for (address, value) in (row_base..).zip(bytes.iter().copied()) {
+							let mut text = RichText::new(format!("{:02X}", value)).monospace();
+							if self.memory_selected == Some(address) {
+								text = text.color(Color32::LIGHT_GREEN);
+							}
+
+							let mut response =
+								ui.add(egui::Label::new(text).sense(egui::Sense::click()));
+							if let Some(name) = io::register_name(address) {
+								response = response.on_hover_text(name);
+							}
+
+							if response.clicked() {
+								self.memory_selected = Some(address);
+								self.memory_edit_string = format!("{:02X}", value);
+							}
+						}
+
+						ui.monospace(
+							bytes
+								.iter()
+								.map(|&x| match x.is_ascii_graphic() || x == b' ' {
+									true => x as char,
+									false => '.',
+								})
+								.collect::<String>(),
+						);
+						ui.monospace(self.banks.region_name(row_base));
+					});
+				}
 			});
 		});
 	}
 }
+
+/// Parses an address entered in a text box, which is either a hex address
+/// (optionally prefixed with `$` or `0x`) or a symbol name. Symbols in banked
+/// regions also return the bank they are in
+fn parse_address(symbols: &SymbolTable, input: &str) -> Result<(Option<u16>, u16), String> {
+	let input = input.trim();
+	let hex = input.strip_prefix('$').or_else(|| input.strip_prefix("0x")).unwrap_or(input);
+
+	if input.is_empty() {
+		return Err(String::from("Enter an address or label"));
+	}
+
+	// Labels are checked first as names such as `Add` are also valid hex
+	match symbols.lookup(input) {
+		Some(symbol) => match symbol.address {
+			0x0000..=0x7FFF | 0xA000..=0xBFFF => Ok((Some(symbol.bank), symbol.address)),
+			_ => Ok((None, symbol.address)),
+		},
+		None => u16::from_str_radix(hex, 16)
+			.map(|address| (None, address))
+			.map_err(|_| format!("Unknown symbol {:?}", input)),
+	}
+}
+
+/// Formats an address along with the nearest label to it, resolving banked
+/// addresses using the currently mapped banks
+fn describe_address(symbols: &SymbolTable, banks: MappedBanks, address: u16) -> String {
+	match symbols.describe(banks.bank_at(address), address) {
+		Some(label) => format!("{:#06X} {}", address, label),
+		None => format!("{:#06X}", address),
+	}
+}
+
+/// Formats the location of a breakpoint, which is either in a specific bank or
+/// in whichever is mapped in
+fn describe_breakpoint(
+	symbols: &SymbolTable,
+	banks: MappedBanks,
+	bank: Option<u16>,
+	address: u16,
+) -> String {
+	match bank {
+		Some(bank) => match symbols.describe(bank, address) {
+			Some(label) => format!("{:02X}:{:04X} {}", bank, address, label),
+			None => format!("{:02X}:{:04X}", bank, address),
+		},
+		None => describe_address(symbols, banks, address),
+	}
+}
+
+/// Uploads `rgba` to the texture in `handle`, creating it if needed
+fn update_texture<'a>(
+	ctx: &Context,
+	handle: &'a mut Option<TextureHandle>,
+	name: &str,
+	size: [usize; 2],
+	rgba: &[u8],
+) -> &'a TextureHandle {
+	let image = ColorImage::from_rgba_unmultiplied(size, rgba);
+
+	match handle {
+		Some(texture) => texture.set(image, TextureOptions::NEAREST),
+		None => *handle = Some(ctx.load_texture(name, image, TextureOptions::NEAREST)),
+	}
+
+	handle.as_ref().unwrap()
+}
+
+/// Maps a rectangle in tile map pixels onto the tile map image drawn at `image`
+fn map_rect(image: Rect, origin: Vec2, size: Vec2) -> Rect {
+	Rect::from_min_size(image.min + (origin * TILE_VIEWER_SCALE), size * TILE_VIEWER_SCALE)
+}